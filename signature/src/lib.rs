@@ -871,6 +871,24 @@ fn signature_real(metadata: TokenStream, input: TokenStream) -> SignatureResult<
                     named_matchers.extend(mappings);
                 }
 
+                let description_value = match &description {
+                    Some(d) => {
+                        let d = Literal::string(d);
+                        quote! {Some(#d.to_string())}
+                    }
+                    None => quote! {None},
+                };
+                let default_value_string = match &default_value {
+                    Some(d) => {
+                        let d = Literal::string(&match d {
+                            TokenTree::Group(g) => g.stream().to_string(),
+                            d => d.to_string(),
+                        });
+                        quote! {Some(#d.to_string())}
+                    }
+                    None => quote! {None},
+                };
+
                 let default_help = if let Some(d) = &default_value {
                     format!(" {}", d.to_string())
                 } else {
@@ -910,7 +928,8 @@ fn signature_real(metadata: TokenStream, input: TokenStream) -> SignatureResult<
                         name: #name_string.to_string(),
                         value_type: #crush_internal_type,
                         allowed: #allowed_values,
-                        description: None,
+                        description: #description_value,
+                        default: #default_value_string,
                         complete: #completion_command,
                         named: false,
                         unnamed: false,
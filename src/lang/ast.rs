@@ -650,7 +650,7 @@ impl TokenListNode {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum TokenType {
     LogicalOperator,
     UnaryOperator,
@@ -2,6 +2,7 @@ use crate::lang::argument::{Argument, ArgumentDefinition, ArgumentType};
 use crate::lang::command::{BoundCommand, Command, CrushCommand, OutputType, Parameter, ArgumentDescription};
 use crate::lang::command_invocation::CommandInvocation;
 use crate::lang::data::dict::Dict;
+use crate::lang::debugger;
 use crate::lang::errors::{argument_error_legacy, error, mandate, CrushResult};
 use crate::lang::execution_context::{CompileContext, CommandContext, JobContext};
 use crate::lang::help::Help;
@@ -33,6 +34,7 @@ impl CrushCommand for Closure {
         let job_definitions = self.job_definitions.clone();
         let parent_env = self.env.clone();
         let env = parent_env.create_child(&context.scope, false);
+        env.set_frame(self.frame());
 
         let mut cc = context.compile_context().with_scope(&env);
         if let Some(this) = context.this {
@@ -58,6 +60,8 @@ impl CrushCommand for Closure {
                 black_hole()
             };
 
+            debugger::before_job(job_definition, &env, &context.global_state)?;
+
             let job = job_definition.invoke(JobContext::new(
                 input,
                 output,
@@ -570,6 +574,20 @@ impl Closure {
         }
     }
 
+    /**
+        A short description of this closure and its location in the source, used in backtraces.
+    */
+    fn frame(&self) -> String {
+        let name = self.name.as_ref().map(|n| n.string.clone()).unwrap_or_else(|| "<closure>".to_string());
+        match (self.job_definitions.first(), self.job_definitions.last()) {
+            (Some(first), Some(last)) => {
+                let location = first.location().union(last.location());
+                format!("{} {}-{}", name, location.start, location.end)
+            }
+            _ => name,
+        }
+    }
+
     fn push_arguments_to_env(
         signature: &Option<Vec<Parameter>>,
         mut arguments: Vec<Argument>,
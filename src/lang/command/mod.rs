@@ -52,6 +52,7 @@ pub struct ArgumentDescription {
     pub value_type: ValueType,
     pub allowed: Option<Vec<Value>>,
     pub description: Option<String>,
+    pub default: Option<String>,
     pub complete: Option<fn(
         cmd: &PartialCommandResult,
         cursor: usize,
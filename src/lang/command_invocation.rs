@@ -1,7 +1,7 @@
 use crate::lang::errors::{error, CrushResult, CrushErrorType};
 use crate::lang::execution_context::{CompileContext, JobContext};
 use crate::lang::data::scope::Scope;
-use crate::lang::{argument::ArgumentDefinition, argument::ArgumentVecCompiler, value::Value};
+use crate::lang::{argument::Argument, argument::ArgumentDefinition, argument::ArgumentVecCompiler, value::Value};
 use crate::lang::command::Command;
 use crate::lang::execution_context::CommandContext;
 use crate::lang::value::ValueDefinition;
@@ -9,7 +9,7 @@ use std::ops::Deref;
 use std::path::PathBuf;
 use std::fmt::{Display, Formatter};
 use std::thread::ThreadId;
-use crate::lang::ast::Location;
+use crate::util::escape::escape;
 
 #[derive(Clone)]
 pub struct CommandInvocation {
@@ -17,7 +17,7 @@ pub struct CommandInvocation {
     arguments: Vec<ArgumentDefinition>,
 }
 
-fn resolve_external_command(name: &str, env: &Scope) -> CrushResult<Option<PathBuf>> {
+pub fn resolve_external_command(name: &str, env: &Scope) -> CrushResult<Option<PathBuf>> {
     if let Some(Value::List(path)) = env.get("cmd_path")? {
         let path_vec = path.dump();
         for val in path_vec {
@@ -126,29 +126,34 @@ impl CommandInvocation {
             .command
             .compile_internal(&mut context.compile_context(), false)
         {
-            Ok((this, value)) => invoke_value(this, value, self.arguments.clone(), context, self.command.location()),
+            Ok((this, value)) => invoke_value(this, value, self.arguments.clone(), context, &self.command),
             Err(err) => {
                 if err.is(CrushErrorType::BlockError) {
                     let cmd = self.command.clone();
                     let arguments = self.arguments.clone();
                     let t = context.global_state.threads().clone();
-                    let location = self.command.location();
                     Ok(Some(t.spawn(
                         &self.command.to_string(),
                         move || {
                             match cmd.clone().compile_unbound(&mut context.compile_context()) {
-                                Ok((this, value)) => context.global_state.printer().handle_error(invoke_value(
-                                    this,
-                                    value,
-                                    arguments,
-                                    context.clone(),
-                                    location,
+                                Ok((this, value)) => context.global_state.printer().handle_error(trap_error(
+                                    invoke_value(
+                                        this,
+                                        value,
+                                        arguments,
+                                        context.clone(),
+                                        &cmd,
+                                    ).map(|_| ()),
+                                    &context.env,
                                 )),
 
-                                _ => context.global_state.printer().handle_error(try_external_command(
-                                    cmd,
-                                    arguments,
-                                    context.clone(),
+                                _ => context.global_state.printer().handle_error(trap_error(
+                                    try_external_command(
+                                        cmd,
+                                        arguments,
+                                        context.clone(),
+                                    ).map(|_| ()),
+                                    &context.env,
                                 )),
                             }
                             Ok(())
@@ -167,10 +172,11 @@ fn invoke_value(
     value: Value,
     local_arguments: Vec<ArgumentDefinition>,
     context: JobContext,
-    location: Location,
+    definition: &ValueDefinition,
 ) -> CrushResult<Option<ThreadId>> {
+    let location = definition.location();
     match value {
-        Value::Command(command) => invoke_command(command, this, local_arguments, context),
+        Value::Command(command) => invoke_command(command, this, local_arguments, context, definition),
         Value::File(f) => {
             if local_arguments.len() == 0 {
                 let meta = f.metadata();
@@ -185,6 +191,7 @@ fn invoke_value(
                             location,
                         ))],
                         context,
+                        definition,
                     )
                 } else {
                     invoke_command(
@@ -195,6 +202,7 @@ fn invoke_value(
                             location,
                         ))],
                         context,
+                        definition,
                     )
                 }
             } else {
@@ -216,17 +224,19 @@ fn invoke_value(
                     location,
                 ))],
                 context,
+                definition,
             ),
             Some(call) => invoke_command(
                 call.as_ref().copy(),
                 Some(Value::Type(t)),
                 local_arguments,
                 context,
+                definition,
             ),
         },
         Value::Struct(s) => match s.get("__call__") {
             Some(Value::Command(call)) => {
-                invoke_command(call, Some(Value::Struct(s)), local_arguments, context)
+                invoke_command(call, Some(Value::Struct(s)), local_arguments, context, definition)
             }
             Some(v) => error(
                 format!(
@@ -245,6 +255,7 @@ fn invoke_value(
                             location,
                         ))],
                         context,
+                        definition,
                     )
                 } else {
                     error(
@@ -264,6 +275,7 @@ fn invoke_value(
                     None,
                     vec![ArgumentDefinition::unnamed(ValueDefinition::Value(value, location))],
                     context,
+                    definition,
                 )
             } else {
                 error(&format!("Not a command {}", value))
@@ -277,27 +289,89 @@ fn invoke_command(
     this: Option<Value>,
     local_arguments: Vec<ArgumentDefinition>,
     context: JobContext,
+    definition: &ValueDefinition,
 ) -> CrushResult<Option<ThreadId>> {
     if !action.can_block(&local_arguments, &mut context.compile_context())
         && !arg_can_block(&local_arguments, &mut context.compile_context())
     {
         let new_context =
             CommandInvocation::execution_context(local_arguments, this, context.clone())?;
-        context.global_state.printer().handle_error(action.invoke(new_context));
+        trace(definition, &new_context);
+        context.global_state.printer().handle_error(trap_error(action.invoke(new_context), &context.env));
         Ok(None)
     } else {
         let t = context.global_state.threads().clone();
         let name = action.name().to_string();
+        let definition = definition.clone();
         Ok(Some(t.spawn(
             &name,
             move || {
-                let res = CommandInvocation::execution_context(local_arguments, this, context.clone())?;
-                action.invoke(res)
+                let res = CommandInvocation::execution_context(local_arguments, this, context.clone())
+                    .and_then(|res| {
+                        trace(&definition, &res);
+                        action.invoke(res)
+                    });
+                trap_error(res, &context.env)
             },
         )?))
     }
 }
 
+/**
+    Hand errors to the nearest error trapping scope, if any, so that e.g. control:retry can tell that
+    a command failed. Errors that aren't trapped are returned as is.
+*/
+fn trap_error(result: CrushResult<()>, env: &Scope) -> CrushResult<()> {
+    match result {
+        Err(err) if !err.is(CrushErrorType::SendError) => match env.report_error(err) {
+            Some(err) => Err(err),
+            None => Ok(()),
+        },
+        res => res,
+    }
+}
+
+/**
+    If execution tracing is turned on, print the command that is about to be invoked, along with
+    its fully evaluated arguments.
+*/
+fn trace(definition: &ValueDefinition, context: &CommandContext) {
+    if context.global_state.trace() {
+        context.global_state.printer().trace(&format_trace(
+            context.scope.depth(),
+            definition,
+            &context.arguments,
+        ));
+    }
+}
+
+fn format_trace_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => escape(s),
+        v => v.to_string(),
+    }
+}
+
+fn format_trace(depth: usize, definition: &ValueDefinition, arguments: &[Argument]) -> String {
+    let location = definition.location();
+    let mut res = format!(
+        "{} {}-{} {}",
+        "+".repeat(depth + 1),
+        location.start,
+        location.end,
+        definition,
+    );
+    for a in arguments {
+        res.push(' ');
+        if let Some(name) = &a.argument_type {
+            res.push_str(name);
+            res.push('=');
+        }
+        res.push_str(&format_trace_value(&a.value));
+    }
+    res
+}
+
 fn try_external_command(
     def: ValueDefinition,
     mut arguments: Vec<ArgumentDefinition>,
@@ -351,3 +425,26 @@ impl Display for CommandInvocation {
         self.command.fmt(f)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lang::ast::{Location, TrackedString};
+
+    #[test]
+    fn check_trace_format() {
+        let definition = ValueDefinition::Label(TrackedString::from("echo", Location::new(4, 8)));
+        let arguments = vec![
+            Argument::unnamed(Value::string("hello"), Location::new(9, 16)),
+            Argument::named("count", Value::Integer(3), Location::new(17, 24)),
+        ];
+        assert_eq!(
+            format_trace(0, &definition, &arguments),
+            "+ 4-8 echo \"hello\" count=3".to_string()
+        );
+        assert_eq!(
+            format_trace(2, &definition, &vec![]),
+            "+++ 4-8 echo".to_string()
+        );
+    }
+}
@@ -7,16 +7,20 @@ use crate::lang::value::{ValueType, Value};
 use crate::util::directory_lister::DirectoryLister;
 use std::path::PathBuf;
 use crate::lang::completion::parse::{ParseResult, CompletionCommand, LastArgument, parse, PartialCommandResult};
+use crate::lang::data::table::ColumnType;
 use nix::NixPath;
 use crate::lang::command::ArgumentDescription;
 use crate::util::escape::escape_without_quotes;
 use crate::lang::parser::Parser;
+use crate::util::glob::Glob;
+use regex::Regex;
 
 pub mod parse;
 
 pub struct Completion {
     completion: String,
     display: String,
+    start: usize,
     position: usize,
 }
 
@@ -29,6 +33,25 @@ impl Completion {
         Completion {
             completion: completion.into(),
             display: display.into(),
+            start: position,
+            position,
+        }
+    }
+
+    /**
+    Create a completion that replaces the part of the line between start and position instead of
+    just inserting text at the position.
+    */
+    pub fn replacing(
+        completion: impl Into<String>,
+        display: impl Into<String>,
+        start: usize,
+        position: usize,
+    ) -> Completion {
+        Completion {
+            completion: completion.into(),
+            display: display.into(),
+            start,
             position,
         }
     }
@@ -38,10 +61,14 @@ impl Completion {
         line: &str,
     ) -> String {
         let mut res = line.to_string();
-        res.insert_str(self.position, &self.completion);
+        res.replace_range(self.start..self.position, &self.completion);
         res
     }
 
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
     pub fn display(&self) -> &str {
         &self.display
     }
@@ -124,12 +151,49 @@ fn complete_label(
                 &k[prefix.len()..],
                 completion_suffix(value.field(k), t)),
             display: k.clone(),
+            start: cursor,
             position: cursor,
         })
         .collect());
     Ok(())
 }
 
+fn complete_parameter(
+    parameters: &[ColumnType],
+    prefix: &str,
+    t: &ValueType,
+    cursor: usize,
+    out: &mut Vec<Completion>,
+) {
+    out.append(&mut parameters
+        .iter()
+        .filter(|p| p.name.starts_with(prefix))
+        .filter(|p| t.is_compatible_with(&p.cell_type) || p.cell_type == ValueType::Any)
+        .map(|p| Completion::new(&p.name[prefix.len()..], p.name.clone(), cursor))
+        .collect());
+}
+
+fn complete_column(
+    columns: &[ColumnType],
+    prefix: &str,
+    add_caret: bool,
+    t: &ValueType,
+    cursor: usize,
+    out: &mut Vec<Completion>,
+) {
+    if !t.is_compatible_with(&ValueType::Field) {
+        return;
+    }
+    out.append(&mut columns
+        .iter()
+        .filter(|c| c.name.starts_with(prefix))
+        .map(|c| Completion::new(
+            format!("{}{} ", if add_caret { "^" } else { "" }, &c.name[prefix.len()..]),
+            format!("^{}", c.name),
+            cursor))
+        .collect());
+}
+
 fn complete_file(
     lister: &impl DirectoryLister,
     prefix: impl Into<PathBuf>,
@@ -172,6 +236,7 @@ fn complete_file(
                     },
                 ),
                 display: k.name.to_str().unwrap().to_string(),
+                start: cursor,
                 position: cursor,
             })
             .collect());
@@ -179,6 +244,100 @@ fn complete_file(
     Ok(())
 }
 
+fn quote_file(name: &str) -> String {
+    let plain = name.chars().next().map(|c| c.is_ascii_alphabetic() || c == '_' || c == '.' || c == '/').unwrap_or(false)
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '/');
+    if plain {
+        name.to_string()
+    } else {
+        format!("'{}'", escape_without_quotes(name))
+    }
+}
+
+/**
+Offer every file matching a pattern as a completion that replaces the pattern. If the pattern
+matches more than one file, the full expansion is also offered, so that the pattern can be
+replaced inline by all of the files it matches.
+*/
+fn complete_pattern_matches(
+    mut matches: Vec<String>,
+    start: usize,
+    cursor: usize,
+    out: &mut Vec<Completion>,
+) {
+    matches.sort();
+    for name in &matches {
+        out.push(Completion::replacing(
+            format!("{} ", quote_file(name)),
+            name.clone(),
+            start,
+            cursor,
+        ));
+    }
+    if matches.len() > 1 {
+        let expansion = matches.iter()
+            .map(|name| quote_file(name))
+            .collect::<Vec<_>>()
+            .join(" ");
+        out.push(Completion::replacing(
+            format!("{} ", expansion),
+            expansion,
+            start,
+            cursor,
+        ));
+    }
+}
+
+fn complete_glob(
+    lister: &impl DirectoryLister,
+    pattern: &str,
+    value_type: &ValueType,
+    start: usize,
+    cursor: usize,
+    out: &mut Vec<Completion>,
+) -> CrushResult<()> {
+    if !value_type.is_compatible_with(&ValueType::File) {
+        return Ok(());
+    }
+    let mut files = Vec::new();
+    Glob::new(pattern).glob_files_with_lister(&PathBuf::from("."), &mut files, lister)?;
+    complete_pattern_matches(
+        files.iter()
+            .filter_map(|f| f.to_str().map(|s| s.to_string()))
+            .collect(),
+        start,
+        cursor,
+        out);
+    Ok(())
+}
+
+fn complete_regex(
+    lister: &impl DirectoryLister,
+    pattern: &str,
+    value_type: &ValueType,
+    start: usize,
+    cursor: usize,
+    out: &mut Vec<Completion>,
+) -> CrushResult<()> {
+    if !value_type.is_compatible_with(&ValueType::File) {
+        return Ok(());
+    }
+    // A half typed pattern is often not a valid regex yet, which just means there is nothing to complete
+    let re = match Regex::new(pattern) {
+        Ok(re) => re,
+        Err(_) => return Ok(()),
+    };
+    complete_pattern_matches(
+        lister.list(".")?
+            .filter_map(|f| f.name.to_str().map(|s| s.to_string()))
+            .filter(|name| re.is_match(name))
+            .collect(),
+        start,
+        cursor,
+        out);
+    Ok(())
+}
+
 fn complete_argument_name(
     arguments: &Vec<ArgumentDescription>,
     prefix: &str,
@@ -195,6 +354,7 @@ fn complete_argument_name(
                 &a.name[prefix.len()..],
                 if is_switch { " " } else { "=" }),
             display: a.name.clone(),
+            start: cursor,
             position: cursor,
         })
         .collect());
@@ -271,6 +431,10 @@ fn complete_partial_argument(
 
         LastArgument::Unknown => {
             complete_label(Value::Scope(scope.clone()), "", &argument_type, cursor, res)?;
+            complete_parameter(&parse_result.locals.parameters, "", &argument_type, cursor, res);
+            if argument_type == ValueType::Field {
+                complete_column(&parse_result.locals.columns, "", true, &argument_type, cursor, res);
+            }
             complete_file(lister, "", false, &argument_type, cursor, res)?;
             if parse_result.last_argument_name.is_none() {
                 if let CompletionCommand::Known(cmd) = parse_result.command {
@@ -281,6 +445,7 @@ fn complete_partial_argument(
 
         LastArgument::Label(label) => {
             complete_label(Value::Scope(scope.clone()), &label, &argument_type, cursor, res)?;
+            complete_parameter(&parse_result.locals.parameters, &label, &argument_type, cursor, res);
             if parse_result.last_argument_name.is_none() {
                 if let CompletionCommand::Known(cmd) = parse_result.command {
                     complete_argument_name(cmd.arguments(), &label, cursor, res, false)?;
@@ -296,6 +461,18 @@ fn complete_partial_argument(
             complete_file(lister, &l, quoted, &argument_type, cursor, res)?;
         }
 
+        LastArgument::Glob(pattern, start) => {
+            complete_glob(lister, &pattern, &argument_type, start, cursor, res)?;
+        }
+
+        LastArgument::Regex(pattern, start) => {
+            complete_regex(lister, &pattern, &argument_type, start, cursor, res)?;
+        }
+
+        LastArgument::Column(prefix) => {
+            complete_column(&parse_result.locals.columns, &prefix, false, &argument_type, cursor, res);
+        }
+
         LastArgument::QuotedString(_) => {}
     }
     Ok(())
@@ -316,8 +493,13 @@ pub fn complete(
             complete_file(lister, "", false, &ValueType::Any, cursor, &mut res)?;
         }
 
-        ParseResult::PartialLabel(label) => {
+        ParseResult::PartialLabel(label, locals) => {
             complete_label(Value::Scope(scope.clone()), &label, &ValueType::Any, cursor, &mut res)?;
+            complete_parameter(&locals.parameters, &label, &ValueType::Any, cursor, &mut res);
+        }
+
+        ParseResult::PartialColumn(prefix, locals) => {
+            complete_column(&locals.columns, &prefix, false, &ValueType::Any, cursor, &mut res);
         }
 
         ParseResult::PartialMember(parent, label) => {
@@ -343,6 +525,9 @@ mod tests {
     use crate::util::directory_lister::FakeDirectoryLister;
     use signature::signature;
     use crate::lang::execution_context::CommandContext;
+    use crate::lang::command::Command;
+    use crate::lang::command::OutputType::{Known, Passthrough};
+    use crate::lang::value::Field;
 
     fn parser() -> Parser {
         Parser::new()
@@ -394,6 +579,47 @@ mod tests {
         argument3: String,
     }
 
+    fn table_cmd(_context: CommandContext) -> CrushResult<()> {
+        Ok(())
+    }
+
+    fn sort_cmd(_context: CommandContext) -> CrushResult<()> {
+        Ok(())
+    }
+
+    fn filter_cmd(_context: CommandContext) -> CrushResult<()> {
+        Ok(())
+    }
+
+    #[signature(
+    table_cmd,
+    output = Known(ValueType::TableInputStream(vec![
+        ColumnType::new("size", ValueType::Integer),
+        ColumnType::new("name", ValueType::String)])))]
+    struct TableCmdSignature {}
+
+    #[signature(sort_cmd, output = Passthrough)]
+    struct SortCmdSignature {
+        field: Field,
+    }
+
+    #[signature(filter_cmd, output = Passthrough)]
+    struct FilterCmdSignature {
+        condition: Command,
+    }
+
+    fn scope_with_table_commands() -> Scope {
+        let root = Scope::create_root();
+        let chld = root.create_namespace("stream", "bla", Box::new(|env| {
+            TableCmdSignature::declare(env)?;
+            SortCmdSignature::declare(env)?;
+            FilterCmdSignature::declare(env)?;
+            Ok(())
+        })).unwrap();
+        root.r#use(&chld);
+        root
+    }
+
     fn scope_with_function() -> Scope {
         let root = Scope::create_root();
         let chld = root.create_namespace("namespace", "bla", Box::new(|env| {
@@ -805,4 +1031,127 @@ mod tests {
         assert_eq!(completions.len(), 1);
         assert_eq!(&completions[0].complete(line), "\"\":format ");
     }
+
+    #[test]
+    fn complete_glob_with_single_match() {
+        let line = "echo burrow/c%";
+        let cursor = line.len();
+
+        let s = Scope::create_root();
+        let completions = complete(line, cursor, &s, &parser(), &lister()).unwrap();
+        assert_eq!(completions.len(), 1);
+        assert_eq!(&completions[0].complete(line), "echo burrow/carrot ");
+    }
+
+    #[test]
+    fn complete_glob_with_expansion() {
+        let line = "echo burrow/%t%";
+        let cursor = line.len();
+
+        let s = Scope::create_root();
+        let completions = complete(line, cursor, &s, &parser(), &lister()).unwrap();
+        assert_eq!(completions.len(), 4);
+        assert_eq!(&completions[0].complete(line), "echo burrow/carrot ");
+        assert_eq!(&completions[3].complete(line), "echo burrow/carrot burrow/lettuce burrow/table ");
+    }
+
+    #[test]
+    fn complete_regex() {
+        let line = "echo re\"^bu.*\"";
+        let cursor = line.len();
+
+        let s = Scope::create_root();
+        let completions = complete(line, cursor, &s, &parser(), &lister()).unwrap();
+        assert_eq!(completions.len(), 1);
+        assert_eq!(&completions[0].complete(line), "echo burrow ");
+    }
+
+    #[test]
+    fn complete_invalid_regex() {
+        let line = "echo re\"bu(\"";
+        let cursor = line.len();
+
+        let s = Scope::create_root();
+        let completions = complete(line, cursor, &s, &parser(), &lister()).unwrap();
+        assert_eq!(completions.len(), 0);
+    }
+
+    #[test]
+    fn complete_column_from_previous_command() {
+        let line = "table_cmd | sort_cmd ^si";
+        let cursor = line.len();
+
+        let s = scope_with_table_commands();
+        let completions = complete(line, cursor, &s, &parser(), &empty_lister()).unwrap();
+        assert_eq!(completions.len(), 1);
+        assert_eq!(&completions[0].complete(line), "table_cmd | sort_cmd ^size ");
+    }
+
+    #[test]
+    fn complete_empty_column_from_previous_command() {
+        let line = "table_cmd | sort_cmd ^";
+        let cursor = line.len();
+
+        let s = scope_with_table_commands();
+        let completions = complete(line, cursor, &s, &parser(), &empty_lister()).unwrap();
+        assert_eq!(completions.len(), 2);
+        assert_eq!(&completions[1].complete(line), "table_cmd | sort_cmd ^name ");
+    }
+
+    #[test]
+    fn complete_column_without_caret() {
+        let line = "table_cmd | sort_cmd ";
+        let cursor = line.len();
+
+        let s = scope_with_table_commands();
+        let completions = complete(line, cursor, &s, &parser(), &empty_lister()).unwrap();
+        let columns = completions.iter()
+            .filter(|c| c.display().starts_with('^'))
+            .map(|c| c.complete(line))
+            .collect::<Vec<_>>();
+        assert_eq!(columns, vec!["table_cmd | sort_cmd ^size ", "table_cmd | sort_cmd ^name "]);
+    }
+
+    #[test]
+    fn complete_column_inside_closure() {
+        let line = "table_cmd | filter_cmd {^na";
+        let cursor = line.len();
+
+        let s = scope_with_table_commands();
+        let completions = complete(line, cursor, &s, &parser(), &empty_lister()).unwrap();
+        assert_eq!(completions.len(), 1);
+        assert_eq!(&completions[0].complete(line), "table_cmd | filter_cmd {^name ");
+    }
+
+    #[test]
+    fn complete_column_inside_closure_after_unknown_command() {
+        let line = "table_cmd | filter_cmd {unknown_cmd | sort_cmd ^na";
+        let cursor = line.len();
+
+        let s = scope_with_table_commands();
+        let completions = complete(line, cursor, &s, &parser(), &empty_lister()).unwrap();
+        assert_eq!(completions.len(), 0);
+    }
+
+    #[test]
+    fn complete_closure_parameter_from_columns() {
+        let line = "table_cmd | filter_cmd {si";
+        let cursor = line.len();
+
+        let s = scope_with_table_commands();
+        let completions = complete(line, cursor, &s, &parser(), &empty_lister()).unwrap();
+        assert_eq!(completions.len(), 1);
+        assert_eq!(&completions[0].complete(line), "table_cmd | filter_cmd {size");
+    }
+
+    #[test]
+    fn complete_closure_parameter_from_signature() {
+        let line = "filter_cmd {|value| val";
+        let cursor = line.len();
+
+        let s = scope_with_table_commands();
+        let completions = complete(line, cursor, &s, &parser(), &empty_lister()).unwrap();
+        assert_eq!(completions.len(), 1);
+        assert_eq!(&completions[0].complete(line), "filter_cmd {|value| value");
+    }
 }
@@ -1,7 +1,8 @@
-use crate::lang::ast::{Node, CommandNode, JobListNode, JobNode};
+use crate::lang::ast::{Node, CommandNode, JobListNode, JobNode, ParameterNode};
 use crate::lang::errors::{error, CrushResult, mandate, argument_error_legacy, to_crush_error};
 use crate::lang::value::{ValueType, Value};
-use crate::lang::command::{Command, ArgumentDescription};
+use crate::lang::command::{Command, ArgumentDescription, OutputType};
+use crate::lang::data::table::ColumnType;
 use crate::lang::data::scope::Scope;
 use std::ops::Deref;
 use regex::Regex;
@@ -32,6 +33,27 @@ pub enum LastArgument {
     File(String, bool),
     QuotedString(String),
     Switch(String),
+    /** A glob pattern and the position in the line where it starts */
+    Glob(String, usize),
+    /** A regex file pattern and the position in the line where it starts */
+    Regex(String, usize),
+    /** A partial field name, without the leading caret */
+    Column(String),
+}
+
+/**
+Information about the surroundings of the cursor that isn't visible in the command itself.
+*/
+#[derive(Clone, Default)]
+pub struct LocalContext {
+    /** The columns of the input of the command being completed, if known */
+    pub columns: Vec<ColumnType>,
+    /**
+    The parameters of the closures surrounding the cursor. For closures without a signature, these
+    are the columns of the input of the command the closure is passed to, since that is how e.g.
+    where and select pass on rows.
+    */
+    pub parameters: Vec<ColumnType>,
 }
 
 #[derive(Clone)]
@@ -52,6 +74,7 @@ pub struct PartialCommandResult {
     pub previous_arguments: Vec<PreviousArgument>,
     pub last_argument_name: Option<String>,
     pub last_argument: LastArgument,
+    pub locals: LocalContext,
 }
 
 impl PartialCommandResult {
@@ -114,7 +137,8 @@ impl PartialCommandResult {
 #[derive(Clone)]
 pub enum ParseResult {
     Nothing,
-    PartialLabel(String),
+    PartialLabel(String, LocalContext),
+    PartialColumn(String, LocalContext),
     PartialMember(Value, String),
     PartialFile(String, bool),
     PartialQuotedString(String),
@@ -134,23 +158,38 @@ fn simple_path(node: &Node, cursor: usize) -> CrushResult<String> {
     }
 }
 
-fn find_command_in_expression(exp: &Node, cursor: usize) -> CrushResult<Option<CommandNode>> {
+fn closure_parameters(signature: &Option<Vec<ParameterNode>>, locals: &LocalContext) -> Vec<ColumnType> {
+    match signature {
+        None => locals.columns.clone(),
+        Some(parameters) => parameters.iter()
+            .map(|p| match p {
+                ParameterNode::Parameter(name, _, _) |
+                ParameterNode::Named(name) |
+                ParameterNode::Unnamed(name) => ColumnType::new(&name.string, ValueType::Any),
+            })
+            .collect(),
+    }
+}
+
+fn find_command_in_expression(exp: &Node, cursor: usize, scope: &Scope, locals: &mut LocalContext) -> CrushResult<Option<CommandNode>> {
     match exp {
         Node::Assignment(_, _, b) => {
-            find_command_in_expression(b, cursor)
+            find_command_in_expression(b, cursor, scope, locals)
         }
 
         Node::Substitution(j) => {
             if j.location.contains(cursor) {
-                Ok(Some(find_command_in_job(j.clone(), cursor)?))
+                Ok(Some(find_command_in_job(j.clone(), cursor, scope, locals)?))
             } else {
                 Ok(None)
             }
         }
 
-        Node::Closure(_, joblist) => {
+        Node::Closure(signature, joblist) => {
             if joblist.location.contains(cursor) {
-                Ok(Some(find_command_in_job_list(joblist.clone(), cursor)?))
+                let mut parameters = closure_parameters(signature, locals);
+                locals.parameters.append(&mut parameters);
+                Ok(Some(find_command_in_job_list(joblist.clone(), cursor, scope, locals)?))
             } else {
                 Ok(None)
             }
@@ -162,34 +201,53 @@ fn find_command_in_expression(exp: &Node, cursor: usize) -> CrushResult<Option<C
     }
 }
 
-fn find_command_in_command(ast: CommandNode, cursor: usize) -> CrushResult<CommandNode> {
+fn find_command_in_command(ast: CommandNode, cursor: usize, scope: &Scope, locals: &mut LocalContext) -> CrushResult<CommandNode> {
     for exp in &ast.expressions {
-        if let Some(res) = find_command_in_expression(exp, cursor)? {
+        if let Some(res) = find_command_in_expression(exp, cursor, scope, locals)? {
             return Ok(res);
         }
     }
     Ok(ast)
 }
 
-fn find_command_in_job(job: JobNode, cursor: usize) -> CrushResult<CommandNode> {
-    for cmd in &job.commands {
-        if cmd.location.contains(cursor) {
-            return find_command_in_command(cmd.clone(), cursor);
+fn output_type(cmd: &CommandNode, input: &OutputType, scope: &Scope) -> OutputType {
+    match cmd.expressions.first().map(|e| parse_command_node(e, scope)) {
+        Some(Ok(CompletionCommand::Known(command))) =>
+            command.output(input)
+                .map(|t| OutputType::Known(t.clone()))
+                .unwrap_or(OutputType::Unknown),
+        _ => OutputType::Unknown,
+    }
+}
+
+fn find_command_in_job(job: JobNode, cursor: usize, scope: &Scope, locals: &mut LocalContext) -> CrushResult<CommandNode> {
+    let mut input = OutputType::Unknown;
+    for (idx, cmd) in job.commands.iter().enumerate() {
+        if cmd.location.contains(cursor) || idx == job.commands.len() - 1 {
+            match input {
+                OutputType::Known(ValueType::TableInputStream(columns)) |
+                OutputType::Known(ValueType::Table(columns)) => locals.columns = columns,
+                // The columns of an enclosing pipeline only apply to the first command of a closure
+                _ if idx > 0 => locals.columns.clear(),
+                _ => {}
+            }
+            return find_command_in_command(cmd.clone(), cursor, scope, locals);
         }
+        input = output_type(cmd, &input, scope);
     }
-    mandate(job.commands.last(), "Nothing to complete").map(|c| c.clone())
+    error("Nothing to complete")
 }
 
-fn find_command_in_job_list(ast: JobListNode, cursor: usize) -> CrushResult<CommandNode> {
+fn find_command_in_job_list(ast: JobListNode, cursor: usize, scope: &Scope, locals: &mut LocalContext) -> CrushResult<CommandNode> {
     for job in &ast.jobs {
         if job.location.contains(cursor) {
-            return find_command_in_job(job.clone(), cursor);
+            return find_command_in_job(job.clone(), cursor, scope, locals);
         }
     }
-    mandate(
-        ast.jobs.last()
-            .and_then(|j| j.commands.last()
-                .map(|c| c.clone())), "Nothing to complete")
+    match ast.jobs.last() {
+        Some(job) => find_command_in_job(job.clone(), cursor, scope, locals),
+        None => error("Nothing to complete"),
+    }
 }
 
 fn fetch_value(node: &Node, scope: &Scope) -> CrushResult<Option<Value>> {
@@ -266,7 +324,8 @@ pub fn parse(
         return Ok(ParseResult::Nothing);
     }
 
-    let cmd = find_command_in_job_list(ast, cursor)?;
+    let mut locals = LocalContext::default();
+    let cmd = find_command_in_job_list(ast, cursor, scope, &mut locals)?;
 
     match cmd.expressions.len() {
         0 => Ok(ParseResult::Nothing),
@@ -276,7 +335,13 @@ pub fn parse(
                 match cmd {
                     Node::Label(label) =>
                         Ok(ParseResult::PartialLabel(
-                            label.prefix(cursor).string)),
+                            label.prefix(cursor).string,
+                            locals)),
+
+                    Node::Field(field) =>
+                        Ok(ParseResult::PartialColumn(
+                            field.prefix(cursor).string.trim_start_matches('^').to_string(),
+                            locals)),
 
                     Node::GetAttr(parent, field) =>
                         Ok(ParseResult::PartialMember(
@@ -305,6 +370,7 @@ pub fn parse(
                         previous_arguments: vec![],
                         last_argument: LastArgument::Unknown,
                         last_argument_name: None,
+                        locals: locals.clone(),
                     }
                 ))
             }
@@ -340,6 +406,7 @@ pub fn parse(
                                 previous_arguments,
                                 last_argument: LastArgument::Switch(l.string.clone()),
                                 last_argument_name,
+                                locals: locals.clone(),
                             }
                         )),
 
@@ -355,6 +422,7 @@ pub fn parse(
                                     previous_arguments,
                                     last_argument: LastArgument::Label(l.string.clone()),
                                     last_argument_name,
+                                    locals: locals.clone(),
                                 }
                             )),
 
@@ -367,6 +435,7 @@ pub fn parse(
                                         mandate(fetch_value(parent, scope)?, "unknown value")?,
                                         field.prefix(cursor).string),
                                     last_argument_name,
+                                    locals: locals.clone(),
                                 })),
 
                         Node::Path(_, _) =>
@@ -376,6 +445,7 @@ pub fn parse(
                                     previous_arguments,
                                     last_argument: LastArgument::File(simple_path(arg.as_ref(), cursor)?, false),
                                     last_argument_name,
+                                    locals: locals.clone(),
                                 }
                             )),
 
@@ -388,6 +458,7 @@ pub fn parse(
                                         if *quoted { unescape(&path.string)? } else { path.string.clone() },
                                         *quoted),
                                     last_argument_name,
+                                    locals: locals.clone(),
                                 }
                             )),
 
@@ -398,11 +469,63 @@ pub fn parse(
                                     previous_arguments,
                                     last_argument: LastArgument::QuotedString(unescape(&s.string)?),
                                     last_argument_name,
+                                    locals: locals.clone(),
+                                }
+                            )),
+
+                        Node::Glob(g) =>
+                            Ok(ParseResult::PartialArgument(
+                                PartialCommandResult {
+                                    command: c,
+                                    previous_arguments,
+                                    last_argument: LastArgument::Glob(
+                                        g.prefix(cursor).string,
+                                        g.location.start),
+                                    last_argument_name,
+                                    locals: locals.clone(),
+                                }
+                            )),
+
+                        Node::Field(f) =>
+                            Ok(ParseResult::PartialArgument(
+                                PartialCommandResult {
+                                    command: c,
+                                    previous_arguments,
+                                    last_argument: LastArgument::Column(
+                                        f.prefix(cursor).string.trim_start_matches('^').to_string()),
+                                    last_argument_name,
+                                    locals: locals.clone(),
+                                }
+                            )),
+
+                        Node::Regex(r) if r.location.end <= cursor =>
+                            Ok(ParseResult::PartialArgument(
+                                PartialCommandResult {
+                                    command: c,
+                                    previous_arguments,
+                                    last_argument: LastArgument::Regex(
+                                        r.string.clone(),
+                                        r.location.start),
+                                    last_argument_name,
+                                    locals: locals.clone(),
                                 }
                             )),
 
                         _ => error("Can't extract argument to complete"),
                     }
+                } else if arg.location().end < cursor {
+                    // The last argument is complete and the cursor is at the start of a new one
+                    let mut previous_arguments = previous_arguments;
+                    previous_arguments.push(parse_previous_argument(cmd.expressions.last().unwrap()));
+                    Ok(ParseResult::PartialArgument(
+                        PartialCommandResult {
+                            command: c,
+                            previous_arguments,
+                            last_argument: LastArgument::Unknown,
+                            last_argument_name: None,
+                            locals: locals.clone(),
+                        }
+                    ))
                 } else {
                     Ok(ParseResult::PartialArgument(
                         PartialCommandResult {
@@ -410,6 +533,7 @@ pub fn parse(
                             previous_arguments,
                             last_argument: LastArgument::Unknown,
                             last_argument_name,
+                            locals: locals.clone(),
                         }
                     ))
                 }
@@ -431,28 +555,28 @@ mod tests {
     #[test]
     fn find_command_in_substitution_test() {
         let ast = ast("a (b)").unwrap();
-        let cmd = find_command_in_job_list(ast, 4).unwrap();
+        let cmd = find_command_in_job_list(ast, 4, &Scope::create_root(), &mut LocalContext::default()).unwrap();
         assert_eq!(cmd.location, Location::new(3, 4))
     }
 
     #[test]
     fn find_command_in_closure_test() {
         let ast = ast("a {b}").unwrap();
-        let cmd = find_command_in_job_list(ast, 4).unwrap();
+        let cmd = find_command_in_job_list(ast, 4, &Scope::create_root(), &mut LocalContext::default()).unwrap();
         assert_eq!(cmd.location, Location::new(3, 4))
     }
 
     #[test]
     fn find_command_in_complicated_mess_test() {
         let ast = ast("a | b {c:d (e f=g) h=(i j)}").unwrap();
-        let cmd = find_command_in_job_list(ast, 25).unwrap();
+        let cmd = find_command_in_job_list(ast, 25, &Scope::create_root(), &mut LocalContext::default()).unwrap();
         assert_eq!(cmd.location, Location::new(22, 25))
     }
 
     #[test]
     fn find_command_in_operator() {
         let ast = ast("ps | where {^cpu == (max_)}").unwrap();
-        let cmd = find_command_in_job_list(ast, 25).unwrap();
+        let cmd = find_command_in_job_list(ast, 25, &Scope::create_root(), &mut LocalContext::default()).unwrap();
         assert_eq!(cmd.location, Location::new(21, 25))
     }
 }
@@ -1,6 +1,7 @@
 pub mod dict;
 pub mod r#struct;
 pub mod list;
+pub mod set;
 pub mod binary;
 pub mod scope;
 pub mod table;
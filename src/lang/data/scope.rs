@@ -1,5 +1,5 @@
 use crate::lang::command::{Command, CrushCommand, OutputType, ArgumentDescription};
-use crate::lang::errors::{error, mandate, CrushResult, argument_error_legacy};
+use crate::lang::errors::{error, mandate, CrushResult, argument_error_legacy, CrushError};
 use crate::lang::execution_context::CommandContext;
 use crate::lang::help::Help;
 use crate::lang::data::r#struct::Struct;
@@ -50,6 +50,14 @@ impl ScopeLoader {
         Ok(())
     }
 
+    /** Declare an additional name for an already declared member of this namespace. */
+    pub fn alias(&mut self, name: &str, original: &str) -> CrushResult<()> {
+        let value = mandate(
+            self.mapping.get(original).cloned(),
+            format!("Unknown variable {{{}}}", original))?;
+        self.declare(name, value)
+    }
+
     /**
         Create a namespace. Namespaces are lazily loaded, so on creating, only a stub is created,
         and the first time a namespace is used, the loader function will be called, and that will
@@ -180,11 +188,24 @@ pub struct ScopeData {
     called.  */
     pub is_stopped: bool,
 
+    /** True if execution of this scope and all scopes called from it has been cancelled, e.g.
+    because a timeout expired. */
+    pub is_cancelled: bool,
+
+    /** If set, errors of commands run in this scope or any scope called from it are collected
+    here instead of being printed. */
+    pub error_trap: Option<Arc<Mutex<Vec<CrushError>>>>,
+
     /** True if this scope can not be further modified. Note that mutable variables in it, e.g.
     lists can still be modified. */
     pub is_readonly: bool,
 
     pub name: Option<String>,
+
+    /** A description of the closure invocation that created this scope, if any. Used by the
+    debugger to produce backtraces. */
+    pub frame: Option<String>,
+
     description: Option<String>,
     is_loaded: bool,
     loader: Option<Box<dyn Send + FnOnce(&mut ScopeLoader) -> CrushResult<()>>>,
@@ -205,8 +226,11 @@ impl ScopeData {
             uses: Vec::new(),
             mapping: OrderedMap::new(),
             is_stopped: false,
+            is_cancelled: false,
+            error_trap: None,
             is_readonly: false,
             name,
+            frame: None,
             description,
             is_loaded: true,
             loader: None,
@@ -228,8 +252,11 @@ impl ScopeData {
             uses: Vec::new(),
             mapping: OrderedMap::new(),
             is_stopped: false,
+            is_cancelled: false,
+            error_trap: None,
             is_readonly: false,
             name,
+            frame: None,
             description,
             is_loaded: false,
             loader: Some(loader),
@@ -246,8 +273,11 @@ impl Clone for ScopeData {
             uses: self.uses.clone(),
             mapping: self.mapping.clone(),
             is_stopped: self.is_stopped,
+            is_cancelled: self.is_cancelled,
+            error_trap: self.error_trap.clone(),
             is_readonly: self.is_readonly,
             name: self.name.clone(),
+            frame: self.frame.clone(),
             description: self.description.clone(),
             is_loaded: true,
             loader: None,
@@ -291,8 +321,11 @@ impl Scope {
                 mapping: OrderedMap::new(),
                 is_loop,
                 is_stopped,
+                is_cancelled: false,
+                error_trap: None,
                 is_readonly,
                 name,
+                frame: None,
                 description,
                 is_loaded: true,
                 loader: None,
@@ -385,7 +418,102 @@ impl Scope {
     }
 
     pub fn is_stopped(&self) -> bool {
-        self.lock().unwrap().is_stopped
+        self.lock().unwrap().is_stopped || self.is_cancelled()
+    }
+
+    /**
+        Cancel execution of this scope and of all scopes called from it.
+    */
+    pub fn cancel(&self) {
+        self.data.lock().unwrap().is_cancelled = true;
+    }
+
+    /**
+        Returns true if this scope or any of its calling scopes has been cancelled.
+    */
+    pub fn is_cancelled(&self) -> bool {
+        let mut current = Some(self.clone());
+        while let Some(scope) = current {
+            let data = scope.data.lock().unwrap();
+            if data.is_cancelled {
+                return true;
+            }
+            current = data.calling_scope.clone();
+        }
+        false
+    }
+
+    /**
+        Collect errors of commands run in this scope, or in any scope called from it, instead of
+        printing them. The collected errors can be retrieved using `take_errors`.
+    */
+    pub fn trap_errors(&self) {
+        self.data.lock().unwrap().error_trap = Some(Arc::from(Mutex::new(Vec::new())));
+    }
+
+    pub fn take_errors(&self) -> Vec<CrushError> {
+        match &self.data.lock().unwrap().error_trap {
+            Some(trap) => trap.lock().unwrap().drain(..).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /**
+        Hand the specified error to the nearest calling scope that traps errors. If there is no
+        such scope, the error is returned so that the caller can report it.
+    */
+    pub fn report_error(&self, error: CrushError) -> Option<CrushError> {
+        let mut current = Some(self.clone());
+        while let Some(scope) = current {
+            let data = scope.data.lock().unwrap();
+            if let Some(trap) = &data.error_trap {
+                trap.lock().unwrap().push(error);
+                return None;
+            }
+            current = data.calling_scope.clone();
+        }
+        Some(error)
+    }
+
+    /**
+        Returns the closure nesting depth of this scope, i.e. the number of closure invocations
+        between this scope and the top level scope. Namespaces and loop scopes are not counted.
+    */
+    pub fn depth(&self) -> usize {
+        let mut res: usize = 0;
+        let mut current = Some(self.clone());
+        while let Some(scope) = current {
+            let data = scope.data.lock().unwrap();
+            if data.name.is_none() && !data.is_loop {
+                res += 1;
+            }
+            current = data.calling_scope.clone();
+        }
+        res.saturating_sub(1)
+    }
+
+    /**
+        Record a description of the closure invocation that created this scope.
+    */
+    pub fn set_frame(&self, frame: String) {
+        self.data.lock().unwrap().frame = Some(frame);
+    }
+
+    /**
+        Returns the closure invocation frames of this scope and all of its calling scopes,
+        innermost first.
+    */
+    pub fn backtrace(&self) -> Vec<String> {
+        let mut res = Vec::new();
+        let mut current = Some(self.clone());
+        while let Some(scope) = current {
+            let data = scope.data.lock().unwrap();
+            if let Some(frame) = &data.frame {
+                res.push(frame.clone());
+            }
+            current = data.calling_scope.clone();
+        }
+        res
     }
 
     fn lock(&self) -> CrushResult<MutexGuard<ScopeData>> {
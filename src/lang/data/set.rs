@@ -0,0 +1,289 @@
+use crate::lang::errors::{argument_error_legacy, error, CrushResult};
+use crate::lang::pipe::CrushStream;
+use crate::lang::{data::table::ColumnType, data::table::Row, value::Value, value::ValueType};
+use crate::util::identity_arc::Identity;
+use chrono::Duration;
+use ordered_map::OrderedMap;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex};
+
+/**
+A mutable, unordered collection of unique values. Elements are kept in insertion order, the same
+way the keys of a dict are. Only values of hashable types can be elements of a set.
+*/
+#[derive(Clone)]
+pub struct Set {
+    element_type: ValueType,
+    elements: Arc<Mutex<OrderedMap<Value, ()>>>,
+}
+
+impl Identity for Set {
+    fn id(&self) -> u64 {
+        self.elements.id()
+    }
+}
+
+impl Set {
+    pub fn new(element_type: ValueType) -> Set {
+        if !element_type.is_hashable() {
+            panic!("Tried to create set with unhashable element type");
+        }
+        Set {
+            element_type,
+            elements: Arc::new(Mutex::new(OrderedMap::new())),
+        }
+    }
+
+    /**
+    Create a set containing the specified values. The element type is the type of the values if
+    they all have the same type, otherwise it is any.
+    */
+    pub fn new_without_type(values: Vec<Value>) -> CrushResult<Set> {
+        let types = values
+            .iter()
+            .map(|v| v.value_type())
+            .collect::<HashSet<ValueType>>();
+        let element_type = if types.len() == 1 {
+            values[0].value_type()
+        } else {
+            ValueType::Any
+        };
+        if !element_type.is_hashable() {
+            return argument_error_legacy("Element type is not hashable");
+        }
+        let set = Set::new(element_type);
+        for value in values {
+            set.insert(value)?;
+        }
+        Ok(set)
+    }
+
+    pub fn len(&self) -> usize {
+        let elements = self.elements.lock().unwrap();
+        elements.len()
+    }
+
+    pub fn clear(&self) {
+        let mut elements = self.elements.lock().unwrap();
+        elements.clear()
+    }
+
+    pub fn copy(&self) -> Set {
+        let elements = self.elements.lock().unwrap();
+        Set {
+            element_type: self.element_type.clone(),
+            elements: Arc::new(Mutex::new(elements.clone())),
+        }
+    }
+
+    pub fn contains(&self, value: &Value) -> bool {
+        if !value.value_type().is_hashable() {
+            return false;
+        }
+        let elements = self.elements.lock().unwrap();
+        elements.contains_key(value)
+    }
+
+    /** Add a value to the set. Returns true if the value was not already in the set. */
+    pub fn insert(&self, value: Value) -> CrushResult<bool> {
+        if !self.element_type.is(&value) {
+            return argument_error_legacy("Invalid element type");
+        }
+        if !value.value_type().is_hashable() {
+            return argument_error_legacy("Element type is not hashable");
+        }
+        let mut elements = self.elements.lock().unwrap();
+        if elements.contains_key(&value) {
+            Ok(false)
+        } else {
+            elements.insert(value, ());
+            Ok(true)
+        }
+    }
+
+    /** Remove a value from the set. Returns true if the value was in the set. */
+    pub fn remove(&self, value: &Value) -> bool {
+        if !value.value_type().is_hashable() {
+            return false;
+        }
+        let mut elements = self.elements.lock().unwrap();
+        elements.remove(value).is_some()
+    }
+
+    pub fn element_type(&self) -> ValueType {
+        self.element_type.clone()
+    }
+
+    pub fn set_type(&self) -> ValueType {
+        ValueType::Set(Box::from(self.element_type.clone()))
+    }
+
+    pub fn elements(&self) -> Vec<Value> {
+        let elements = self.elements.lock().unwrap();
+        elements.keys().cloned().collect()
+    }
+
+    fn with_elements(element_type: ValueType, elements: Vec<Value>) -> Set {
+        let mut map = OrderedMap::with_capacity(elements.len());
+        for value in elements {
+            map.insert(value, ());
+        }
+        Set {
+            element_type,
+            elements: Arc::new(Mutex::new(map)),
+        }
+    }
+
+    /** A new set with the elements that are in either set. */
+    pub fn union(&self, other: &Set) -> Set {
+        let element_type = if self.element_type == other.element_type {
+            self.element_type.clone()
+        } else {
+            ValueType::Any
+        };
+        let mut elements = self.elements();
+        elements.append(&mut other.elements());
+        Set::with_elements(element_type, elements)
+    }
+
+    /** A new set with the elements that are in both sets. */
+    pub fn intersection(&self, other: &Set) -> Set {
+        Set::with_elements(
+            self.element_type.clone(),
+            self.elements().into_iter().filter(|v| other.contains(v)).collect(),
+        )
+    }
+
+    /** A new set with the elements of this set that are not in the other set. */
+    pub fn difference(&self, other: &Set) -> Set {
+        Set::with_elements(
+            self.element_type.clone(),
+            self.elements().into_iter().filter(|v| !other.contains(v)).collect(),
+        )
+    }
+
+    pub fn materialize(self) -> CrushResult<Set> {
+        Ok(Set::with_elements(
+            self.element_type.materialize()?,
+            self.elements()
+                .into_iter()
+                .map(|v| v.materialize())
+                .collect::<CrushResult<Vec<_>>>()?,
+        ))
+    }
+}
+
+impl std::cmp::PartialEq for Set {
+    fn eq(&self, other: &Set) -> bool {
+        let us = self.elements.lock().unwrap().clone();
+        let them = other.elements.lock().unwrap().clone();
+        us.len() == them.len() && us.keys().all(|v| them.contains_key(v))
+    }
+}
+
+impl Display for Set {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        f.write_str("set{")?;
+        let elements = self.elements.lock().unwrap();
+        let mut first = true;
+        for v in elements.keys() {
+            if first {
+                first = false;
+            } else {
+                f.write_str(" ")?;
+            }
+            f.write_str(&v.to_string())?;
+        }
+        f.write_str("}")?;
+        Ok(())
+    }
+}
+
+impl std::cmp::PartialOrd for Set {
+    fn partial_cmp(&self, _other: &Set) -> Option<Ordering> {
+        None
+    }
+}
+
+pub struct SetReader {
+    list: Vec<Value>,
+    idx: usize,
+    types: Vec<ColumnType>,
+}
+
+impl SetReader {
+    pub fn new(set: Set) -> SetReader {
+        SetReader {
+            types: vec![ColumnType::new("value", set.element_type.clone())],
+            list: set.elements(),
+            idx: 0usize,
+        }
+    }
+}
+
+impl CrushStream for SetReader {
+    fn read(&mut self) -> CrushResult<Row> {
+        if self.idx >= self.list.len() {
+            return error("End of stream");
+        }
+        self.idx += 1;
+        Ok(Row::new(vec![self.list[self.idx - 1].clone()]))
+    }
+
+    fn read_timeout(
+        &mut self,
+        _timeout: Duration,
+    ) -> Result<Row, crate::lang::pipe::RecvTimeoutError> {
+        match self.read() {
+            Ok(r) => Ok(r),
+            Err(_) => Err(crate::lang::pipe::RecvTimeoutError::Disconnected),
+        }
+    }
+
+    fn types(&self) -> &[ColumnType] {
+        &self.types
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn of(values: &[i128]) -> Set {
+        Set::new_without_type(values.iter().map(|v| Value::Integer(*v)).collect()).unwrap()
+    }
+
+    #[test]
+    fn check_insert() {
+        let set = of(&[1, 2]);
+        assert_eq!(set.insert(Value::Integer(3)).unwrap(), true);
+        assert_eq!(set.insert(Value::Integer(1)).unwrap(), false);
+        assert!(set.insert(Value::string("a")).is_err());
+        assert_eq!(set.len(), 3);
+        assert!(set.remove(&Value::Integer(2)));
+        assert!(!set.contains(&Value::Integer(2)));
+        assert_eq!(set.to_string(), "set{1 3}");
+    }
+
+    #[test]
+    fn check_unhashable() {
+        let set = Set::new(ValueType::Any);
+        let list = Value::List(crate::lang::data::list::List::new(ValueType::Integer, vec![]));
+        assert!(set.insert(list.clone()).is_err());
+        assert!(!set.contains(&list));
+        assert!(Set::new_without_type(vec![list]).is_err());
+    }
+
+    #[test]
+    fn check_operations() {
+        let a = of(&[1, 2, 3]);
+        let b = of(&[2, 3, 4]);
+        assert_eq!(a.union(&b).to_string(), "set{1 2 3 4}");
+        assert_eq!(a.intersection(&b).to_string(), "set{2 3}");
+        assert_eq!(a.difference(&b).to_string(), "set{1}");
+        assert!(a.intersection(&b) == of(&[3, 2]));
+        assert_eq!(a.union(&b).element_type(), ValueType::Integer);
+    }
+}
@@ -116,10 +116,19 @@ impl From<Row> for Vec<Value> {
 }
 
 
+/** A hint to the output formatter about how the cells of a column should be shown. */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ColumnFormat {
+    Plain,
+    /** The cells are sizes in bytes, which can be shown as e.g. 1.5 MiB. */
+    ByteSize,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ColumnType {
     pub name: String,
     pub cell_type: ValueType,
+    pub format: ColumnFormat,
 }
 
 impl ColumnType {
@@ -131,6 +140,7 @@ impl ColumnType {
             res.push(ColumnType {
                 name: col.name.clone(),
                 cell_type: col.cell_type.materialize()?,
+                format: col.format,
             });
         }
         Ok(res)
@@ -140,8 +150,14 @@ impl ColumnType {
         ColumnType {
             name: name.to_string(),
             cell_type,
+            format: ColumnFormat::Plain,
         }
     }
+
+    pub fn with_format(mut self, format: ColumnFormat) -> ColumnType {
+        self.format = format;
+        self
+    }
 }
 
 impl Display for ColumnType {
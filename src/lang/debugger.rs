@@ -0,0 +1,197 @@
+use rustyline::error::ReadlineError;
+use rustyline::Editor;
+use crate::lang::data::scope::Scope;
+use crate::lang::errors::{to_crush_error, CrushResult};
+use crate::lang::execute;
+use crate::lang::global_state::GlobalState;
+use crate::lang::job::Job;
+use crate::lang::pretty::create_simple_pretty_printer;
+use crate::lang::printer::Printer;
+
+const DEBUG_PROMPT: &'static str = "debug# ";
+
+/**
+What the debugger should do when the next job is about to be run.
+*/
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DebugMode {
+    /**
+    Don't pause while starting up, e.g. while running the configuration file. Once user code
+    starts running, behave like StepInto.
+    */
+    Start,
+    /** Run until a breakpoint is hit. */
+    Continue,
+    /** Pause before the next job, including jobs inside of called closures. */
+    StepInto,
+    /** Pause before the next job at the given closure depth or shallower. */
+    Step(usize),
+}
+
+/**
+Called before every job in a closure or script is run. Drops into the debugger if the current
+debug mode says that execution should be paused here.
+*/
+pub fn before_job(job: &Job, scope: &Scope, global_state: &GlobalState) -> CrushResult<()> {
+    if should_pause(global_state.debug_mode(), scope.depth()) {
+        let location = job.location();
+        pause(&format!("{}-{} {}", location.start, location.end, job), scope, global_state)
+    } else {
+        Ok(())
+    }
+}
+
+fn should_pause(mode: DebugMode, depth: usize) -> bool {
+    match mode {
+        DebugMode::Start | DebugMode::Continue => false,
+        DebugMode::StepInto => true,
+        DebugMode::Step(max_depth) => depth <= max_depth,
+    }
+}
+
+/**
+Called right before user code starts running. If the debugger was waiting for that, start
+stepping.
+*/
+pub fn user_code_starting(global_state: &GlobalState) {
+    if global_state.debug_mode() == DebugMode::Start {
+        global_state.set_debug_mode(DebugMode::StepInto);
+    }
+}
+
+/**
+Run code that isn't written by the user, e.g. the loading of a namespace, without stepping into
+it. Breakpoints still work. Once done, the previous debug mode is restored, unless a breakpoint
+changed it.
+*/
+pub fn without_stepping<T>(global_state: &GlobalState, f: impl FnOnce() -> CrushResult<T>) -> CrushResult<T> {
+    let mode = global_state.debug_mode();
+    global_state.set_debug_mode(DebugMode::Continue);
+    let res = f();
+    if global_state.debug_mode() == DebugMode::Continue {
+        global_state.set_debug_mode(mode);
+    }
+    res
+}
+
+/**
+The debug mode to resume execution with after the specified debugger command, or None if the
+command doesn't resume execution.
+*/
+fn resume_mode(cmd: &str, depth: usize) -> Option<DebugMode> {
+    match cmd {
+        "step" => Some(DebugMode::Step(depth)),
+        "step_into" => Some(DebugMode::StepInto),
+        "continue" => Some(DebugMode::Continue),
+        _ => None,
+    }
+}
+
+/**
+Pause execution and run a nested REPL in the specified scope until the user resumes execution.
+
+Only one debugger session can be active at a time. Breakpoints that are hit while the debugger
+is already paused, e.g. by commands run from the debugger prompt or by other threads, are
+ignored.
+*/
+pub fn pause(position: &str, scope: &Scope, global_state: &GlobalState) -> CrushResult<()> {
+    if !global_state.begin_debug_session() {
+        return Ok(());
+    }
+    global_state.set_debug_mode(DebugMode::Continue);
+    let res = session(position, scope, global_state);
+    global_state.end_debug_session();
+    res
+}
+
+fn session(position: &str, scope: &Scope, global_state: &GlobalState) -> CrushResult<()> {
+    let printer = global_state.printer();
+    let output = create_simple_pretty_printer(printer.clone(), global_state);
+    printer.line(&format!("Paused at {}", position));
+    printer.line(r#"Type "step", "step_into", "continue" or "backtrace" to control execution."#);
+
+    let mut rl = Editor::<()>::new();
+    loop {
+        printer.ping();
+        match rl.readline(DEBUG_PROMPT) {
+            Ok(cmd) => match (cmd.trim(), resume_mode(cmd.trim(), scope.depth())) {
+                (_, Some(mode)) => {
+                    global_state.set_debug_mode(mode);
+                    break;
+                }
+                ("", None) => {}
+                ("backtrace", None) => print_backtrace(position, scope, printer),
+                (cmd, None) => {
+                    rl.add_history_entry(cmd);
+                    printer.handle_error(execute::string(scope, cmd, &output, global_state));
+                    global_state.threads().reap(printer);
+                    if scope.is_stopped() {
+                        break;
+                    }
+                }
+            },
+            Err(ReadlineError::Interrupted) => printer.line("^C"),
+            Err(ReadlineError::Eof) => break,
+            Err(err) => return to_crush_error(Err(err)),
+        }
+    }
+    Ok(())
+}
+
+fn print_backtrace(position: &str, scope: &Scope, printer: &Printer) {
+    printer.line(&format!("#0 {}", position));
+    for (idx, frame) in scope.backtrace().iter().enumerate() {
+        printer.line(&format!("#{} {}", idx + 1, frame));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lang::printer;
+
+    #[test]
+    fn step_pauses_at_same_depth_or_shallower() {
+        let mode = resume_mode("step", 2).unwrap();
+        assert!(should_pause(mode, 1));
+        assert!(should_pause(mode, 2));
+        assert!(!should_pause(mode, 3));
+    }
+
+    #[test]
+    fn step_into_pauses_everywhere() {
+        let mode = resume_mode("step_into", 2).unwrap();
+        assert!(should_pause(mode, 0));
+        assert!(should_pause(mode, 5));
+    }
+
+    #[test]
+    fn continue_never_pauses() {
+        let mode = resume_mode("continue", 2).unwrap();
+        assert!(!should_pause(mode, 0));
+        assert!(!should_pause(mode, 5));
+        assert_eq!(resume_mode("backtrace", 2), None);
+    }
+
+    #[test]
+    fn stepping_starts_with_user_code() {
+        let (printer, _) = printer::noop();
+        let global_state = GlobalState::new(printer).unwrap();
+        global_state.set_debug_mode(DebugMode::Start);
+        assert!(!should_pause(global_state.debug_mode(), 0));
+
+        without_stepping(&global_state, || {
+            user_code_starting(&global_state);
+            Ok(())
+        }).unwrap();
+        assert_eq!(global_state.debug_mode(), DebugMode::Start);
+
+        user_code_starting(&global_state);
+        assert_eq!(global_state.debug_mode(), DebugMode::StepInto);
+        without_stepping(&global_state, || {
+            assert!(!should_pause(global_state.debug_mode(), 0));
+            Ok(())
+        }).unwrap();
+        assert_eq!(global_state.debug_mode(), DebugMode::StepInto);
+    }
+}
@@ -0,0 +1,328 @@
+use crate::lang::errors::{error, to_crush_error, CrushResult};
+use crate::lang::printer::Printer;
+use crate::util::file::cwd;
+use chrono::{DateTime, Duration, Local};
+use serde_json::json;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/**
+When the ranks of all directories add up to more than this, they are all scaled down, and
+directories whose rank drops below one are forgotten. This keeps the database small, and lets old
+habits fade.
+*/
+const MAX_TOTAL_RANK: f64 = 5000.0;
+const AGING_FACTOR: f64 = 0.9;
+
+/** A directory in the frecency database. */
+#[derive(Clone)]
+pub struct DirectoryEntry {
+    pub path: PathBuf,
+    /** The number of visits, scaled down over time. */
+    pub rank: f64,
+    pub last_visit: DateTime<Local>,
+}
+
+impl DirectoryEntry {
+    /**
+    The frecency of the directory, i.e. its rank weighted by how recently it was last visited.
+    */
+    pub fn score(&self, now: DateTime<Local>) -> f64 {
+        let age = now - self.last_visit;
+        let weight = if age < Duration::hours(1) {
+            4.0
+        } else if age < Duration::days(1) {
+            2.0
+        } else if age < Duration::weeks(1) {
+            0.5
+        } else {
+            0.25
+        };
+        self.rank * weight
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        json!({
+            "path": self.path.to_string_lossy(),
+            "rank": self.rank,
+            "last_visit": self.last_visit.to_rfc3339(),
+        })
+    }
+
+    fn from_json(value: &serde_json::Value) -> Option<DirectoryEntry> {
+        Some(DirectoryEntry {
+            path: PathBuf::from(value.get("path")?.as_str()?),
+            rank: value.get("rank")?.as_f64()?,
+            last_visit: DateTime::parse_from_rfc3339(value.get("last_visit")?.as_str()?)
+                .ok()?
+                .with_timezone(&Local),
+        })
+    }
+}
+
+/**
+Check if all the search terms occur in the path, in order. Matching is case insensitive. If that
+fails, the path also matches if the characters of the terms occur in it in order, so that e.g.
+`prj` matches `projects`.
+*/
+fn fuzzy_match(path: &Path, terms: &[String]) -> bool {
+    let path = path.to_string_lossy().to_lowercase();
+    let mut rest = path.as_str();
+    let mut substrings = true;
+    for term in terms {
+        let term = term.to_lowercase();
+        match rest.find(&term) {
+            Some(idx) => rest = &rest[idx + term.len()..],
+            None => {
+                substrings = false;
+                break;
+            }
+        }
+    }
+    if substrings {
+        return true;
+    }
+    let mut chars = path.chars();
+    terms.iter()
+        .flat_map(|t| t.to_lowercase().chars().collect::<Vec<_>>())
+        .all(|c| chars.any(|p| p == c))
+}
+
+struct DirectoryData {
+    previous: Option<PathBuf>,
+    stack: Vec<PathBuf>,
+    entries: HashMap<PathBuf, DirectoryEntry>,
+    file: Option<PathBuf>,
+    /** The number of lines in the file, including outdated ones. */
+    lines: usize,
+}
+
+/**
+The directory history of the shell: the previous working directory, the directory stack used by
+pushd and popd, and a database of visited directories ranked by frecency, i.e. how frequently and
+recently they were visited.
+
+The database is stored in a file with one JSON object per line. Every visit appends the updated
+entry to the file, so a directory can have several lines, and the last one wins. The file is only
+rewritten when the ranks are aged, or when outdated lines outnumber the current ones.
+*/
+#[derive(Clone)]
+pub struct DirectoryHistory {
+    data: Arc<Mutex<DirectoryData>>,
+}
+
+impl DirectoryHistory {
+    pub fn new() -> DirectoryHistory {
+        DirectoryHistory {
+            data: Arc::from(Mutex::new(DirectoryData {
+                previous: None,
+                stack: Vec::new(),
+                entries: HashMap::new(),
+                file: None,
+                lines: 0,
+            })),
+        }
+    }
+
+    /** Read the frecency database in the specified file, and the number of lines in it. */
+    fn read_lines(file: &Path) -> CrushResult<(HashMap<PathBuf, DirectoryEntry>, usize)> {
+        if !file.exists() {
+            return Ok((HashMap::new(), 0));
+        }
+        let content = to_crush_error(fs::read_to_string(file))?;
+        let mut entries = HashMap::new();
+        let mut lines = 0;
+        for line in content.lines() {
+            lines += 1;
+            if let Some(entry) = serde_json::from_str(line).ok().and_then(|v| DirectoryEntry::from_json(&v)) {
+                entries.insert(entry.path.clone(), entry);
+            }
+        }
+        Ok((entries, lines))
+    }
+
+    /** Read the frecency database in the specified file. */
+    pub fn read(file: &Path) -> CrushResult<Vec<DirectoryEntry>> {
+        Ok(DirectoryHistory::read_lines(file)?.0.into_iter().map(|(_, e)| e).collect())
+    }
+
+    /**
+    Load the frecency database from the specified file. All visits from now on will be saved to
+    the same file.
+    */
+    pub fn load(&self, file: &Path) -> CrushResult<()> {
+        let (entries, lines) = DirectoryHistory::read_lines(file)?;
+        if let Some(parent) = file.parent() {
+            to_crush_error(fs::create_dir_all(parent))?;
+        }
+        let mut data = self.data.lock().unwrap();
+        data.entries = entries;
+        data.file = Some(file.to_path_buf());
+        data.lines = lines;
+        Ok(())
+    }
+
+    /** Rewrite the whole file, dropping outdated lines. */
+    fn save(data: &mut DirectoryData) -> CrushResult<()> {
+        if let Some(file) = &data.file {
+            let mut content = String::new();
+            for entry in data.entries.values() {
+                content.push_str(&entry.to_json().to_string());
+                content.push('\n');
+            }
+            to_crush_error(fs::write(file, content))?;
+            data.lines = data.entries.len();
+        }
+        Ok(())
+    }
+
+    /** Append the current state of the entry for the specified directory to the file. */
+    fn append(data: &mut DirectoryData, dir: &Path) -> CrushResult<()> {
+        if let (Some(file), Some(entry)) = (&data.file, data.entries.get(dir)) {
+            let mut out = to_crush_error(OpenOptions::new().create(true).append(true).open(file))?;
+            to_crush_error(writeln!(out, "{}", entry.to_json()))?;
+            data.lines += 1;
+        }
+        Ok(())
+    }
+
+    fn visit(data: &mut DirectoryData, dir: &Path) -> CrushResult<()> {
+        let now = Local::now();
+        let entry = data.entries.entry(dir.to_path_buf()).or_insert_with(|| DirectoryEntry {
+            path: dir.to_path_buf(),
+            rank: 0.0,
+            last_visit: now,
+        });
+        entry.rank += 1.0;
+        entry.last_visit = now;
+
+        if data.entries.values().map(|e| e.rank).sum::<f64>() > MAX_TOTAL_RANK {
+            for entry in data.entries.values_mut() {
+                entry.rank *= AGING_FACTOR;
+            }
+            data.entries.retain(|_, e| e.rank >= 1.0);
+            DirectoryHistory::save(data)
+        } else if data.lines >= 2 * data.entries.len() {
+            DirectoryHistory::save(data)
+        } else {
+            DirectoryHistory::append(data, dir)
+        }
+    }
+
+    /**
+    Change the working directory, remembering the old one as the previous directory and
+    recording a visit to the new one. Failing to save the visit is reported to the printer, but
+    doesn't make the directory change fail, since it has already happened.
+    */
+    pub fn change_directory(&self, dir: &Path, printer: &Printer) -> CrushResult<()> {
+        let old = cwd()?;
+        to_crush_error(std::env::set_current_dir(dir))?;
+        let new = cwd()?;
+        let mut data = self.data.lock().unwrap();
+        data.previous = Some(old);
+        printer.handle_error(DirectoryHistory::visit(&mut data, &new));
+        Ok(())
+    }
+
+    /** The working directory before the last directory change, if any. */
+    pub fn previous(&self) -> Option<PathBuf> {
+        self.data.lock().unwrap().previous.clone()
+    }
+
+    pub fn push(&self, dir: PathBuf) {
+        self.data.lock().unwrap().stack.push(dir);
+    }
+
+    pub fn pop(&self) -> CrushResult<PathBuf> {
+        match self.data.lock().unwrap().stack.pop() {
+            Some(dir) => Ok(dir),
+            None => error("The directory stack is empty"),
+        }
+    }
+
+    /** The directory stack, with the most recently pushed directory first. */
+    pub fn stack(&self) -> Vec<PathBuf> {
+        self.data.lock().unwrap().stack.iter().rev().cloned().collect()
+    }
+
+    /**
+    All existing directories in the frecency database that match the specified search terms,
+    best match first.
+    */
+    pub fn matches(&self, terms: &[String]) -> Vec<DirectoryEntry> {
+        let entries = self.data.lock().unwrap().entries.values().cloned().collect::<Vec<_>>();
+        DirectoryHistory::rank(entries, terms)
+    }
+
+    /** Filter the specified entries by the search terms, and sort them by frecency. */
+    pub fn rank(mut entries: Vec<DirectoryEntry>, terms: &[String]) -> Vec<DirectoryEntry> {
+        let now = Local::now();
+        entries.retain(|e| fuzzy_match(&e.path, terms) && e.path.is_dir());
+        entries.sort_by(|a, b| b.score(now).partial_cmp(&a.score(now)).unwrap());
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terms(t: &[&str]) -> Vec<String> {
+        t.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn check_fuzzy_match() {
+        let path = Path::new("/home/user/Projects/crush/src");
+        assert!(fuzzy_match(path, &terms(&["crush"])));
+        assert!(fuzzy_match(path, &terms(&["proj", "src"])));
+        assert!(fuzzy_match(path, &terms(&["prj"])));
+        assert!(!fuzzy_match(path, &terms(&["src", "proj", "xyz"])));
+        assert!(!fuzzy_match(path, &terms(&["bin"])));
+    }
+
+    #[test]
+    fn check_frecency_order() {
+        let now = Local::now();
+        let recent = DirectoryEntry { path: PathBuf::from("/"), rank: 2.0, last_visit: now };
+        let old = DirectoryEntry { path: PathBuf::from("/tmp"), rank: 10.0, last_visit: now - Duration::weeks(2) };
+        assert!(recent.score(now) > old.score(now));
+        let ranked = DirectoryHistory::rank(vec![old, recent], &[]);
+        assert_eq!(ranked[0].path, PathBuf::from("/"));
+    }
+
+    #[test]
+    fn check_json_round_trip() {
+        let entry = DirectoryEntry { path: PathBuf::from("/tmp"), rank: 3.5, last_visit: Local::now() };
+        let copy = DirectoryEntry::from_json(&entry.to_json()).unwrap();
+        assert_eq!(copy.path, entry.path);
+        assert_eq!(copy.rank, entry.rank);
+        assert_eq!(copy.last_visit.timestamp(), entry.last_visit.timestamp());
+    }
+
+    #[test]
+    fn check_visits_are_appended() {
+        let file = std::env::temp_dir().join(format!("crush_directories_{}.json", std::process::id()));
+        let _ = fs::remove_file(&file);
+        let history = DirectoryHistory::new();
+        history.load(&file).unwrap();
+        {
+            let mut data = history.data.lock().unwrap();
+            DirectoryHistory::visit(&mut data, Path::new("/")).unwrap();
+            DirectoryHistory::visit(&mut data, Path::new("/tmp")).unwrap();
+            DirectoryHistory::visit(&mut data, Path::new("/tmp")).unwrap();
+            DirectoryHistory::visit(&mut data, Path::new("/")).unwrap();
+            assert_eq!(data.lines, 4);
+            // There are twice as many lines as entries now, so the next visit rewrites the file
+            DirectoryHistory::visit(&mut data, Path::new("/tmp")).unwrap();
+            assert_eq!(data.lines, 2);
+        }
+        let mut entries = DirectoryHistory::read(&file).unwrap();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(entries.iter().map(|e| e.rank).collect::<Vec<_>>(), vec![2.0, 3.0]);
+        fs::remove_file(&file).unwrap();
+    }
+}
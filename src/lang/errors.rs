@@ -9,6 +9,7 @@ pub enum CrushErrorType {
     InvalidArgument(String),
     InvalidData(String),
     GenericError(String),
+    TimeoutError(String),
     BlockError,
     SendError,
     EOFError,
@@ -43,11 +44,16 @@ impl CrushError {
         self.error_type == CrushErrorType::EOFError
     }
 
+    pub fn is_timeout(&self) -> bool {
+        matches!(self.error_type, CrushErrorType::TimeoutError(_))
+    }
+
     pub fn message(&self) -> String {
         match &self.error_type {
             InvalidArgument(s)
             | InvalidData(s)
-            | GenericError(s) => s.clone(),
+            | GenericError(s)
+            | TimeoutError(s) => s.clone(),
             BlockError => "Block error".to_string(),
             SendError => "Send error".to_string(),
             EOFError => "EOF error".to_string(),
@@ -170,6 +176,14 @@ pub fn data_error<T>(message: impl Into<String>) -> CrushResult<T> {
     })
 }
 
+pub fn timeout_error<T>(message: impl Into<String>) -> CrushResult<T> {
+    Err(CrushError {
+        error_type: TimeoutError(message.into()),
+        location: None,
+        definition: None,
+    })
+}
+
 pub fn error<T>(message: impl Into<String>) -> CrushResult<T> {
     Err(CrushError {
         error_type: GenericError(message.into()),
@@ -8,6 +8,7 @@ use std::io::Write;
 use std::path::Path;
 use std::{fs};
 use crate::lang::global_state::GlobalState;
+use crate::lang::debugger;
 
 pub fn file(
     global_env: &Scope,
@@ -65,6 +66,7 @@ pub fn string(
 ) -> CrushResult<()> {
     let jobs = global_state.parser().parse(command, &global_env)?;
     for job_definition in jobs {
+        debugger::before_job(&job_definition, global_env, global_state)?;
         let handle = job_definition.invoke(JobContext::new(
             empty_channel(),
             output.clone(),
@@ -3,6 +3,7 @@ use crate::lang::command::Command;
 use crate::lang::data::dict::Dict;
 use crate::lang::errors::{argument_error_legacy, error, CrushResult};
 use crate::lang::data::list::List;
+use crate::lang::data::set::Set;
 use crate::lang::printer::Printer;
 use crate::lang::data::r#struct::Struct;
 use crate::lang::data::scope::Scope;
@@ -10,9 +11,11 @@ use crate::lang::pipe::{InputStream, ValueReceiver, ValueSender, OutputStream};
 use crate::lang::data::table::Table;
 use crate::lang::value::{Value, ValueType};
 use crate::util::glob::Glob;
+use crate::util::ip::Cidr;
 use crate::util::replace::Replace;
-use chrono::{DateTime, Duration, Local};
+use chrono::{DateTime, Duration, FixedOffset};
 use regex::Regex;
+use std::net::IpAddr;
 use std::path::PathBuf;
 use crate::lang::global_state::GlobalState;
 
@@ -30,6 +33,7 @@ pub trait ArgumentVector {
     fn value(&mut self, idx: usize) -> CrushResult<Value>;
     fn glob(&mut self, idx: usize) -> CrushResult<Glob>;
     fn r#struct(&mut self, idx: usize) -> CrushResult<Struct>;
+    fn set(&mut self, idx: usize) -> CrushResult<Set>;
     fn bool(&mut self, idx: usize) -> CrushResult<bool>;
     fn files(&mut self, printer: &Printer) -> CrushResult<Vec<PathBuf>>;
     fn optional_bool(&mut self, idx: usize) -> CrushResult<Option<bool>>;
@@ -135,6 +139,7 @@ impl ArgumentVector for Vec<Argument> {
     argument_getter!(r#type, ValueType, Type, "type");
     argument_getter!(glob, Glob, Glob, "glob");
     argument_getter!(r#struct, Struct, Struct, "struct");
+    argument_getter!(set, Set, Set, "set");
     argument_getter!(bool, bool, Bool, "bool");
     argument_getter!(file, PathBuf, File, "file");
 
@@ -301,6 +306,7 @@ impl CommandContext {
 pub trait This {
     fn list(self) -> CrushResult<List>;
     fn dict(self) -> CrushResult<Dict>;
+    fn set(self) -> CrushResult<Set>;
     fn string(self) -> CrushResult<String>;
     fn r#struct(self) -> CrushResult<Struct>;
     fn file(self) -> CrushResult<PathBuf>;
@@ -310,12 +316,14 @@ pub trait This {
     fn float(self) -> CrushResult<f64>;
     fn r#type(self) -> CrushResult<ValueType>;
     fn duration(self) -> CrushResult<Duration>;
-    fn time(self) -> CrushResult<DateTime<Local>>;
+    fn time(self) -> CrushResult<DateTime<FixedOffset>>;
     fn table(self) -> CrushResult<Table>;
     fn table_input_stream(self) -> CrushResult<InputStream>;
     fn table_output_stream(self) -> CrushResult<OutputStream>;
     fn binary(self) -> CrushResult<Vec<u8>>;
     fn scope(self) -> CrushResult<Scope>;
+    fn ip(self) -> CrushResult<IpAddr>;
+    fn cidr(self) -> CrushResult<Cidr>;
 }
 
 macro_rules! this_method {
@@ -343,6 +351,7 @@ macro_rules! this_method {
 impl This for Option<Value> {
     this_method!(list, List, List, "list");
     this_method!(dict, Dict, Dict, "dict");
+    this_method!(set, Set, Set, "set");
     this_method!(string, String, String, "string");
     this_method!(r#struct, Struct, Struct, "struct");
     this_method!(file, PathBuf, File, "file");
@@ -353,8 +362,10 @@ impl This for Option<Value> {
     this_method!(float, f64, Float, "float");
     this_method!(r#type, ValueType, Type, "type");
     this_method!(duration, Duration, Duration, "duration");
-    this_method!(time, DateTime<Local>, Time, "time");
+    this_method!(time, DateTime<FixedOffset>, Time, "time");
     this_method!(scope, Scope, Scope, "scope");
+    this_method!(ip, IpAddr, Ip, "ip");
+    this_method!(cidr, Cidr, Cidr, "cidr");
     this_method!(table_input_stream, InputStream, TableInputStream, "table_input_stream");
     this_method!(table_output_stream, OutputStream, TableOutputStream, "table_output_stream");
 
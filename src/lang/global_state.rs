@@ -1,13 +1,35 @@
 use num_format::{SystemLocale, Grouping};
 use crate::lang::errors::{CrushResult, to_crush_error};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
 use crate::lang::threads::ThreadStore;
 use crate::lang::printer::Printer;
 use crate::lang::command::Command;
 use crate::lang::parser::Parser;
+use crate::lang::debugger::DebugMode;
+use crate::lang::history::History;
+use crate::lang::directory_history::DirectoryHistory;
+use crate::lang::table_format::TableFormat;
+use crate::lang::data::dict::Dict;
+use crate::lang::output_format::OutputFormat;
+use crate::lang::value_format::ValueFormat;
+use crate::util::git::StateCache;
+use chrono::Duration;
+
+/** The outcome of an interactive command. */
+#[derive(Clone)]
+pub struct JobStatus {
+    pub duration: Duration,
+    /** The last error reported by the command, if any. */
+    pub error: Option<String>,
+}
 
 struct StateData {
     locale: SystemLocale,
+    trace: bool,
+    debug_mode: DebugMode,
+    debug_paused: bool,
+    pager: bool,
+    output_format: OutputFormat,
 }
 
 #[derive(Clone)]
@@ -17,22 +39,48 @@ pub struct GlobalState {
     printer: Printer,
     exit_status: Arc<Mutex<Option<i32>>>,
     prompt: Arc<Mutex<Option<Command>>>,
+    right_prompt: Arc<Mutex<Option<Command>>>,
+    last_job: Arc<Mutex<Option<JobStatus>>>,
     parser: Parser,
+    history: History,
+    directories: DirectoryHistory,
+    output_lock: Arc<Mutex<()>>,
+    output_printed: Arc<Condvar>,
+    table_format: Arc<Mutex<TableFormat>>,
+    value_format: Arc<Mutex<ValueFormat>>,
+    git: StateCache,
 }
 
 impl GlobalState {
     pub fn new(printer: Printer) -> CrushResult<GlobalState> {
+        let locale = to_crush_error(SystemLocale::default())?;
+        let mut value_format = ValueFormat::default();
+        value_format.set_locale_decimal(locale.decimal());
         Ok(GlobalState {
             data: Arc::from(Mutex::new(
                 StateData {
-                    locale: to_crush_error(SystemLocale::default())?,
+                    locale,
+                    trace: false,
+                    debug_mode: DebugMode::Continue,
+                    debug_paused: false,
+                    pager: false,
+                    output_format: OutputFormat::Pretty,
                 }
             )),
             threads: ThreadStore::new(),
             printer,
             exit_status: Arc::from(Mutex::new(None)),
             prompt: Arc::from(Mutex::new(None)),
+            right_prompt: Arc::from(Mutex::new(None)),
+            last_job: Arc::from(Mutex::new(None)),
             parser: Parser::new(),
+            history: History::new(),
+            directories: DirectoryHistory::new(),
+            output_lock: Arc::from(Mutex::new(())),
+            output_printed: Arc::from(Condvar::new()),
+            table_format: Arc::from(Mutex::new(TableFormat::default())),
+            value_format: Arc::from(Mutex::new(value_format)),
+            git: StateCache::default(),
         })
     }
 
@@ -40,6 +88,19 @@ impl GlobalState {
         &self.parser
     }
 
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    pub fn directories(&self) -> &DirectoryHistory {
+        &self.directories
+    }
+
+    /** The git repository states shown in the prompt, see `StateCache`. */
+    pub fn git(&self) -> &StateCache {
+        &self.git
+    }
+
     pub fn grouping(&self) -> Grouping {
         let data = self.data.lock().unwrap();
         data.locale.grouping()
@@ -53,6 +114,11 @@ impl GlobalState {
         &self.printer
     }
 
+    /** The colour theme of this shell, see crush:theme. It is shared with the printer. */
+    pub fn theme(&self) -> Dict {
+        self.printer.theme()
+    }
+
     pub fn locale(&self) -> SystemLocale {
         let data = self.data.lock().unwrap();
         data.locale.clone()
@@ -70,9 +136,120 @@ impl GlobalState {
 
     pub fn set_locale(&self, new_locale: SystemLocale) {
         let mut data = self.data.lock().unwrap();
+        self.value_format.lock().unwrap().set_locale_decimal(new_locale.decimal());
         data.locale = new_locale;
     }
 
+    /**
+    Turn execution tracing on or off. When tracing is on, every command invocation is printed
+    to standard error before it runs.
+    */
+    pub fn set_trace(&self, trace: bool) {
+        let mut data = self.data.lock().unwrap();
+        data.trace = trace;
+    }
+
+    pub fn trace(&self) -> bool {
+        let data = self.data.lock().unwrap();
+        data.trace
+    }
+
+    /**
+    Turn the pager of the output formatter on or off. The pager is only ever used in interactive
+    mode, where this is kept in sync with crush:config:pager.
+    */
+    pub fn set_pager(&self, pager: bool) {
+        let mut data = self.data.lock().unwrap();
+        data.pager = pager;
+    }
+
+    pub fn pager(&self) -> bool {
+        let data = self.data.lock().unwrap();
+        data.pager
+    }
+
+    /**
+    Set the format the output formatter writes values in. Unless overridden on the command line,
+    this is pretty if standard output is a terminal and json otherwise.
+    */
+    pub fn set_output_format(&self, output_format: OutputFormat) {
+        let mut data = self.data.lock().unwrap();
+        data.output_format = output_format;
+    }
+
+    pub fn output_format(&self) -> OutputFormat {
+        let data = self.data.lock().unwrap();
+        data.output_format
+    }
+
+    /**
+    The output formatter holds this lock while it is printing a value, so that others can wait for
+    it to finish.
+    */
+    pub fn output_lock(&self) -> MutexGuard<'_, ()> {
+        self.output_lock.lock().unwrap()
+    }
+
+    /**
+    Release the output lock until the output formatter has printed another value, then take it
+    again.
+    */
+    pub fn wait_for_printed<'a>(&self, lock: MutexGuard<'a, ()>) -> MutexGuard<'a, ()> {
+        self.output_printed.wait(lock).unwrap()
+    }
+
+    /** Called by the output formatter, while holding the output lock, after printing a value. */
+    pub fn notify_printed(&self) {
+        self.output_printed.notify_all();
+    }
+
+    /** Set the table rendering options used by the output formatter, see crush:table. */
+    pub fn set_table_format(&self, format: TableFormat) {
+        *self.table_format.lock().unwrap() = format;
+    }
+
+    pub fn table_format(&self) -> TableFormat {
+        self.table_format.lock().unwrap().clone()
+    }
+
+    /** Set the value formatting options used by the output formatter, see crush:value_format. */
+    pub fn set_value_format(&self, format: ValueFormat) {
+        *self.value_format.lock().unwrap() = format;
+    }
+
+    pub fn value_format(&self) -> ValueFormat {
+        self.value_format.lock().unwrap().clone()
+    }
+
+    pub fn set_debug_mode(&self, mode: DebugMode) {
+        let mut data = self.data.lock().unwrap();
+        data.debug_mode = mode;
+    }
+
+    pub fn debug_mode(&self) -> DebugMode {
+        let data = self.data.lock().unwrap();
+        data.debug_mode
+    }
+
+    /**
+    Mark the debugger as paused. Returns false if the debugger was already paused, in which case
+    the caller must not start another debugger session.
+    */
+    pub fn begin_debug_session(&self) -> bool {
+        let mut data = self.data.lock().unwrap();
+        if data.debug_paused {
+            false
+        } else {
+            data.debug_paused = true;
+            true
+        }
+    }
+
+    pub fn end_debug_session(&self) {
+        let mut data = self.data.lock().unwrap();
+        data.debug_paused = false;
+    }
+
     pub fn set_prompt(&self, prompt: Option<Command>) {
         let mut data = self.prompt.lock().unwrap();
         *data = prompt;
@@ -82,4 +259,25 @@ impl GlobalState {
         let data = self.prompt.lock().unwrap();
         data.as_ref().map(|a| a.copy())
     }
+
+    pub fn set_right_prompt(&self, prompt: Option<Command>) {
+        let mut data = self.right_prompt.lock().unwrap();
+        *data = prompt;
+    }
+
+    pub fn right_prompt(&self) -> Option<Command> {
+        let data = self.right_prompt.lock().unwrap();
+        data.as_ref().map(|a| a.copy())
+    }
+
+    pub fn set_last_job(&self, status: JobStatus) {
+        let mut data = self.last_job.lock().unwrap();
+        *data = Some(status);
+    }
+
+    /** The outcome of the last interactive command, if any has been run. */
+    pub fn last_job(&self) -> Option<JobStatus> {
+        let data = self.last_job.lock().unwrap();
+        data.clone()
+    }
 }
\ No newline at end of file
@@ -0,0 +1,219 @@
+use crate::lang::errors::{to_crush_error, CrushResult};
+use chrono::{DateTime, Duration, Local};
+use serde_json::json;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/**
+The default maximum number of entries to keep in the history. Older entries are discarded. The
+limit can be changed through crush:config:history_size.
+*/
+pub const MAX_HISTORY_SIZE: usize = 10_000;
+
+#[derive(Clone)]
+pub struct HistoryEntry {
+    pub command: String,
+    pub cwd: PathBuf,
+    pub host: String,
+    pub start: DateTime<Local>,
+    pub duration: Duration,
+    /** The message of the last error reported while running the command, if any. */
+    pub error: Option<String>,
+}
+
+impl HistoryEntry {
+    fn to_json(&self) -> serde_json::Value {
+        json!({
+            "command": self.command,
+            "cwd": self.cwd.to_string_lossy(),
+            "host": self.host,
+            "start": self.start.to_rfc3339(),
+            "duration": self.duration.num_milliseconds(),
+            "error": self.error,
+        })
+    }
+
+    fn from_json(value: &serde_json::Value) -> Option<HistoryEntry> {
+        Some(HistoryEntry {
+            command: value.get("command")?.as_str()?.to_string(),
+            cwd: PathBuf::from(value.get("cwd")?.as_str()?),
+            host: value.get("host")?.as_str()?.to_string(),
+            start: DateTime::parse_from_rfc3339(value.get("start")?.as_str()?)
+                .ok()?
+                .with_timezone(&Local),
+            duration: Duration::milliseconds(value.get("duration")?.as_i64()?),
+            error: value.get("error")?.as_str().map(|s| s.to_string()),
+        })
+    }
+}
+
+/**
+The history of all interactive commands, along with some metadata about every invocation.
+
+The history is stored in a file with one JSON object per line. New entries are appended to the
+file as they are added.
+*/
+#[derive(Clone)]
+pub struct History {
+    entries: Arc<Mutex<Vec<HistoryEntry>>>,
+    file: Arc<Mutex<Option<PathBuf>>>,
+    max_size: Arc<Mutex<usize>>,
+}
+
+impl History {
+    pub fn new() -> History {
+        History {
+            entries: Arc::from(Mutex::new(Vec::new())),
+            file: Arc::from(Mutex::new(None)),
+            max_size: Arc::from(Mutex::new(MAX_HISTORY_SIZE)),
+        }
+    }
+
+    /**
+    Change the maximum number of entries to keep. If there are more entries than that, the oldest
+    ones are discarded. The history file is truncated the next time it is loaded.
+    */
+    pub fn set_max_size(&self, max_size: usize) {
+        *self.max_size.lock().unwrap() = max_size;
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() > max_size {
+            let excess = entries.len() - max_size;
+            entries.drain(..excess);
+        }
+    }
+
+    /**
+    Load the history from the specified file. All entries added from now on will be appended to
+    the same file.
+    */
+    pub fn load(&self, file: &Path) -> CrushResult<()> {
+        let mut entries = Vec::new();
+        let max_size = *self.max_size.lock().unwrap();
+        if file.exists() {
+            for line in to_crush_error(fs::read_to_string(file))?.lines() {
+                if let Some(entry) = serde_json::from_str(line).ok().and_then(|v| HistoryEntry::from_json(&v)) {
+                    entries.push(entry);
+                }
+            }
+            if entries.len() > max_size {
+                entries.drain(..(entries.len() - max_size));
+                let mut content = String::new();
+                for entry in &entries {
+                    content.push_str(&entry.to_json().to_string());
+                    content.push('\n');
+                }
+                to_crush_error(fs::write(file, content))?;
+            }
+        } else if let Some(parent) = file.parent() {
+            to_crush_error(fs::create_dir_all(parent))?;
+        }
+
+        *self.entries.lock().unwrap() = entries;
+        *self.file.lock().unwrap() = Some(file.to_path_buf());
+        Ok(())
+    }
+
+    /**
+    Convert a history file in the old format, with one command per line, into the current format.
+    Nothing is done if the new file already exists. The old file is left in place. Since the old
+    format has no metadata, every entry gets the modification time of the old file as its start
+    time.
+    */
+    pub fn migrate(legacy: &Path, file: &Path) -> CrushResult<()> {
+        if file.exists() || !legacy.exists() {
+            return Ok(());
+        }
+        let start = to_crush_error(fs::metadata(legacy).and_then(|m| m.modified()))
+            .map(DateTime::<Local>::from)?;
+        let mut content = String::new();
+        for line in to_crush_error(fs::read_to_string(legacy))?.lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = HistoryEntry {
+                command: line.to_string(),
+                cwd: PathBuf::new(),
+                host: String::new(),
+                start,
+                duration: Duration::zero(),
+                error: None,
+            };
+            content.push_str(&entry.to_json().to_string());
+            content.push('\n');
+        }
+        if let Some(parent) = file.parent() {
+            to_crush_error(fs::create_dir_all(parent))?;
+        }
+        to_crush_error(fs::write(file, content))
+    }
+
+    pub fn add(&self, entry: HistoryEntry) -> CrushResult<()> {
+        let line = entry.to_json().to_string();
+        {
+            let mut entries = self.entries.lock().unwrap();
+            entries.push(entry);
+            if entries.len() > *self.max_size.lock().unwrap() {
+                entries.remove(0);
+            }
+        }
+
+        if let Some(file) = self.file.lock().unwrap().as_ref() {
+            let mut out = to_crush_error(OpenOptions::new().create(true).append(true).open(file))?;
+            to_crush_error(writeln!(out, "{}", line))?;
+        }
+        Ok(())
+    }
+
+    pub fn entries(&self) -> Vec<HistoryEntry> {
+        self.entries.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_json_round_trip() {
+        let entry = HistoryEntry {
+            command: "ls | head 3".to_string(),
+            cwd: PathBuf::from("/tmp"),
+            host: "localhost".to_string(),
+            start: Local::now(),
+            duration: Duration::milliseconds(1500),
+            error: Some("Unknown variable foo".to_string()),
+        };
+        let copy = HistoryEntry::from_json(&entry.to_json()).unwrap();
+        assert_eq!(copy.command, entry.command);
+        assert_eq!(copy.cwd, entry.cwd);
+        assert_eq!(copy.host, entry.host);
+        assert_eq!(copy.start.timestamp(), entry.start.timestamp());
+        assert_eq!(copy.duration, entry.duration);
+        assert_eq!(copy.error, entry.error);
+    }
+
+    #[test]
+    fn check_migrate() {
+        let dir = std::env::temp_dir().join(format!("crush_history_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let legacy = dir.join("history");
+        let file = dir.join("history.json");
+        fs::write(&legacy, "ls\n\necho 1 2\n").unwrap();
+
+        History::migrate(&legacy, &file).unwrap();
+        let history = History::new();
+        history.load(&file).unwrap();
+        let commands: Vec<String> = history.entries().into_iter().map(|e| e.command).collect();
+        assert_eq!(commands, vec!["ls".to_string(), "echo 1 2".to_string()]);
+        assert!(legacy.exists());
+
+        fs::write(&legacy, "pwd\n").unwrap();
+        History::migrate(&legacy, &file).unwrap();
+        history.load(&file).unwrap();
+        assert_eq!(history.entries().len(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
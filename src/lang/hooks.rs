@@ -0,0 +1,83 @@
+/**
+Hooks are closures that Crush calls when something happens in the shell, e.g. before and after
+every interactive command and on every directory change. They live in lists in the crush:hooks
+struct, so adding a hook is just a matter of pushing a closure onto the right list.
+*/
+
+use crate::lang::argument::Argument;
+use crate::lang::ast::Location;
+use crate::lang::data::list::List;
+use crate::lang::data::r#struct::Struct;
+use crate::lang::data::scope::Scope;
+use crate::lang::errors::{data_error, CrushResult};
+use crate::lang::execution_context::JobContext;
+use crate::lang::global_state::GlobalState;
+use crate::lang::pipe::{black_hole, empty_channel};
+use crate::lang::value::{Value, ValueType};
+
+#[derive(Clone, Copy)]
+pub enum Hook {
+    /** Run before each interactive command, with the named argument command. */
+    Pre,
+    /** Run after each interactive command, with the named arguments command, duration and success. */
+    Post,
+    /** Run after every directory change, with the named arguments previous and directory. */
+    Cd,
+}
+
+impl Hook {
+    fn name(&self) -> &'static str {
+        match self {
+            Hook::Pre => "pre",
+            Hook::Post => "post",
+            Hook::Cd => "cd",
+        }
+    }
+}
+
+/** Create the crush:hooks struct, with an empty list of closures for every hook. */
+pub fn create() -> Struct {
+    Struct::new(
+        [Hook::Pre, Hook::Post, Hook::Cd].iter()
+            .map(|hook| (hook.name(), Value::List(List::new(ValueType::Command, vec![]))))
+            .collect(),
+        None,
+    )
+}
+
+fn hooks(hook: Hook, scope: &Scope) -> CrushResult<Vec<Value>> {
+    match scope.get_absolute_path(vec![
+        "global".to_string(), "crush".to_string(), "hooks".to_string(), hook.name().to_string()])? {
+        Value::List(list) => Ok(list.dump()),
+        v => data_error(format!("Expected crush:hooks:{} to be a list, not {}", hook.name(), v.value_type())),
+    }
+}
+
+fn invoke(value: Value, hook: Hook, arguments: &[(&str, Value)], scope: &Scope, global_state: &GlobalState) -> CrushResult<()> {
+    match value {
+        Value::Command(command) => command.invoke(
+            JobContext::new(empty_channel(), black_hole(), scope.clone(), global_state.clone())
+                .command_context(
+                    arguments.iter()
+                        .map(|(name, value)| Argument::named(name, value.clone(), Location::new(0, 0)))
+                        .collect(),
+                    None)),
+        v => data_error(format!("Expected the {} hook to be a command, not {}", hook.name(), v.value_type())),
+    }
+}
+
+/**
+Call every closure of the specified hook with the specified named arguments. The values the
+closures return are discarded, but anything they print is not. Errors are reported, but they
+don't stop the remaining closures from running, nor are they passed on to the caller.
+*/
+pub fn run(hook: Hook, arguments: &[(&str, Value)], scope: &Scope, global_state: &GlobalState) {
+    match hooks(hook, scope) {
+        Ok(closures) => {
+            for closure in closures {
+                global_state.printer().handle_error(invoke(closure, hook, arguments, scope, global_state));
+            }
+        }
+        Err(e) => global_state.printer().crush_error(e),
+    }
+}
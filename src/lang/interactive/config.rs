@@ -0,0 +1,360 @@
+use crate::lang::argument::Argument;
+use crate::lang::ast::Location;
+use crate::lang::command::{Command, CrushCommand};
+use crate::lang::command::OutputType::Known;
+use crate::lang::data::dict::Dict;
+use crate::lang::data::r#struct::Struct;
+use crate::lang::data::scope::Scope;
+use crate::lang::errors::{argument_error_legacy, data_error, error, mandate, CrushResult};
+use crate::lang::execution_context::{ArgumentVector, CommandContext, JobContext, This};
+use crate::lang::global_state::GlobalState;
+use crate::lang::history::MAX_HISTORY_SIZE;
+use crate::lang::interactive::hint::{HELP_MARKER, KEY_BINDING_MARKERS};
+use crate::lang::interactive::notify::{NotifyStyle, NOTIFY_STYLES};
+use crate::lang::pipe::{empty_channel, pipe};
+use crate::lang::value::{Value, ValueType};
+use rustyline::config::{BellStyle, Configurer};
+use rustyline::{Cmd, CompletionType, EditMode, Editor, Helper, KeyPress};
+use chrono::Duration;
+
+const EDIT_MODES: &[&str] = &["emacs", "vi"];
+const COMPLETION_TYPES: &[&str] = &["list", "circular"];
+const BELL_STYLES: &[&str] = &["audible", "none", "visible"];
+
+/** How long a command must run for, in seconds, before a notification is shown when it finishes. */
+const DEFAULT_NOTIFY_AFTER: i64 = 10;
+
+/** The keys that expand the argument hint into the full help text of the current command. */
+const HELP_KEYS: &[KeyPress] = &[KeyPress::Meta('h'), KeyPress::F(1)];
+
+fn allowed_values(name: &str) -> Option<&'static [&'static str]> {
+    match name {
+        "edit_mode" => Some(EDIT_MODES),
+        "completion" => Some(COMPLETION_TYPES),
+        "bell" => Some(BELL_STYLES),
+        "notify" => Some(NOTIFY_STYLES),
+        _ => None,
+    }
+}
+
+/**
+Parse a key name like "ctrl-t", "alt-f" or "f5" into a key press. "meta-" is accepted as a synonym
+for "alt-".
+*/
+fn parse_key(name: &str) -> CrushResult<KeyPress> {
+    let lower = name.to_lowercase();
+    let single = |s: &str| {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => argument_error_legacy(format!("Invalid key name {}", name)),
+        }
+    };
+    if let Some(rest) = lower.strip_prefix("ctrl-") {
+        Ok(KeyPress::Ctrl(single(rest)?))
+    } else if let Some(rest) = lower.strip_prefix("alt-").or_else(|| lower.strip_prefix("meta-")) {
+        Ok(KeyPress::Meta(single(rest)?))
+    } else if let Some(Ok(n)) = lower.strip_prefix('f').map(|n| n.parse::<u8>()) {
+        if n >= 1 && n <= 12 {
+            Ok(KeyPress::F(n))
+        } else {
+            argument_error_legacy(format!("Invalid function key {}", name))
+        }
+    } else {
+        argument_error_legacy(format!("Invalid key name {}, expected e.g. ctrl-t, alt-f or f5", name))
+    }
+}
+
+fn validate(name: &str, value: &Value) -> CrushResult<()> {
+    match (name, value) {
+        (_, Value::String(s)) => match allowed_values(name) {
+            Some(allowed) if !allowed.contains(&s.as_str()) =>
+                argument_error_legacy(format!(
+                    "Invalid value {} for crush:config:{}, expected one of {}",
+                    s, name, allowed.join(", "))),
+            _ => Ok(()),
+        },
+        ("history_size", Value::Integer(i)) if *i < 1 =>
+            argument_error_legacy("crush:config:history_size must be at least 1"),
+        ("notify_after", Value::Duration(d)) if *d < Duration::zero() =>
+            argument_error_legacy("crush:config:notify_after can not be negative"),
+        ("key_bindings", Value::Dict(bindings)) => {
+            if bindings.len() > KEY_BINDING_MARKERS.len() {
+                return argument_error_legacy(format!(
+                    "At most {} key bindings are supported", KEY_BINDING_MARKERS.len()));
+            }
+            for (key, _) in bindings.elements() {
+                parse_key(&key.to_string())?;
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn config_set(mut context: CommandContext) -> CrushResult<()> {
+    let this = context.this.r#struct()?;
+    let name = context.arguments.string(0)?;
+    let value = context.arguments.value(1)?;
+    let current = mandate(
+        this.get(&name),
+        format!("Unknown configuration option crush:config:{}", name))?;
+    if current.value_type() != value.value_type() {
+        return argument_error_legacy(format!(
+            "Expected crush:config:{} to be of type {}, not {}",
+            name, current.value_type(), value.value_type()));
+    }
+    validate(&name, &value)?;
+    this.set(&name, value);
+    context.output.send(Value::Empty())
+}
+
+/**
+Create the crush:config struct, populated with the default configuration. Assigning to a field
+checks that the new value has the right type and is valid.
+*/
+pub fn create() -> Struct {
+    let class = Struct::new(
+        vec![
+            ("__setattr__", Value::Command(<dyn CrushCommand>::command(
+                config_set, false,
+                vec!["global".to_string(), "crush".to_string(), "config".to_string(), "__setattr__".to_string()],
+                "crush:config:__setattr__ name:string value:any",
+                "Modify the specified configuration option",
+                None,
+                Known(ValueType::Empty),
+                vec![],
+            ))),
+        ],
+        None,
+    );
+    Struct::new(
+        vec![
+            ("edit_mode", Value::string("emacs")),
+            ("completion", Value::string("list")),
+            ("history_size", Value::Integer(MAX_HISTORY_SIZE as i128)),
+            ("history_dedupe", Value::Bool(true)),
+            ("bell", Value::string("audible")),
+            ("key_bindings", Value::Dict(Dict::new(ValueType::String, ValueType::Command))),
+            ("pager", Value::Bool(true)),
+            ("notify", Value::string("bell")),
+            ("notify_after", Value::Duration(Duration::seconds(DEFAULT_NOTIFY_AFTER))),
+        ],
+        Some(class),
+    )
+}
+
+pub struct KeyBinding {
+    key: KeyPress,
+    command: Command,
+}
+
+/**
+The line editor configuration, as read from crush:config.
+*/
+pub struct EditorConfig {
+    edit_mode: EditMode,
+    completion: CompletionType,
+    history_size: usize,
+    history_dedupe: bool,
+    bell: BellStyle,
+    key_bindings: Vec<KeyBinding>,
+    pager: bool,
+    notify: NotifyStyle,
+    notify_after: Duration,
+}
+
+impl Default for EditorConfig {
+    fn default() -> EditorConfig {
+        EditorConfig {
+            edit_mode: EditMode::Emacs,
+            completion: CompletionType::List,
+            history_size: MAX_HISTORY_SIZE,
+            history_dedupe: true,
+            bell: BellStyle::Audible,
+            key_bindings: vec![],
+            pager: true,
+            notify: NotifyStyle::Bell,
+            notify_after: Duration::seconds(DEFAULT_NOTIFY_AFTER),
+        }
+    }
+}
+
+fn field(config: &Struct, name: &str) -> CrushResult<Value> {
+    let value = mandate(config.get(name), format!("Missing configuration option crush:config:{}", name))?;
+    validate(name, &value)?;
+    Ok(value)
+}
+
+fn string_field(config: &Struct, name: &str) -> CrushResult<String> {
+    match field(config, name)? {
+        Value::String(s) => Ok(s),
+        v => data_error(format!("Expected crush:config:{} to be a string, not {}", name, v.value_type())),
+    }
+}
+
+impl EditorConfig {
+    /** Read the configuration from crush:config. */
+    pub fn load(scope: &Scope) -> CrushResult<EditorConfig> {
+        let config = match scope.get_absolute_path(
+            vec!["global".to_string(), "crush".to_string(), "config".to_string()])? {
+            Value::Struct(s) => s,
+            v => return data_error(format!("Expected crush:config to be a struct, not {}", v.value_type())),
+        };
+
+        let edit_mode = match string_field(&config, "edit_mode")?.as_str() {
+            "vi" => EditMode::Vi,
+            _ => EditMode::Emacs,
+        };
+        let completion = match string_field(&config, "completion")?.as_str() {
+            "circular" => CompletionType::Circular,
+            _ => CompletionType::List,
+        };
+        let bell = match string_field(&config, "bell")?.as_str() {
+            "none" => BellStyle::None,
+            "visible" => BellStyle::Visible,
+            _ => BellStyle::Audible,
+        };
+        let history_size = match field(&config, "history_size")? {
+            Value::Integer(i) => i as usize,
+            v => return data_error(format!("Expected crush:config:history_size to be an integer, not {}", v.value_type())),
+        };
+        let history_dedupe = match field(&config, "history_dedupe")? {
+            Value::Bool(b) => b,
+            v => return data_error(format!("Expected crush:config:history_dedupe to be a boolean, not {}", v.value_type())),
+        };
+        let pager = match field(&config, "pager")? {
+            Value::Bool(b) => b,
+            v => return data_error(format!("Expected crush:config:pager to be a boolean, not {}", v.value_type())),
+        };
+        let notify = NotifyStyle::parse(&string_field(&config, "notify")?);
+        let notify_after = match field(&config, "notify_after")? {
+            Value::Duration(d) => d,
+            v => return data_error(format!("Expected crush:config:notify_after to be a duration, not {}", v.value_type())),
+        };
+        let mut key_bindings = Vec::new();
+        match field(&config, "key_bindings")? {
+            Value::Dict(bindings) => {
+                for (key, command) in bindings.elements() {
+                    match command {
+                        Value::Command(command) => key_bindings.push(KeyBinding {
+                            key: parse_key(&key.to_string())?,
+                            command,
+                        }),
+                        v => return data_error(format!(
+                            "Expected the key binding for {} to be a command, not {}", key, v.value_type())),
+                    }
+                }
+            }
+            v => return data_error(format!("Expected crush:config:key_bindings to be a dict, not {}", v.value_type())),
+        }
+
+        Ok(EditorConfig {
+            edit_mode,
+            completion,
+            history_size,
+            history_dedupe,
+            bell,
+            key_bindings,
+            pager,
+            notify,
+            notify_after,
+        })
+    }
+
+    /**
+    Apply this configuration to the editor, replacing the key bindings of the previously applied
+    configuration.
+    */
+    pub fn apply<H: Helper>(&self, editor: &mut Editor<H>, previous: &EditorConfig, global_state: &GlobalState) {
+        editor.set_edit_mode(self.edit_mode);
+        editor.set_completion_type(self.completion);
+        editor.set_max_history_size(self.history_size);
+        editor.set_history_ignore_dups(self.history_dedupe);
+        editor.set_bell_style(self.bell);
+        global_state.history().set_max_size(self.history_size);
+        global_state.set_pager(self.pager);
+
+        for binding in &previous.key_bindings {
+            editor.unbind_sequence(binding.key);
+        }
+        for key in HELP_KEYS {
+            editor.bind_sequence(*key, Cmd::Insert(1, HELP_MARKER.to_string()));
+        }
+        for (binding, marker) in self.key_bindings.iter().zip(KEY_BINDING_MARKERS) {
+            editor.bind_sequence(binding.key, Cmd::Insert(1, marker.to_string()));
+        }
+    }
+
+    /**
+    The notification style to use for a command that ran for the specified duration, if it ran for
+    long enough to deserve one.
+    */
+    pub fn notification(&self, duration: Duration) -> Option<NotifyStyle> {
+        if self.notify != NotifyStyle::None && duration >= self.notify_after {
+            Some(self.notify)
+        } else {
+            None
+        }
+    }
+
+    /** The key binding that inserts the specified marker, if any. */
+    pub fn key_binding(&self, marker: char) -> Option<&KeyBinding> {
+        KEY_BINDING_MARKERS.iter()
+            .position(|m| *m == marker)
+            .and_then(|idx| self.key_bindings.get(idx))
+    }
+}
+
+impl KeyBinding {
+    /**
+    Invoke the command of this key binding with the named arguments line and cursor. If the command
+    returns a string, it replaces the line being edited.
+    */
+    pub fn invoke(&self, line: &str, cursor: usize, env: &Scope, global_state: &GlobalState) -> CrushResult<String> {
+        let (snd, recv) = pipe();
+        self.command.invoke(
+            JobContext::new(empty_channel(), snd, env.clone(), global_state.clone())
+                .command_context(
+                    vec![
+                        Argument::named("line", Value::string(line), Location::new(0, 0)),
+                        Argument::named(
+                            "cursor",
+                            Value::Integer(line[..cursor].chars().count() as i128),
+                            Location::new(0, 0)),
+                    ],
+                    None))?;
+        match recv.recv()? {
+            Value::String(s) => Ok(s),
+            Value::Empty() => Ok(line.to_string()),
+            v => error(format!("Expected key binding to return a string, not {}", v.value_type())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_parse_key() {
+        assert_eq!(parse_key("ctrl-t").unwrap(), KeyPress::Ctrl('t'));
+        assert_eq!(parse_key("Alt-f").unwrap(), KeyPress::Meta('f'));
+        assert_eq!(parse_key("meta-x").unwrap(), KeyPress::Meta('x'));
+        assert_eq!(parse_key("f5").unwrap(), KeyPress::F(5));
+        assert!(parse_key("f13").is_err());
+        assert!(parse_key("ctrl-tab").is_err());
+        assert!(parse_key("x").is_err());
+    }
+
+    #[test]
+    fn check_validate() {
+        assert!(validate("edit_mode", &Value::string("vi")).is_ok());
+        assert!(validate("edit_mode", &Value::string("nano")).is_err());
+        assert!(validate("history_size", &Value::Integer(0)).is_err());
+        assert!(validate("history_dedupe", &Value::Bool(false)).is_ok());
+        assert!(validate("notify", &Value::string("osc9")).is_ok());
+        assert!(validate("notify", &Value::string("popup")).is_err());
+        assert!(validate("notify_after", &Value::Duration(Duration::seconds(-1))).is_err());
+    }
+}
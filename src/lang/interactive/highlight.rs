@@ -0,0 +1,320 @@
+use crate::lang::ast::{TokenNode, TokenType};
+use crate::lang::command::Command;
+use crate::lang::command_invocation::resolve_external_command;
+use crate::lang::data::scope::Scope;
+use crate::lang::value::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/**
+The highlight classes that tokens can be sorted into. The names of the classes are the keys of the
+crush:highlight dict.
+*/
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Highlight {
+    Operator,
+    StringLiteral,
+    FileLiteral,
+    NumericLiteral,
+    Label,
+    Field,
+    Command,
+    Namespace,
+    Variable,
+    Argument,
+    Unknown,
+    Error,
+}
+
+impl Highlight {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Highlight::Operator => "operator",
+            Highlight::StringLiteral => "string_literal",
+            Highlight::FileLiteral => "file_literal",
+            Highlight::NumericLiteral => "numeric_literal",
+            Highlight::Label => "label",
+            Highlight::Field => "field",
+            Highlight::Command => "command",
+            Highlight::Namespace => "namespace",
+            Highlight::Variable => "variable",
+            Highlight::Argument => "argument",
+            Highlight::Unknown => "unknown",
+            Highlight::Error => "error",
+        }
+    }
+}
+
+fn classify_value(value: &Value) -> Highlight {
+    match value {
+        Value::Command(_) => Highlight::Command,
+        Value::Scope(_) => Highlight::Namespace,
+        _ => Highlight::Variable,
+    }
+}
+
+fn is_wildcard(name: &str) -> bool {
+    name.contains('%') || name.contains('?')
+}
+
+fn accepts_argument(command: &Command, name: &str) -> bool {
+    let arguments = command.arguments();
+    arguments.is_empty() || arguments.iter().any(|a| a.named || a.name == name)
+}
+
+/**
+The results of the file system lookups made while classifying labels. Highlighting runs on every
+keystroke, so the same cache is kept while a line is being edited, and every name is only looked
+up once instead of on every redraw.
+*/
+#[derive(Default)]
+pub struct LookupCache {
+    commands: HashMap<String, bool>,
+    files: HashMap<String, bool>,
+}
+
+impl LookupCache {
+    pub fn clear(&mut self) {
+        self.commands.clear();
+        self.files.clear();
+    }
+
+    fn is_external_command(&mut self, name: &str, scope: &Scope) -> bool {
+        *self.commands.entry(name.to_string())
+            .or_insert_with(|| resolve_external_command(name, scope).ok().flatten().is_some())
+    }
+
+    fn is_file(&mut self, name: &str) -> bool {
+        *self.files.entry(name.to_string())
+            .or_insert_with(|| Path::new(name).exists())
+    }
+}
+
+struct Frame {
+    open: TokenType,
+    command: Option<Command>,
+}
+
+/**
+Sort every token of a command line into a highlight class, by resolving labels against the
+specified scope. This is what makes it possible to tell known commands apart from likely typos
+while typing. Labels that aren't variables are looked up in the file system through the
+specified cache.
+*/
+pub fn classify(tokens: &[TokenNode], scope: &Scope, lookups: &mut LookupCache) -> Vec<Option<Highlight>> {
+    let mut res = Vec::with_capacity(tokens.len());
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut command: Option<Command> = None;
+    let mut at_command_start = true;
+    let mut in_signature = false;
+    let mut member_of: Option<Value> = None;
+    let mut declared = HashSet::new();
+
+    for (idx, tok) in tokens.iter().enumerate() {
+        let previous = if idx > 0 { Some(tokens[idx - 1].token_type) } else { None };
+        let next = tokens.get(idx + 1).map(|t| t.token_type);
+
+        let highlight = match tok.token_type {
+            TokenType::SubStart | TokenType::JobStart | TokenType::GetItemStart => {
+                stack.push(Frame { open: tok.token_type, command: command.take() });
+                at_command_start = tok.token_type != TokenType::GetItemStart;
+                Some(Highlight::Operator)
+            }
+
+            TokenType::SubEnd | TokenType::JobEnd | TokenType::GetItemEnd => {
+                let expected = match tok.token_type {
+                    TokenType::SubEnd => TokenType::SubStart,
+                    TokenType::JobEnd => TokenType::JobStart,
+                    _ => TokenType::GetItemStart,
+                };
+                at_command_start = false;
+                match stack.pop() {
+                    Some(frame) if frame.open == expected => {
+                        command = frame.command;
+                        Some(Highlight::Operator)
+                    }
+                    Some(frame) => {
+                        command = frame.command;
+                        Some(Highlight::Error)
+                    }
+                    None => Some(Highlight::Error),
+                }
+            }
+
+            TokenType::Pipe => {
+                if previous == Some(TokenType::JobStart) {
+                    in_signature = true;
+                } else if in_signature {
+                    in_signature = false;
+                    at_command_start = true;
+                } else {
+                    at_command_start = true;
+                    command = None;
+                }
+                Some(Highlight::Operator)
+            }
+
+            TokenType::Separator => {
+                at_command_start = !in_signature;
+                command = None;
+                None
+            }
+
+            TokenType::Colon => Some(Highlight::Operator),
+
+            TokenType::LabelOrWildcard => {
+                let name = tok.data.as_str();
+                let is_member = previous == Some(TokenType::Colon) && member_of.is_some();
+                let value = if is_member {
+                    member_of.take().and_then(|parent| parent.field(name).ok().flatten())
+                } else {
+                    scope.get(name).ok().flatten()
+                };
+                member_of = value.clone();
+                let continues_path = next == Some(TokenType::Colon);
+
+                if in_signature {
+                    declared.insert(name.to_string());
+                    Some(Highlight::Variable)
+                } else if at_command_start && !is_member && next == Some(TokenType::AssignmentOperator) {
+                    at_command_start = false;
+                    declared.insert(name.to_string());
+                    Some(Highlight::Variable)
+                } else if at_command_start {
+                    if !continues_path {
+                        at_command_start = false;
+                    }
+                    match value {
+                        Some(value) => {
+                            if let Value::Command(cmd) = &value {
+                                command = Some(cmd.copy());
+                            }
+                            Some(classify_value(&value))
+                        }
+                        None => {
+                            command = None;
+                            let in_closure = stack.iter().any(|f| f.open == TokenType::JobStart);
+                            if !is_member && (in_closure || declared.contains(name) || is_wildcard(name)) {
+                                Some(Highlight::Label)
+                            } else if !is_member && lookups.is_external_command(name, scope) {
+                                Some(Highlight::Command)
+                            } else if !is_member && lookups.is_file(name) {
+                                Some(Highlight::Label)
+                            } else {
+                                Some(Highlight::Unknown)
+                            }
+                        }
+                    }
+                } else if !is_member && next == Some(TokenType::AssignmentOperator) {
+                    match &command {
+                        Some(cmd) if !accepts_argument(cmd, name) => Some(Highlight::Unknown),
+                        _ => Some(Highlight::Argument),
+                    }
+                } else {
+                    match value {
+                        Some(value) => Some(classify_value(&value)),
+                        None => {
+                            let in_closure = stack.iter().any(|f| f.open == TokenType::JobStart);
+                            if is_member {
+                                Some(Highlight::Unknown)
+                            } else if in_closure
+                                || declared.contains(name)
+                                || is_wildcard(name)
+                                || lookups.is_file(name) {
+                                Some(Highlight::Label)
+                            } else {
+                                Some(Highlight::Unknown)
+                            }
+                        }
+                    }
+                }
+            }
+
+            TokenType::Flag => {
+                let name = tok.data.trim_start_matches("--");
+                match &command {
+                    Some(cmd) if !accepts_argument(cmd, name) => Some(Highlight::Unknown),
+                    _ => Some(Highlight::Argument),
+                }
+            }
+
+            TokenType::Field => Some(Highlight::Field),
+            TokenType::QuotedString | TokenType::Regex => Some(Highlight::StringLiteral),
+            TokenType::QuotedFile | TokenType::FileOrWildcard => Some(Highlight::FileLiteral),
+            TokenType::Integer | TokenType::Float => Some(Highlight::NumericLiteral),
+            TokenType::LogicalOperator | TokenType::UnaryOperator | TokenType::TermOperator |
+            TokenType::FactorOperator | TokenType::ComparisonOperator |
+            TokenType::AssignmentOperator => Some(Highlight::Operator),
+            TokenType::Named | TokenType::Unnamed => Some(Highlight::Operator),
+        };
+
+        if tok.token_type != TokenType::LabelOrWildcard && tok.token_type != TokenType::Colon {
+            member_of = None;
+        }
+        res.push(highlight);
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lang::parser::Parser;
+    use crate::lang::execution_context::CommandContext;
+    use crate::lang::errors::CrushResult;
+    use crate::lang::command::{CrushCommand, OutputType};
+
+    fn noop(_context: CommandContext) -> CrushResult<()> {
+        Ok(())
+    }
+
+    fn classify_line(line: &str) -> Vec<Option<Highlight>> {
+        let root = Scope::create_root();
+        root.declare("answer", Value::Integer(42)).unwrap();
+        root.declare("cmd", Value::Command(<dyn CrushCommand>::command(
+            noop, false, vec!["global".to_string(), "cmd".to_string()], "cmd", "", None,
+            OutputType::Unknown, vec![]))).unwrap();
+        let env = root.create_child(&root, false);
+        let tokens = Parser::new().tokenize(line).unwrap();
+        classify(&tokens, &env, &mut LookupCache::default())
+    }
+
+    #[test]
+    fn known_and_unknown_commands() {
+        assert_eq!(
+            classify_line("cmd answer"),
+            vec![Some(Highlight::Command), Some(Highlight::Variable)]);
+        assert_eq!(
+            classify_line("no_such_command_hopefully"),
+            vec![Some(Highlight::Unknown)]);
+    }
+
+    #[test]
+    fn declared_variables_are_known() {
+        assert_eq!(
+            classify_line("x := 4; cmd x"),
+            vec![
+                Some(Highlight::Variable), Some(Highlight::Operator), Some(Highlight::NumericLiteral),
+                None, Some(Highlight::Command), Some(Highlight::Label)]);
+    }
+
+    #[test]
+    fn lookups_are_cached() {
+        let mut lookups = LookupCache::default();
+        lookups.files.insert("no_such_file_hopefully".to_string(), true);
+        let root = Scope::create_root();
+        let tokens = Parser::new().tokenize("echo no_such_file_hopefully").unwrap();
+        assert_eq!(classify(&tokens, &root, &mut lookups)[1], Some(Highlight::Label));
+        lookups.clear();
+        assert_eq!(classify(&tokens, &root, &mut lookups)[1], Some(Highlight::Unknown));
+    }
+
+    #[test]
+    fn mismatched_brackets() {
+        assert_eq!(
+            classify_line("cmd (answer}"),
+            vec![
+                Some(Highlight::Command), Some(Highlight::Operator),
+                Some(Highlight::Variable), Some(Highlight::Error)]);
+    }
+}
@@ -0,0 +1,215 @@
+use crate::lang::command::{ArgumentDescription, Command};
+use crate::lang::completion::parse::{parse, CompletionCommand, ParseResult, PartialCommandResult};
+use crate::lang::data::scope::Scope;
+use crate::lang::help::full_help;
+use crate::lang::parser::Parser;
+use crate::lang::value::Value;
+use std::collections::HashSet;
+
+/**
+An invisible character that the expand help key binding inserts into the line. Rustyline has no
+way to bind keys to callbacks, so this is how the hinter learns that the full help text should be
+shown instead of the one line signature. Deleting the character collapses the help again. The
+character is zero width, so it does not affect the layout of the line, and it is stripped from the
+line before it is completed, highlighted or executed.
+*/
+pub const HELP_MARKER: char = '\u{1}';
+
+/**
+The invisible characters that custom key bindings insert into the line, one per binding, in the
+order of the bindings. Control characters that the terminal acts on, like bell, backspace and
+shift in/out, are left out.
+*/
+pub const KEY_BINDING_MARKERS: &[char] = &[
+    '\u{2}', '\u{3}', '\u{4}', '\u{5}', '\u{6}',
+    '\u{10}', '\u{11}', '\u{12}', '\u{13}', '\u{14}', '\u{15}', '\u{16}', '\u{17}', '\u{18}',
+    '\u{19}', '\u{1a}',
+];
+
+pub fn is_marker(ch: char) -> bool {
+    ch == HELP_MARKER || KEY_BINDING_MARKERS.contains(&ch)
+}
+
+/**
+Remove all help and key binding markers from a line, and translate a position in the line into
+the corresponding position in the stripped line.
+*/
+pub fn strip_markers(line: &str, pos: usize) -> (String, usize) {
+    let before: usize = line[..pos].chars().filter(|c| is_marker(*c)).map(|c| c.len_utf8()).sum();
+    (line.chars().filter(|c| !is_marker(*c)).collect(), pos - before)
+}
+
+/**
+Translate a position in a line stripped of markers back into a position in the original line.
+*/
+pub fn unstripped_position(line: &str, stripped_pos: usize) -> usize {
+    let mut remaining = stripped_pos;
+    for (idx, ch) in line.char_indices() {
+        if is_marker(ch) {
+            continue;
+        }
+        if remaining == 0 {
+            return idx;
+        }
+        remaining -= ch.len_utf8();
+    }
+    line.len()
+}
+
+fn format_argument(argument: &ArgumentDescription) -> String {
+    let mut res = format!("{}: {}", argument.name, argument.value_type);
+    if let Some(default) = &argument.default {
+        res.push_str(&format!(" = {}", default));
+    }
+    if let Some(allowed) = &argument.allowed {
+        res.push_str(&format!(
+            " ({})",
+            allowed.iter().map(|v| v.to_string()).collect::<Vec<_>>().join("|")));
+    }
+    res
+}
+
+/** The arguments of the command that have not yet been given a value. */
+fn remaining_arguments(cmd: &Command, parse_result: &PartialCommandResult) -> Vec<ArgumentDescription> {
+    let mut named = HashSet::new();
+    let mut unnamed = 0usize;
+    for arg in &parse_result.previous_arguments {
+        match &arg.name {
+            Some(name) => {
+                named.insert(name.clone());
+            }
+            None => unnamed += 1,
+        }
+    }
+
+    cmd.arguments()
+        .iter()
+        .filter(|a| !named.contains(&a.name))
+        .skip(unnamed)
+        .cloned()
+        .collect()
+}
+
+fn signature_hint(parse_result: &PartialCommandResult) -> Option<String> {
+    match &parse_result.command {
+        CompletionCommand::Known(cmd) => {
+            let remaining = remaining_arguments(cmd, parse_result);
+            if remaining.is_empty() {
+                return None;
+            }
+            let mut res = remaining.iter().map(format_argument).collect::<Vec<_>>().join(", ");
+            if let Some(description) = parse_result.last_argument_description().and_then(|d| d.description.as_ref()) {
+                res.push_str(&format!("  # {}", description));
+            }
+            Some(res)
+        }
+        CompletionCommand::Unknown => None,
+    }
+}
+
+fn command_at_cursor(parse_result: ParseResult, scope: &Scope) -> Option<Command> {
+    match parse_result {
+        ParseResult::PartialArgument(p) => match p.command {
+            CompletionCommand::Known(cmd) => Some(cmd),
+            CompletionCommand::Unknown => None,
+        },
+        ParseResult::PartialLabel(label, _) => match scope.get(&label) {
+            Ok(Some(Value::Command(cmd))) => Some(cmd),
+            _ => None,
+        },
+        ParseResult::PartialMember(parent, label) => match parent.field(&label) {
+            Ok(Some(Value::Command(cmd))) => Some(cmd),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/**
+Generate a hint for the command whose argument list the cursor is in.
+
+Normally, the hint is a one line summary of the arguments that have not been given yet, along with
+the description of the argument currently being typed. If the line contains a help marker, the
+full help text of the command is shown instead.
+*/
+pub fn hint(line: &str, pos: usize, scope: &Scope, parser: &Parser) -> Option<String> {
+    let expand = line.contains(HELP_MARKER);
+    let (line, pos) = strip_markers(line, pos);
+    if pos != line.len() {
+        return None;
+    }
+    let parse_result = parse(&line, pos, scope, parser).ok()?;
+    if expand {
+        command_at_cursor(parse_result, scope)
+            .map(|cmd| format!("\n{}", full_help(cmd.help())))
+    } else if let ParseResult::PartialArgument(parse_result) = parse_result {
+        signature_hint(&parse_result)
+            .map(|hint| if line.ends_with(' ') { hint } else { format!(" {}", hint) })
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lang::execution_context::CommandContext;
+    use crate::lang::errors::CrushResult;
+    use signature::signature;
+
+    fn my_cmd(_context: CommandContext) -> CrushResult<()> {
+        Ok(())
+    }
+
+    #[signature(my_cmd, short = "Do the thing.")]
+    struct MyCmdSignature {
+        #[description("the first argument.")]
+        first: String,
+        #[description("the second argument.")]
+        #[default(3)]
+        second: i128,
+        #[values("a", "b")]
+        third: String,
+    }
+
+    fn scope() -> Scope {
+        let root = Scope::create_root();
+        let chld = root.create_namespace("namespace", "bla", Box::new(|env| {
+            MyCmdSignature::declare(env)?;
+            Ok(())
+        })).unwrap();
+        root.r#use(&chld);
+        root
+    }
+
+    #[test]
+    fn check_signature_hint() {
+        let s = scope();
+        assert_eq!(
+            hint("my_cmd ", 7, &s, &Parser::new()),
+            Some("first: string, second: integer = 3, third: string (a|b)  # the first argument.".to_string()));
+        assert_eq!(
+            hint("my_cmd first=\"x\" ", 17, &s, &Parser::new()),
+            Some("second: integer = 3, third: string (a|b)  # the second argument.".to_string()));
+        assert_eq!(hint("my_cmd ", 3, &s, &Parser::new()), None);
+    }
+
+    #[test]
+    fn check_expanded_hint() {
+        let s = scope();
+        let line = format!("my_cmd {}", HELP_MARKER);
+        let res = hint(&line, line.len(), &s, &Parser::new()).unwrap();
+        assert!(res.starts_with("\nmy_cmd"));
+        assert!(res.contains("Do the thing."));
+    }
+
+    #[test]
+    fn check_strip_markers() {
+        let line = format!("ab{}cd", HELP_MARKER);
+        assert_eq!(strip_markers(&line, line.len()), ("abcd".to_string(), 4));
+        assert_eq!(unstripped_position(&line, 1), 1);
+        assert_eq!(unstripped_position(&line, 3), 4);
+        let line = format!("a{}b{}", KEY_BINDING_MARKERS[0], HELP_MARKER);
+        assert_eq!(strip_markers(&line, 3), ("ab".to_string(), 2));
+    }
+}
@@ -1,24 +1,37 @@
+pub mod config;
+mod highlight;
+mod hint;
+pub mod notify;
+pub mod prompt;
 mod rustyline_helper;
 
 use rustyline;
 
 use rustyline::error::ReadlineError;
-use rustyline::{Editor, Config, CompletionType, EditMode, OutputStreamType};
+use rustyline::{Editor, Config, OutputStreamType};
 use crate::util::file::home;
 use std::path::PathBuf;
 use crate::lang::data::scope::Scope;
 use crate::lang::pipe::{ValueSender, empty_channel, pipe, black_hole};
 use crate::lang::errors::{CrushResult, to_crush_error, data_error};
 use crate::lang::execute;
+use crate::lang::debugger;
+use crate::lang::threads::{self, JobId};
+use crate::lang::pretty::wait_for_output;
 
-use crate::lang::global_state::GlobalState;
+use crate::lang::global_state::{GlobalState, JobStatus};
 use crate::lang::command::Command;
 use crate::lang::command_invocation::CommandInvocation;
 use crate::lang::value::{ValueDefinition, Value};
 use crate::lang::ast::Location;
 use crate::lang::execution_context::JobContext;
-
-const DEFAULT_PROMPT: &'static str = "crush# ";
+use crate::lang::history::{History, HistoryEntry};
+use crate::lang::hooks::{self, Hook};
+use crate::lang::interactive::config::EditorConfig;
+use crate::lang::interactive::hint::{strip_markers, KEY_BINDING_MARKERS};
+use crate::util::file::cwd;
+use chrono::Local;
+use sys_info::hostname;
 
 pub fn config_dir() -> CrushResult<PathBuf> {
     to_crush_error(std::env::var("XDG_CONFIG_HOME"))
@@ -30,9 +43,19 @@ pub fn config_dir() -> CrushResult<PathBuf> {
 }
 
 fn crush_history_file() -> CrushResult<PathBuf> {
+    Ok(config_dir()?.join("history.json"))
+}
+
+/** The file that the history was stored in before it recorded anything but the commands. */
+fn legacy_history_file() -> CrushResult<PathBuf> {
     Ok(config_dir()?.join("history"))
 }
 
+/** The file that the frecency database of visited directories is stored in. */
+pub fn crush_directories_file() -> CrushResult<PathBuf> {
+    Ok(config_dir()?.join("directories.json"))
+}
+
 pub fn execute_prompt(
     prompt: Option<Command>,
     env: &Scope,
@@ -72,6 +95,24 @@ pub fn load_init(
     }
 }
 
+/**
+Re-read crush:config and apply it to the editor, so that changes take effect on the next line.
+*/
+fn apply_config<H: rustyline::Helper>(
+    rl: &mut Editor<H>,
+    current: &mut EditorConfig,
+    env: &Scope,
+    global_state: &GlobalState,
+) {
+    match EditorConfig::load(env) {
+        Ok(config) => {
+            config.apply(rl, current, global_state);
+            *current = config;
+        }
+        Err(e) => global_state.printer().crush_error(e),
+    }
+}
+
 pub fn run(
     global_env: Scope,
     pretty_printer: &ValueSender,
@@ -85,8 +126,6 @@ pub fn run(
 
     let config = Config::builder()
         .history_ignore_space(true)
-        .completion_type(CompletionType::List)
-        .edit_mode(EditMode::Emacs)
         .output_stream(OutputStreamType::Stdout)
         .build();
 
@@ -97,24 +136,74 @@ pub fn run(
 
     let mut rl = Editor::with_config(config);
     rl.set_helper(Some(h));
+    let mut editor_config = EditorConfig::default();
+    apply_config(&mut rl, &mut editor_config, &global_env, global_state);
     if let Ok(file) = crush_history_file() {
-        let _ = rl.load_history(&file);
+        if let Ok(legacy) = legacy_history_file() {
+            printer.handle_error(History::migrate(&legacy, &file));
+        }
+        printer.handle_error(global_state.history().load(&file));
     }
+    if let Ok(file) = crush_directories_file() {
+        printer.handle_error(global_state.directories().load(&file));
+    }
+    for entry in global_state.history().entries() {
+        rl.add_history_entry(entry.command);
+    }
+    let mut initial = String::new();
+    let mut job: JobId = 0;
     loop {
+        apply_config(&mut rl, &mut editor_config, &global_env, global_state);
+        global_state.git().start();
         let prompt = match execute_prompt(global_state.prompt(), &global_env, global_state) {
             Ok(s) => s,
             Err(e) => {
                 global_state.printer().crush_error(e);
                 None
             }
-        }.unwrap_or_else(|| DEFAULT_PROMPT.to_string());
-        let readline = rl.readline(&prompt);
+        }.unwrap_or_else(|| prompt::default_prompt(global_state));
+        let right_prompt = match execute_prompt(global_state.right_prompt(), &global_env, global_state) {
+            Ok(s) => s,
+            Err(e) => {
+                global_state.printer().crush_error(e);
+                None
+            }
+        }.unwrap_or_else(|| prompt::default_right_prompt(global_state));
+        global_state.git().stop();
+        if let Some(helper) = rl.helper_mut() {
+            helper.set_right_prompt(right_prompt);
+            helper.clear_lookups();
+        }
+        let readline = rl.readline_with_initial(&prompt, (&initial, ""));
+        initial.clear();
+
+        if let Ok(line) = &readline {
+            if let Some((pos, marker)) = line.char_indices().find(|(_, c)| KEY_BINDING_MARKERS.contains(c)) {
+                let (stripped, cursor) = strip_markers(line, pos);
+                initial = match editor_config.key_binding(marker) {
+                    Some(binding) => match binding.invoke(&stripped, cursor, &global_env, global_state) {
+                        Ok(replacement) => replacement,
+                        Err(e) => {
+                            global_state.printer().crush_error(e);
+                            stripped
+                        }
+                    },
+                    None => stripped,
+                };
+                continue;
+            }
+        }
 
-        match readline {
+        match readline.map(|cmd| strip_markers(&cmd, 0).0) {
             Ok(cmd) if cmd.is_empty() => global_state.threads().reap(global_state.printer()),
             Ok(cmd) => {
-                rl.add_history_entry(&cmd);
                 global_state.threads().reap(global_state.printer());
+                hooks::run(Hook::Pre, &[("command", Value::string(&cmd))], &global_env, global_state);
+                job += 1;
+                threads::set_current_job(Some(job));
+                let start = Local::now();
+                let start_dir = cwd().unwrap_or_default();
+                debugger::user_code_starting(global_state);
                 global_state.printer().handle_error(
                     execute::string(
                         &global_env,
@@ -122,7 +211,38 @@ pub fn run(
                         pretty_printer,
                         global_state,
                     ));
+                wait_for_output(pretty_printer, global_state);
                 global_state.threads().reap(global_state.printer());
+                threads::set_current_job(None);
+                let status = JobStatus {
+                    duration: Local::now() - start,
+                    error: global_state.printer().take_last_error(job),
+                };
+                global_state.set_last_job(status.clone());
+                if let Some(style) = editor_config.notification(status.duration) {
+                    if notify::terminal_idle() {
+                        notify::notify(style, &cmd, &status, global_state.printer());
+                    }
+                }
+                hooks::run(
+                    Hook::Post,
+                    &[
+                        ("command", Value::string(&cmd)),
+                        ("duration", Value::Duration(status.duration)),
+                        ("success", Value::Bool(status.error.is_none())),
+                    ],
+                    &global_env,
+                    global_state);
+                // Deduplication only applies to the line editor, crush:history records every invocation
+                rl.add_history_entry(&cmd);
+                global_state.printer().handle_error(global_state.history().add(HistoryEntry {
+                    command: cmd.trim_end_matches('\n').to_string(),
+                    cwd: start_dir,
+                    host: hostname().unwrap_or_default(),
+                    start,
+                    duration: status.duration,
+                    error: status.error,
+                }));
                 if global_state.exit_status().is_some() {
                     break;
                 }
@@ -140,17 +260,6 @@ pub fn run(
                 break;
             }
         }
-
-        if let Ok(file) = crush_history_file() {
-            if let Err(err) = rl.save_history(&file) {
-                global_state.printer().line(&format!("Error: Failed to save history: {}", err))
-            }
-        }
-    }
-    if let Ok(file) = crush_history_file() {
-        if let Err(err) = rl.save_history(&file) {
-            global_state.printer().line(&format!("Error: Failed to save history: {}", err))
-        }
     }
     Ok(())
 }
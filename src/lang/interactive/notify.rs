@@ -0,0 +1,110 @@
+/**
+Notifications for interactive commands that take a long time to finish. The notification is just
+a line of text and an escape sequence sent to the terminal, so no desktop notification daemon is
+needed. Terminal emulators that understand the OSC 777 or OSC 9 sequences turn them into desktop
+notifications, and most others can be configured to flag the tab when the bell rings.
+*/
+
+use crate::lang::global_state::JobStatus;
+use crate::lang::interactive::prompt::{format_duration, FAILURE_MARKER};
+use crate::lang::printer::Printer;
+use nix::poll::{poll, PollFd, PollFlags};
+
+pub const NOTIFY_STYLES: &[&str] = &["bell", "osc777", "osc9", "none"];
+
+/** The longest part of the command to include in the notification. */
+const MAX_COMMAND_LENGTH: usize = 60;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NotifyStyle {
+    Bell,
+    Osc777,
+    Osc9,
+    None,
+}
+
+impl NotifyStyle {
+    pub fn parse(name: &str) -> NotifyStyle {
+        match name {
+            "osc777" => NotifyStyle::Osc777,
+            "osc9" => NotifyStyle::Osc9,
+            "none" => NotifyStyle::None,
+            _ => NotifyStyle::Bell,
+        }
+    }
+}
+
+/**
+True if the user hasn't typed anything while the command was running. Typing ahead means the user
+is watching the terminal, so there is no point in a notification.
+*/
+pub fn terminal_idle() -> bool {
+    let mut fds = [PollFd::new(0, PollFlags::POLLIN)];
+    !matches!(poll(&mut fds, 0), Ok(n) if n > 0)
+}
+
+/** The first line of the command, shortened to fit in a notification. */
+fn summary(command: &str) -> String {
+    let first = command.trim().lines().next().unwrap_or("");
+    let mut res = first.chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_COMMAND_LENGTH)
+        .collect::<String>();
+    if first.chars().count() > MAX_COMMAND_LENGTH || command.trim().lines().count() > 1 {
+        res.push('…');
+    }
+    res
+}
+
+fn message(command: &str, status: &JobStatus) -> String {
+    format!(
+        "{} {} after {}: {}",
+        if status.error.is_some() { FAILURE_MARKER } else { "✓" },
+        if status.error.is_some() { "Failed" } else { "Finished" },
+        format_duration(status.duration),
+        summary(command))
+}
+
+fn escape_sequence(style: NotifyStyle, message: &str) -> String {
+    match style {
+        NotifyStyle::Bell => "\x07".to_string(),
+        NotifyStyle::Osc777 => format!("\x1b]777;notify;Crush;{}\x07", message),
+        NotifyStyle::Osc9 => format!("\x1b]9;{}\x07", message),
+        NotifyStyle::None => String::new(),
+    }
+}
+
+/** Print a notice that the specified command has finished, and alert the terminal. */
+pub fn notify(style: NotifyStyle, command: &str, status: &JobStatus, printer: &Printer) {
+    let message = message(command, status);
+    printer.line(&format!("{}{}", escape_sequence(style, &message), message));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn check_summary() {
+        assert_eq!(summary("sleep 10"), "sleep 10");
+        assert_eq!(summary("  ls\n"), "ls");
+        assert_eq!(summary("for {\nls\n}"), "for {…");
+        assert_eq!(summary(&"x".repeat(70)), format!("{}…", "x".repeat(60)));
+    }
+
+    #[test]
+    fn check_message() {
+        let ok = JobStatus { duration: Duration::seconds(75), error: None };
+        assert_eq!(message("make", &ok), "✓ Finished after 1m15s: make");
+        let failed = JobStatus { duration: Duration::seconds(12), error: Some("oops".to_string()) };
+        assert_eq!(message("make", &failed), "✗ Failed after 12.0s: make");
+    }
+
+    #[test]
+    fn check_escape_sequence() {
+        assert_eq!(escape_sequence(NotifyStyle::Osc9, "done"), "\x1b]9;done\x07");
+        assert_eq!(escape_sequence(NotifyStyle::Osc777, "done"), "\x1b]777;notify;Crush;done\x07");
+        assert_eq!(escape_sequence(NotifyStyle::None, "done"), "");
+    }
+}
@@ -0,0 +1,213 @@
+/**
+The segments that the default prompt is built from. The same segments are available as commands
+in the crush:segment namespace, so that users can compose their own prompt from them.
+*/
+
+use crate::lang::global_state::GlobalState;
+use crate::util::file::{cwd, home};
+use crate::util::user_map::get_current_username;
+use chrono::Duration;
+use std::path::Path;
+use unicode_width::UnicodeWidthChar;
+
+const GREEN: &str = "\x1b[32m";
+const BLUE: &str = "\x1b[1;34m";
+const MAGENTA: &str = "\x1b[35m";
+const RED: &str = "\x1b[31m";
+const YELLOW: &str = "\x1b[33m";
+const NORMAL: &str = "\x1b[0m";
+
+pub const FAILURE_MARKER: &str = "✗";
+
+pub fn user() -> String {
+    get_current_username().unwrap_or_default()
+}
+
+pub fn host() -> String {
+    sys_info::hostname().unwrap_or_default()
+}
+
+/**
+Format a directory for the prompt. The home directory is replaced by ~, and if abbreviate is true,
+all components but the last are shortened to their first character.
+*/
+fn format_directory(dir: &Path, home: Option<&Path>, abbreviate: bool) -> String {
+    let (prefix, rest) = match home.and_then(|h| dir.strip_prefix(h).ok()) {
+        Some(rest) => ("~".to_string(), rest),
+        None => (String::new(), dir),
+    };
+    let components = rest.iter().map(|c| c.to_string_lossy().to_string()).collect::<Vec<_>>();
+    let len = components.len();
+    let components = components.into_iter().enumerate().map(|(idx, c)| {
+        if abbreviate && idx + 1 < len && c != "/" {
+            c.chars().take(if c.starts_with('.') { 2 } else { 1 }).collect()
+        } else {
+            c
+        }
+    }).collect::<Vec<_>>();
+    match (prefix.is_empty(), components.is_empty()) {
+        (false, true) => prefix,
+        (false, false) => format!("{}/{}", prefix, components.join("/")),
+        (true, _) => match components.split_first() {
+            Some((root, rest)) if root == "/" => format!("/{}", rest.join("/")),
+            _ => components.join("/"),
+        },
+    }
+}
+
+/** The current working directory, formatted for the prompt. */
+pub fn directory(abbreviate: bool) -> String {
+    match cwd() {
+        Ok(dir) => format_directory(&dir, home().ok().as_deref(), abbreviate),
+        Err(_) => "?".to_string(),
+    }
+}
+
+/**
+The branch of the git repository containing the current working directory, followed by a * if the
+work tree has been modified. Empty outside of git repositories.
+*/
+pub fn git(global_state: &GlobalState) -> String {
+    match cwd().ok().and_then(|dir| global_state.git().state(&dir)) {
+        Some(state) => format!("{}{}", state.branch, if state.dirty { "*" } else { "" }),
+        None => String::new(),
+    }
+}
+
+/** A marker if the last interactive command failed, otherwise empty. */
+pub fn status(global_state: &GlobalState) -> String {
+    match global_state.last_job() {
+        Some(job) if job.error.is_some() => FAILURE_MARKER.to_string(),
+        _ => String::new(),
+    }
+}
+
+/** A compact representation of a duration, like 850ms, 2.3s or 1h5m. */
+pub fn format_duration(d: Duration) -> String {
+    let millis = d.num_milliseconds();
+    if millis < 1000 {
+        format!("{}ms", millis)
+    } else if millis < 60_000 {
+        format!("{:.1}s", millis as f64 / 1000.0)
+    } else if millis < 3_600_000 {
+        format!("{}m{}s", millis / 60_000, millis % 60_000 / 1000)
+    } else {
+        format!("{}h{}m", millis / 3_600_000, millis % 3_600_000 / 60_000)
+    }
+}
+
+/**
+How long the last interactive command ran for. Empty if no command has been run yet, or if it was
+quicker than the specified minimum.
+*/
+pub fn duration(global_state: &GlobalState, minimum: Duration) -> String {
+    match global_state.last_job() {
+        Some(job) if job.duration >= minimum => format_duration(job.duration),
+        _ => String::new(),
+    }
+}
+
+fn colored(color: &str, text: &str) -> String {
+    if text.is_empty() {
+        String::new()
+    } else {
+        format!("{}{}{}", color, text, NORMAL)
+    }
+}
+
+/** The prompt used when no prompt has been set using crush:prompt. */
+pub fn default_prompt(global_state: &GlobalState) -> String {
+    let git = git(global_state);
+    format!(
+        "{} {}{}{}# ",
+        colored(GREEN, &format!("{}@{}", user(), host())),
+        colored(BLUE, &directory(true)),
+        if git.is_empty() { "" } else { " " },
+        colored(MAGENTA, &git))
+}
+
+/**
+The right prompt used when no right prompt has been set using crush:right_prompt. It shows if the
+last command failed, and how long it ran for if it took more than a second.
+*/
+pub fn default_right_prompt(global_state: &GlobalState) -> String {
+    [
+        colored(RED, &status(global_state)),
+        colored(YELLOW, &duration(global_state, Duration::seconds(1))),
+    ].iter()
+        .filter(|s| !s.is_empty())
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/** The number of columns the specified text occupies on screen, ignoring ANSI escape sequences. */
+pub fn visible_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\x1b' {
+            if chars.next() == Some('[') {
+                for ch in &mut chars {
+                    if ch.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            }
+        } else {
+            width += ch.width().unwrap_or(0);
+        }
+    }
+    width
+}
+
+/**
+Escape sequences that draw the specified text at the right edge of the current line and then
+return the cursor to where it was, or an empty string if it doesn't fit.
+*/
+pub fn right_aligned(text: &str, columns: usize) -> String {
+    let width = visible_width(text);
+    if text.is_empty() || width + 1 >= columns {
+        return String::new();
+    }
+    format!("\x1b7\x1b[{}G{}\x1b8", columns - width + 1, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_format_directory() {
+        let home = Path::new("/home/user");
+        assert_eq!(format_directory(Path::new("/home/user"), Some(home), true), "~");
+        assert_eq!(format_directory(Path::new("/home/user/projects/crush"), Some(home), true), "~/p/crush");
+        assert_eq!(format_directory(Path::new("/home/user/.config/crush"), Some(home), true), "~/.c/crush");
+        assert_eq!(format_directory(Path::new("/home/user/projects/crush"), Some(home), false), "~/projects/crush");
+        assert_eq!(format_directory(Path::new("/usr/local/bin"), Some(home), true), "/u/l/bin");
+        assert_eq!(format_directory(Path::new("/"), Some(home), true), "/");
+        assert_eq!(format_directory(Path::new("/tmp"), None, true), "/tmp");
+    }
+
+    #[test]
+    fn check_format_duration() {
+        assert_eq!(format_duration(Duration::milliseconds(850)), "850ms");
+        assert_eq!(format_duration(Duration::milliseconds(2345)), "2.3s");
+        assert_eq!(format_duration(Duration::seconds(65)), "1m5s");
+        assert_eq!(format_duration(Duration::minutes(65)), "1h5m");
+    }
+
+    #[test]
+    fn check_visible_width() {
+        assert_eq!(visible_width("abc"), 3);
+        assert_eq!(visible_width("\x1b[1;34mabc\x1b[0m"), 3);
+        assert_eq!(visible_width("✗ 2.3s"), 6);
+    }
+
+    #[test]
+    fn check_right_aligned() {
+        assert_eq!(right_aligned("abc", 10), "\x1b7\x1b[8Gabc\x1b8");
+        assert_eq!(right_aligned("abc", 3), "");
+        assert_eq!(right_aligned("", 10), "");
+    }
+}
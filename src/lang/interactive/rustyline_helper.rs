@@ -8,12 +8,16 @@ use rustyline::error::ReadlineError;
 use rustyline::completion::{Pair, Completer};
 use crate::lang::errors::CrushResult;
 use std::cmp::min;
-use crate::lang::ast::TokenType;
+use crate::lang::interactive::highlight::{self, Highlight, LookupCache};
+use crate::lang::interactive::hint::{self, strip_markers, unstripped_position, KEY_BINDING_MARKERS};
 use crate::lang::value::Value;
 use crate::util::directory_lister::directory_lister;
 use crate::lang::data::scope::Scope;
 use rustyline_derive::Helper;
 use crate::lang::global_state::GlobalState;
+use crate::lang::interactive::prompt;
+use termion::terminal_size;
+use std::cell::RefCell;
 
 #[derive(Helper)]
 pub struct RustylineHelper {
@@ -21,6 +25,8 @@ pub struct RustylineHelper {
     scope: Scope,
     highlighter: MatchingBracketHighlighter,
     hinter: HistoryHinter,
+    right_prompt: String,
+    lookups: RefCell<LookupCache>,
 }
 
 impl RustylineHelper {
@@ -30,44 +36,47 @@ impl RustylineHelper {
             scope,
             highlighter: MatchingBracketHighlighter::new(),
             hinter: HistoryHinter {},
+            right_prompt: String::new(),
+            lookups: RefCell::new(LookupCache::default()),
         }
     }
 
+    /** Set the text shown at the right edge of the line the prompt is on. */
+    pub fn set_right_prompt(&mut self, right_prompt: String) {
+        self.right_prompt = right_prompt;
+    }
+
+    /** Forget the file system lookups made while highlighting the previous line. */
+    pub fn clear_lookups(&mut self) {
+        self.lookups.get_mut().clear();
+    }
+
     fn complete_internal(
         &self,
         line: &str,
         pos: usize,
         _ctx: &Context<'_>,
     ) -> CrushResult<(usize, Vec<Pair>)> {
+        let raw_line = line;
+        let (line, pos) = strip_markers(line, pos);
+        let line = line.as_str();
         let mut res = crate::lang::completion::complete(
             line, pos, &self.scope, &self.state.parser(),&directory_lister())?;
+        // Rustyline wants all candidates to replace the same part of the line, so completions that
+        // start later are extended with the text in between
+        let start = res.iter().map(|c| c.start()).min().unwrap_or(pos);
         let crunched = res.drain(..)
             .map(|c| Pair {
                 display: c.display().to_string(),
-                replacement: c.replacement().to_string(),
+                replacement: format!("{}{}", &line[start..c.start()], c.replacement()),
             }).collect();
-        Ok((pos, crunched))
+        Ok((unstripped_position(raw_line, start), crunched))
     }
 
-    fn get_color(&self, token_type: TokenType) -> Option<String> {
-        if let Ok(Value::Dict(highlight)) = self.scope.get_absolute_path(
+    fn get_color(&self, highlight: Highlight) -> Option<String> {
+        if let Ok(Value::Dict(colors)) = self.scope.get_absolute_path(
             vec!["global".to_string(), "crush".to_string(), "highlight".to_string()]) {
-            use TokenType::*;
-            let res = match token_type {
-                QuotedString => highlight.get(&Value::string("string_literal")),
-                Regex => highlight.get(&Value::string("string_literal")),
-                QuotedFile => highlight.get(&Value::string("file_literal")),
-                LabelOrWildcard => highlight.get(&Value::string("label")),
-                Integer => highlight.get(&Value::string("numeric_literal")),
-                Float => highlight.get(&Value::string("numeric_literal")),
-                Field => highlight.get(&Value::string("field")),
-                Pipe | LogicalOperator | UnaryOperator | TermOperator | FactorOperator |
-                ComparisonOperator | AssignmentOperator | GetItemEnd | GetItemStart | SubEnd |
-                SubStart | JobEnd | JobStart =>
-                    highlight.get(&Value::string("operator")),
-                _ => None,
-            };
-            match res {
+            match colors.get(&Value::string(highlight.name())) {
                 Some(Value::String(s)) => Some(s),
                 _ => None,
             }
@@ -76,24 +85,26 @@ impl RustylineHelper {
         }
     }
 
-    fn highlight_internal(&self, line: &str, _cursor: usize) -> CrushResult<String> {
+    fn highlight_internal(&self, line: &str, cursor: usize) -> CrushResult<String> {
+        // Help markers are zero width, so dropping them does not change the layout of the line
+        let (line, _cursor) = strip_markers(line, cursor);
+        let line = line.as_str();
         let mut res = String::new();
         let mut pos = 0;
-        for tok in self.state.parser().tokenize(
-            &self.state.parser().close_token(line))? {
+        let tokens = self.state.parser().tokenize(
+            &self.state.parser().close_token(line))?;
+        let highlights = highlight::classify(&tokens, &self.scope, &mut self.lookups.borrow_mut());
+        for (tok, highlight) in tokens.iter().zip(highlights) {
             if tok.start >= line.len() {
                 break;
             }
             res.push_str(&line[pos..tok.start]);
             let mut do_reset = false;
-            match self.get_color(tok.token_type) {
-                Some(color) => {
-                    if !color.is_empty() {
-                        do_reset = true;
-                        res.push_str(&color);
-                    }
+            if let Some(color) = highlight.and_then(|h| self.get_color(h)) {
+                if !color.is_empty() {
+                    do_reset = true;
+                    res.push_str(&color);
                 }
-                None => {}
             }
 
             res.push_str(&line[tok.start..min(tok.end, line.len())]);
@@ -103,6 +114,7 @@ impl RustylineHelper {
             }
             pos = tok.end;
         }
+        res.push_str(&line[min(pos, line.len())..]);
         Ok(res)
     }
 }
@@ -128,7 +140,11 @@ impl Completer for RustylineHelper {
 
 impl Hinter for RustylineHelper {
     fn hint(&self, line: &str, pos: usize, ctx: &Context<'_>) -> Option<String> {
-        self.hinter.hint(line, pos, ctx)
+        if line.contains(KEY_BINDING_MARKERS) {
+            return Some("  # press enter to run the key binding".to_string());
+        }
+        hint::hint(line, pos, &self.scope, &self.state.parser())
+            .or_else(|| self.hinter.hint(line, pos, ctx))
     }
 }
 
@@ -143,13 +159,21 @@ impl Highlighter for RustylineHelper {
     fn highlight_prompt<'b, 's: 'b, 'p: 'b>(
         &'s self,
         prompt: &'p str,
-        _default: bool,
+        default: bool,
     ) -> Cow<'b, str> {
-        Borrowed(prompt)
+        /*
+        The line editor calculates the width of the prompt before it is highlighted, so the right
+        prompt can be drawn here without throwing off the cursor position.
+        */
+        match (default, terminal_size()) {
+            (true, Ok((columns, _))) if !self.right_prompt.is_empty() =>
+                Owned(format!("{}{}", prompt::right_aligned(&self.right_prompt, columns as usize), prompt)),
+            _ => Borrowed(prompt),
+        }
     }
 
     fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
-        Owned("\x1b[1m".to_owned() + hint + "\x1b[m")
+        Owned("\x1b[90m".to_owned() + hint + "\x1b[m")
     }
 
     fn highlight_char(&self, _line: &str, _pos: usize) -> bool {
@@ -162,7 +186,10 @@ impl Validator for RustylineHelper {
         &self,
         ctx: &mut validate::ValidationContext,
     ) -> rustyline::Result<validate::ValidationResult> {
-        let input = ctx.input().to_string();
+        if ctx.input().contains(KEY_BINDING_MARKERS) {
+            return Ok(ValidationResult::Valid(None));
+        }
+        let (input, _) = strip_markers(ctx.input(), 0);
         if let Ok(closed) = self.state.parser().close_command(&input) {
             match self.state.parser().ast(&closed) {
                 Ok(_) => Ok(
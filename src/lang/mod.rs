@@ -3,22 +3,32 @@ pub mod ast;
 pub mod command;
 pub mod command_invocation;
 pub mod completion;
+pub mod debugger;
+pub mod directory_history;
 pub mod errors;
 pub mod execute;
 pub mod execution_context;
 pub mod files;
 pub mod global_state;
 pub mod help;
+pub mod history;
+pub mod hooks;
 pub mod job;
 pub mod number;
 pub mod ordered_string_map;
+pub mod output_format;
+pub mod pager;
 pub mod parser;
 pub mod patterns;
 pub mod pretty;
 pub mod printer;
 pub mod serialization;
+pub mod table_format;
 pub mod pipe;
+pub mod theme;
 pub mod threads;
+pub mod tree;
 pub mod value;
+pub mod value_format;
 pub mod data;
 pub mod interactive;
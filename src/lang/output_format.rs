@@ -0,0 +1,230 @@
+/**
+The formats the output formatter can write values in. The pretty format is meant for humans and
+is the default when standard output is a terminal. The others are meant for other programs, and
+are used when the output of crush is piped into another program or redirected to a file, so that
+e.g. `crush script.crush | jq` works without any extra effort.
+*/
+
+use crate::lang::data::binary::BinaryReader;
+use crate::lang::data::list::List;
+use crate::lang::data::r#struct::Struct;
+use crate::lang::data::table::{ColumnType, Row, TableReader};
+use crate::lang::errors::{argument_error_legacy, to_crush_error, CrushResult};
+use crate::lang::pipe::CrushStream;
+use crate::lang::printer::Printer;
+use crate::lang::serialization::serialize;
+use crate::lang::value::Value;
+use crate::lib::io::json::to_json;
+use prost::encoding::encode_varint;
+
+pub const OUTPUT_FORMATS: &[&str] = &["pretty", "json", "tsv", "pup"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    /** Human readable tables. */
+    Pretty,
+    /** One JSON document per line, and one line per row of a table. */
+    Json,
+    /** Tab separated values, with a header line for every table. */
+    Tsv,
+    /** The native crush serialization format, one length prefixed document per value. */
+    Pup,
+}
+
+impl OutputFormat {
+    pub fn parse(name: &str) -> CrushResult<OutputFormat> {
+        match name {
+            "pretty" => Ok(OutputFormat::Pretty),
+            "json" => Ok(OutputFormat::Json),
+            "tsv" => Ok(OutputFormat::Tsv),
+            "pup" => Ok(OutputFormat::Pup),
+            _ => argument_error_legacy(format!(
+                "Invalid output format {}, expected one of {}", name, OUTPUT_FORMATS.join(", "))),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OutputFormat::Pretty => "pretty",
+            OutputFormat::Json => "json",
+            OutputFormat::Tsv => "tsv",
+            OutputFormat::Pup => "pup",
+        }
+    }
+}
+
+/**
+The JSON representation of a value. Values that have no JSON representation, like commands and
+dicts, are represented by their string form instead of failing the whole output.
+*/
+fn json(value: Value) -> serde_json::Value {
+    match value {
+        Value::Empty() => serde_json::Value::Null,
+        value => {
+            let fallback = value.to_string();
+            to_json(value).unwrap_or(serde_json::Value::String(fallback))
+        }
+    }
+}
+
+fn json_row(row: Row, types: &[ColumnType]) -> serde_json::Value {
+    let mut map = serde_json::map::Map::new();
+    for (column, cell) in types.iter().zip(Vec::from(row)) {
+        map.insert(column.name.clone(), json(cell));
+    }
+    serde_json::Value::Object(map)
+}
+
+/** Escape the characters that would break up a TSV cell or line. */
+fn tsv_escape(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '\\' => res.push_str("\\\\"),
+            '\t' => res.push_str("\\t"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            ch => res.push(ch),
+        }
+    }
+    res
+}
+
+fn tsv_line(cells: impl Iterator<Item=String>) -> String {
+    cells.map(|c| tsv_escape(&c)).collect::<Vec<_>>().join("\t")
+}
+
+fn tsv_cell(value: Value) -> String {
+    match value {
+        Value::Empty() => String::new(),
+        value => value.to_string(),
+    }
+}
+
+fn print_stream(format: OutputFormat, stream: &mut impl CrushStream, printer: &Printer) {
+    let types = stream.types().to_vec();
+    if format == OutputFormat::Tsv {
+        printer.line(&tsv_line(types.iter().map(|t| t.name.clone())));
+    }
+    while let Ok(row) = stream.read() {
+        match format {
+            OutputFormat::Tsv => printer.line(&tsv_line(Vec::from(row).into_iter().map(tsv_cell))),
+            _ => printer.line(&json_row(row, &types).to_string()),
+        }
+    }
+}
+
+fn print_list(format: OutputFormat, list: List, printer: &Printer) {
+    for value in list.dump() {
+        match format {
+            OutputFormat::Tsv => printer.line(&tsv_escape(&tsv_cell(value))),
+            _ => printer.line(&json(value).to_string()),
+        }
+    }
+}
+
+fn print_struct(format: OutputFormat, s: Struct, printer: &Printer) {
+    match format {
+        OutputFormat::Tsv => {
+            let elements = s.local_elements();
+            printer.line(&tsv_line(elements.iter().map(|(name, _)| name.clone())));
+            printer.line(&tsv_line(elements.into_iter().map(|(_, value)| tsv_cell(value))));
+        }
+        _ => printer.line(&json(Value::Struct(s)).to_string()),
+    }
+}
+
+fn print_binary(binary: &mut dyn BinaryReader, printer: &Printer) -> CrushResult<()> {
+    let mut buff = vec![0; 64 * 1024];
+    loop {
+        let len = to_crush_error(binary.read(&mut buff))?;
+        if len == 0 {
+            return Ok(());
+        }
+        printer.bytes(&buff[0..len]);
+    }
+}
+
+/**
+The pup document for a value, prefixed with its length as a protobuf varint, so that a reader can
+tell where one document ends and the next one begins. This is the same framing that protobuf
+libraries use for delimited messages.
+*/
+fn pup_document(value: &Value) -> CrushResult<Vec<u8>> {
+    let mut document = Vec::new();
+    serialize(value, &mut document)?;
+    let mut res = Vec::with_capacity(document.len() + 10);
+    encode_varint(document.len() as u64, &mut res);
+    res.extend(document);
+    Ok(res)
+}
+
+/**
+Write a value to standard output in the specified machine readable format. Binary data is written
+as is in the JSON and TSV formats, so that e.g. the contents of a file can be piped through crush.
+*/
+pub fn print_value(format: OutputFormat, value: Value, printer: &Printer) {
+    match (format, value) {
+        (_, Value::Empty()) => {}
+        (OutputFormat::Pup, value) => {
+            match pup_document(&value) {
+                Ok(document) => printer.bytes(&document),
+                Err(e) => printer.crush_error(e),
+            }
+        }
+        (_, Value::TableInputStream(mut stream)) => print_stream(format, &mut stream, printer),
+        (_, Value::Table(table)) => print_stream(format, &mut TableReader::new(table), printer),
+        (_, Value::List(list)) => print_list(format, list, printer),
+        (_, Value::Set(set)) =>
+            print_list(format, List::new(set.element_type(), set.elements()), printer),
+        (_, Value::Struct(s)) => print_struct(format, s, printer),
+        (_, Value::BinaryInputStream(mut binary)) =>
+            printer.handle_error(print_binary(binary.as_mut(), printer)),
+        (_, Value::Binary(data)) => printer.bytes(&data),
+        (OutputFormat::Tsv, value) => printer.line(&tsv_escape(&tsv_cell(value))),
+        (_, value) => printer.line(&json(value).to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lang::data::scope::Scope;
+    use crate::lang::serialization::deserialize;
+    use crate::lang::value::ValueType;
+    use prost::encoding::decode_varint;
+
+    #[test]
+    fn check_parse() {
+        assert_eq!(OutputFormat::parse("tsv").unwrap(), OutputFormat::Tsv);
+        assert_eq!(OutputFormat::parse("pretty").unwrap().name(), "pretty");
+        assert!(OutputFormat::parse("xml").is_err());
+    }
+
+    #[test]
+    fn check_tsv_escape() {
+        assert_eq!(tsv_escape("a b"), "a b");
+        assert_eq!(tsv_escape("a\tb\nc\\d"), "a\\tb\\nc\\\\d");
+    }
+
+    #[test]
+    fn check_json_row() {
+        let types = vec![
+            ColumnType::new("name", ValueType::String),
+            ColumnType::new("size", ValueType::Integer),
+            ColumnType::new("parent", ValueType::Any),
+        ];
+        let row = Row::new(vec![Value::string("a"), Value::Integer(3), Value::Empty()]);
+        assert_eq!(json_row(row, &types).to_string(), r#"{"name":"a","size":3,"parent":null}"#);
+    }
+
+    #[test]
+    fn check_pup_document() {
+        let document = pup_document(&Value::string("a string long enough")).unwrap();
+        let mut buf = document.as_slice();
+        let len = decode_varint(&mut buf).unwrap() as usize;
+        assert_eq!(len, buf.len());
+        let root = Scope::create_root();
+        assert!(deserialize(&buf.to_vec(), &root).unwrap() == Value::string("a string long enough"));
+    }
+}
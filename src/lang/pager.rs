@@ -0,0 +1,370 @@
+/**
+An interactive pager for the output formatter.
+
+Lines are buffered until they no longer fit on the screen. At that point, the pager takes over the
+terminal and shows the output so far, while the output formatter keeps adding lines to it in the
+background. If the output fits on the screen, it is printed as usual.
+*/
+
+use crate::lang::errors::{to_crush_error, CrushResult};
+use crate::lang::printer::Printer;
+use nix::poll::{poll, PollFd, PollFlags};
+use nix::unistd::read;
+use std::cell::RefCell;
+use std::cmp::{max, min};
+use std::io::{stdout, Write};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use termion::event::{parse_event, Event, Key};
+use termion::raw::IntoRawMode;
+use termion::screen::AlternateScreen;
+use termion::terminal_size;
+use unicode_width::UnicodeWidthChar;
+
+/** How often the screen is redrawn to show new lines when no key is pressed, in milliseconds. */
+const REDRAW_INTERVAL: i32 = 100;
+
+/** The number of columns to scroll when scrolling horizontally. */
+const HORIZONTAL_STEP: usize = 8;
+
+struct Buffer {
+    header: Option<String>,
+    lines: Vec<String>,
+    complete: bool,
+    quit: bool,
+}
+
+pub struct Pager {
+    printer: Printer,
+    buffer: Arc<Mutex<Buffer>>,
+    thread: RefCell<Option<JoinHandle<()>>>,
+}
+
+impl Pager {
+    pub fn new(printer: Printer) -> Pager {
+        Pager {
+            printer,
+            buffer: Arc::from(Mutex::new(Buffer {
+                header: None,
+                lines: Vec::new(),
+                complete: false,
+                quit: false,
+            })),
+            thread: RefCell::new(None),
+        }
+    }
+
+    /**
+    Add a header line. The first header line is frozen at the top of the screen while scrolling.
+    Tables that arrive in several chunks repeat their header for every chunk, those repeats are
+    dropped.
+    */
+    pub fn header(&self, line: &str) {
+        let mut buffer = self.buffer.lock().unwrap();
+        match &buffer.header {
+            None if buffer.lines.is_empty() => buffer.header = Some(line.to_string()),
+            Some(header) if header == line => {}
+            _ => buffer.lines.push(line.to_string()),
+        }
+        drop(buffer);
+        self.check_size();
+    }
+
+    pub fn line(&self, line: &str) {
+        {
+            let mut buffer = self.buffer.lock().unwrap();
+            if buffer.quit {
+                return;
+            }
+            buffer.lines.extend(line.split('\n').map(|l| l.to_string()));
+        }
+        self.check_size();
+    }
+
+    /** True if the user has closed the pager, in which case any further output is pointless. */
+    pub fn is_quit(&self) -> bool {
+        self.buffer.lock().unwrap().quit
+    }
+
+    fn check_size(&self) {
+        if self.thread.borrow().is_some() {
+            return;
+        }
+        let len = {
+            let buffer = self.buffer.lock().unwrap();
+            buffer.lines.len() + buffer.header.iter().count()
+        };
+        if len >= self.printer.height() {
+            let buffer = self.buffer.clone();
+            let printer = self.printer.clone();
+            printer.ping();
+            match thread::Builder::new()
+                .name("pager".to_string())
+                .spawn(move || {
+                    if let Err(e) = run(&buffer) {
+                        buffer.lock().unwrap().quit = true;
+                        printer.crush_error(e);
+                    }
+                }) {
+                Ok(handle) => *self.thread.borrow_mut() = Some(handle),
+                Err(e) => self.printer.error(&e.to_string()),
+            }
+        }
+    }
+
+    /**
+    Mark the output as complete. If the pager is showing, wait for the user to close it, otherwise
+    print the buffered output.
+    */
+    pub fn finish(self) {
+        self.buffer.lock().unwrap().complete = true;
+        match self.thread.borrow_mut().take() {
+            Some(handle) => {
+                let _ = handle.join();
+            }
+            None => {
+                let mut buffer = self.buffer.lock().unwrap();
+                if let Some(header) = buffer.header.take() {
+                    self.printer.line(&header);
+                }
+                for line in buffer.lines.drain(..) {
+                    self.printer.line(&line);
+                }
+            }
+        }
+    }
+}
+
+/**
+The part of a line that starts `left` columns in and is at most `width` columns wide.
+*/
+fn slice(line: &str, left: usize, width: usize) -> String {
+    let mut res = String::new();
+    let mut column = 0;
+    let mut styled = false;
+    let mut chars = line.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\x1b' {
+            // Colour escape sequences take up no space and are kept even when the text they
+            // apply to is scrolled out of view, so that the visible part keeps its style.
+            let start = res.len();
+            res.push(ch);
+            for ch in &mut chars {
+                res.push(ch);
+                if ch.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            styled = !matches!(&res[start..], "\x1b[0m" | "\x1b[m");
+            continue;
+        }
+        let w = ch.width().unwrap_or(0);
+        if column >= left && column + w <= left + width {
+            res.push(ch);
+        }
+        column += w;
+        if column >= left + width {
+            break;
+        }
+    }
+    if styled {
+        res.push_str("\x1b[0m");
+    }
+    res
+}
+
+struct View {
+    top: usize,
+    left: usize,
+    search: Option<String>,
+    input: Option<String>,
+    message: Option<String>,
+}
+
+impl View {
+    fn find(&mut self, lines: &[String], forward: bool) {
+        let pattern = match &self.search {
+            Some(p) if !p.is_empty() => p,
+            _ => return,
+        };
+        let found = if forward {
+            (self.top + 1..lines.len()).find(|idx| lines[*idx].contains(pattern.as_str()))
+        } else {
+            (0..min(self.top, lines.len())).rev().find(|idx| lines[*idx].contains(pattern.as_str()))
+        };
+        match found {
+            Some(idx) => self.top = idx,
+            None => self.message = Some(format!("Pattern not found: {}", pattern)),
+        }
+    }
+
+    /** Handle a key press. Returns false when the pager should close. */
+    fn key(&mut self, key: Key, lines: &[String], page: usize) -> bool {
+        self.message = None;
+        if let Some(input) = &mut self.input {
+            match key {
+                Key::Char('\n') => {
+                    self.search = self.input.take();
+                    self.find(lines, true);
+                }
+                Key::Esc | Key::Ctrl('c') => self.input = None,
+                Key::Backspace => {
+                    input.pop();
+                }
+                Key::Char(c) => input.push(c),
+                _ => {}
+            }
+            return true;
+        }
+        let last = lines.len().saturating_sub(page);
+        match key {
+            Key::Char('q') | Key::Char('Q') | Key::Esc | Key::Ctrl('c') => return false,
+            Key::Down | Key::Char('j') | Key::Char('\n') => self.top += 1,
+            Key::Up | Key::Char('k') => self.top = self.top.saturating_sub(1),
+            Key::PageDown | Key::Char(' ') | Key::Char('f') => self.top += page,
+            Key::PageUp | Key::Char('b') => self.top = self.top.saturating_sub(page),
+            Key::Home | Key::Char('g') => self.top = 0,
+            Key::End | Key::Char('G') => self.top = last,
+            Key::Right | Key::Char('l') => self.left += HORIZONTAL_STEP,
+            Key::Left | Key::Char('h') => self.left = self.left.saturating_sub(HORIZONTAL_STEP),
+            Key::Char('/') => self.input = Some(String::new()),
+            Key::Char('n') => self.find(lines, true),
+            Key::Char('N') => self.find(lines, false),
+            _ => {}
+        }
+        self.top = min(self.top, last);
+        true
+    }
+}
+
+fn draw(out: &mut impl Write, buffer: &Buffer, view: &View, width: usize, height: usize) -> CrushResult<()> {
+    let mut screen = format!("{}", termion::clear::All);
+    let mut row = 1;
+    if let Some(header) = &buffer.header {
+        screen.push_str(&format!(
+            "{}{}{}{}",
+            termion::cursor::Goto(1, row), termion::style::Bold,
+            slice(header, view.left, width), termion::style::Reset));
+        row += 1;
+    }
+    let body = height - row as usize;
+    for line in buffer.lines.iter().skip(view.top).take(body) {
+        screen.push_str(&format!("{}{}", termion::cursor::Goto(1, row), slice(line, view.left, width)));
+        row += 1;
+    }
+
+    let status = match (&view.input, &view.message) {
+        (Some(input), _) => format!("/{}", input),
+        (None, Some(message)) => message.clone(),
+        (None, None) => format!(
+            "lines {}-{} of {}{}  (q: quit, /: search, arrows: scroll)",
+            min(view.top + 1, buffer.lines.len()),
+            min(view.top + body, buffer.lines.len()),
+            buffer.lines.len(),
+            if buffer.complete { "" } else { "+" }),
+    };
+    screen.push_str(&format!(
+        "{}{}{}{}",
+        termion::cursor::Goto(1, height as u16), termion::style::Invert,
+        slice(&status, 0, width), termion::style::Reset));
+    to_crush_error(out.write_all(screen.as_bytes()))?;
+    to_crush_error(out.flush())
+}
+
+/** Wait for input for a short while, and return the keys that were pressed, if any. */
+fn read_keys() -> CrushResult<Vec<Key>> {
+    let mut fds = [PollFd::new(0, PollFlags::POLLIN)];
+    if to_crush_error(poll(&mut fds, REDRAW_INTERVAL))? == 0 {
+        return Ok(vec![]);
+    }
+    let mut bytes = [0u8; 64];
+    let len = to_crush_error(read(0, &mut bytes))?;
+    let mut iter = bytes[..len].iter().map(|b| Ok(*b));
+    let mut res = Vec::new();
+    while let Some(Ok(b)) = iter.next() {
+        if let Ok(Event::Key(key)) = parse_event(b, &mut iter) {
+            res.push(key);
+        }
+    }
+    Ok(res)
+}
+
+fn run(buffer: &Arc<Mutex<Buffer>>) -> CrushResult<()> {
+    let mut screen = AlternateScreen::from(to_crush_error(stdout().into_raw_mode())?);
+    write!(screen, "{}", termion::cursor::Hide).ok();
+    let mut view = View { top: 0, left: 0, search: None, input: None, message: None };
+    let res = loop {
+        let (width, height) = match terminal_size() {
+            Ok((w, h)) => (max(w as usize, 10), max(h as usize, 5)),
+            Err(_) => (80, 30),
+        };
+        {
+            let buffer = buffer.lock().unwrap();
+            if let Err(e) = draw(&mut screen, &buffer, &view, width, height) {
+                break Err(e);
+            }
+        }
+        let keys = match read_keys() {
+            Ok(keys) => keys,
+            Err(e) => break Err(e),
+        };
+        let mut buffer = buffer.lock().unwrap();
+        let page = height - 1 - buffer.header.iter().count();
+        if !keys.into_iter().all(|key| view.key(key, &buffer.lines, page)) {
+            buffer.quit = true;
+            break Ok(());
+        }
+    };
+    write!(screen, "{}", termion::cursor::Show).ok();
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_slice() {
+        assert_eq!(slice("abcdef", 0, 3), "abc");
+        assert_eq!(slice("abcdef", 2, 3), "cde");
+        assert_eq!(slice("abcdef", 4, 10), "ef");
+        assert_eq!(slice("ab", 4, 10), "");
+    }
+
+    #[test]
+    fn check_slice_styled() {
+        assert_eq!(slice("a\x1b[1mbc\x1b[0md", 0, 2), "a\x1b[1mb\x1b[0m");
+        assert_eq!(slice("\x1b[35m123\x1b[0m", 1, 10), "\x1b[35m23\x1b[0m");
+        assert_eq!(slice("\x1b[35m123\x1b[0m", 0, 2), "\x1b[35m12\x1b[0m");
+    }
+
+    #[test]
+    fn check_scrolling() {
+        let lines = (0..100).map(|i| i.to_string()).collect::<Vec<_>>();
+        let mut view = View { top: 0, left: 0, search: None, input: None, message: None };
+        assert!(view.key(Key::Char(' '), &lines, 10));
+        assert_eq!(view.top, 10);
+        view.key(Key::Char('G'), &lines, 10);
+        assert_eq!(view.top, 90);
+        view.key(Key::Down, &lines, 10);
+        assert_eq!(view.top, 90);
+        view.key(Key::Char('g'), &lines, 10);
+        assert_eq!(view.top, 0);
+        assert!(!view.key(Key::Char('q'), &lines, 10));
+    }
+
+    #[test]
+    fn check_search() {
+        let lines = vec!["apple", "banana", "cherry", "banana split"]
+            .into_iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let mut view = View { top: 0, left: 0, search: None, input: None, message: None };
+        for key in "/nan\n".chars() {
+            view.key(Key::Char(key), &lines, 2);
+        }
+        assert_eq!(view.top, 1);
+        view.key(Key::Char('n'), &lines, 2);
+        assert_eq!(view.top, 2);
+        view.key(Key::Char('N'), &lines, 2);
+        assert_eq!(view.top, 1);
+    }
+}
@@ -50,6 +50,14 @@ fn close_switch(input: &str) -> String {
     }
 }
 
+fn close_field(input: &str) -> String {
+    if input.ends_with('^') {
+        format!("{}x", input)
+    } else {
+        input.to_string()
+    }
+}
+
 #[derive(Clone)]
 pub struct Parser {
     parser: Arc<Mutex<lalrparser::JobListParser>>,
@@ -83,7 +91,7 @@ impl Parser {
     syntax tree.
     */
     pub fn close_token(&self, input: &str) -> String {
-        close_switch(&close_quote(input))
+        close_field(&close_switch(&close_quote(input)))
     }
 
     /**
@@ -167,6 +175,8 @@ mod tests {
         assert_eq!(p.close_command("a b=").unwrap(), "a b= x");
         assert_eq!(p.close_command("a +").unwrap(), "a + x");
         assert_eq!(p.close_command("a \"").unwrap(), "a \"\"");
+        assert_eq!(p.close_command("a ^").unwrap(), "a ^x");
+        assert_eq!(p.close_command("a {^").unwrap(), "a {^x}");
     }
 
     #[test]
@@ -9,7 +9,7 @@ use crate::lang::data::table::ColumnType;
 use crate::lang::data::table::Row;
 use crate::lang::value::Value;
 use chrono::Duration;
-use crossbeam::{bounded, unbounded, Receiver, Sender};
+use crossbeam::{bounded, unbounded, Receiver, Select, Sender, TryRecvError};
 use lazy_static::lazy_static;
 
 pub type RecvTimeoutError = crossbeam::channel::RecvTimeoutError;
@@ -60,6 +60,11 @@ impl ValueSender {
     pub fn is_pipeline(&self) -> bool {
         self.is_pipeline
     }
+
+    /** True if every value sent so far has been received. */
+    pub fn is_empty(&self) -> bool {
+        self.sender.is_empty()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -73,6 +78,31 @@ impl ValueReceiver {
         to_crush_error(self.receiver.recv())
     }
 
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Value, RecvTimeoutError> {
+        self.receiver.recv_timeout(timeout.to_std().unwrap())
+    }
+
+    /**
+    Receive a value if one is available. Returns None if no value is available and an error if
+    the sending side is gone.
+    */
+    pub fn try_recv(&self) -> CrushResult<Option<Value>> {
+        match self.receiver.try_recv() {
+            Ok(value) => Ok(Some(value)),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => send_error(),
+        }
+    }
+
+    /**
+    Block until a value is available or the sending side is gone, without receiving the value.
+    */
+    pub fn wait_ready(&self) {
+        let mut select = Select::new();
+        select.recv(&self.receiver);
+        select.ready();
+    }
+
     pub fn is_pipeline(&self) -> bool {
         self.is_pipeline
     }
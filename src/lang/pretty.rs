@@ -5,9 +5,10 @@
 use crate::lang::data::binary::BinaryReader;
 use crate::lang::errors::to_crush_error;
 use crate::lang::data::list::ListReader;
+use crate::lang::data::set::SetReader;
 use crate::lang::printer::Printer;
 use crate::lang::pipe::{CrushStream, InputStream, ValueSender, pretty_printer_pipe};
-use crate::lang::data::table::ColumnType;
+use crate::lang::data::table::{ColumnFormat, ColumnType};
 use crate::lang::data::table::Row;
 use crate::lang::data::table::Table;
 use crate::lang::data::table::TableReader;
@@ -15,6 +16,7 @@ use crate::lang::value::Alignment;
 use crate::lang::value::Value;
 use crate::lang::value::ValueType;
 use crate::lang::data::r#struct::Struct;
+use crate::lang::data::dict::Dict;
 use std::cmp::max;
 use std::io::{BufReader, Read};
 use std::thread;
@@ -22,6 +24,13 @@ use chrono::Duration;
 use crate::util::hex::to_hex;
 use crate::lang::global_state::GlobalState;
 use num_format::Grouping;
+use crate::lang::pager::Pager;
+use crate::lang::table_format::{Border, Rule, TableFormat};
+use crate::lang::output_format::{self, OutputFormat};
+use crate::lang::value_format::ValueFormat;
+use crate::lang::tree;
+use crate::lang::theme;
+use termion::is_tty;
 
 trait Width {
     fn width(&self) -> usize;
@@ -39,6 +48,12 @@ impl Width for &str {
     }
 }
 
+/**
+Create the output formatter of this shell, which prints all values sent to it. In interactive mode,
+output that doesn't fit on the screen is shown in a pager, unless crush:config:pager is turned off.
+If the output format isn't pretty, values are written in that machine readable format instead.
+The formatter holds the output lock of the global state while printing.
+*/
 pub fn create_pretty_printer(
     printer: Printer,
     global_state: &GlobalState,
@@ -50,9 +65,86 @@ pub fn create_pretty_printer(
         thread::Builder::new()
             .name("output-formater".to_string())
             .spawn(move || {
-                let mut pp = PrettyPrinter { printer, grouping: global_state.grouping() };
+                loop {
+                    i.wait_ready();
+                    let _lock = global_state.output_lock();
+                    match i.try_recv() {
+                        Ok(Some(val)) => {
+                            print_unlocked(
+                                val, global_state.table_format(), &printer, &global_state,
+                                |pp, val| pp.print_value(val));
+                            global_state.notify_printed();
+                        }
+                        Ok(None) => {}
+                        Err(_) => break,
+                    }
+                }
+            }),
+    ));
+    o
+}
+
+/**
+Print a single value the same way the output formatter does, but using the specified table
+rendering options. This is for commands that print values themselves, like table:show. In pretty
+mode, the value is printed using the print function.
+*/
+pub fn print_formatted(
+    value: Value,
+    format: TableFormat,
+    global_state: &GlobalState,
+    print: impl FnOnce(&PrettyPrinter, Value),
+) {
+    let _lock = global_state.output_lock();
+    print_unlocked(value, format, global_state.printer(), global_state, print);
+}
+
+fn print_unlocked(
+    value: Value,
+    format: TableFormat,
+    printer: &Printer,
+    global_state: &GlobalState,
+    print: impl FnOnce(&PrettyPrinter, Value),
+) {
+    let output_format = global_state.output_format();
+    if output_format != OutputFormat::Pretty {
+        output_format::print_value(output_format, value, printer);
+        return;
+    }
+    let mut pp = PrettyPrinter::new(printer.clone(), global_state.grouping())
+        .with_format(format)
+        .with_value_format(global_state.value_format())
+        .with_theme(global_state.theme());
+    if global_state.pager() && is_tty(&std::io::stdout()) {
+        pp.pager = Some(Pager::new(printer.clone()));
+    }
+    print(&pp, value);
+    if let Some(pager) = pp.pager.take() {
+        pager.finish();
+    }
+}
+
+/**
+Create an output formatter that neither pages nor takes the output lock, for printing while the
+main output formatter may be busy, e.g. from the debugger.
+*/
+pub fn create_simple_pretty_printer(
+    printer: Printer,
+    global_state: &GlobalState,
+) -> ValueSender {
+    let global_state = global_state.clone();
+    let (o, i) = pretty_printer_pipe();
+    let printer_clone = printer.clone();
+    printer_clone.handle_error(to_crush_error(
+        thread::Builder::new()
+            .name("output-formater".to_string())
+            .spawn(move || {
+                let mut pp = PrettyPrinter::new(printer, global_state.grouping())
+                    .with_theme(global_state.theme());
                 while let Ok(val) = i.recv() {
                     pp.grouping = global_state.grouping();
+                    pp.format = global_state.table_format();
+                    pp.value_format = global_state.value_format();
                     pp.print_value(val);
                 }
             }),
@@ -60,9 +152,25 @@ pub fn create_pretty_printer(
     o
 }
 
+/**
+Block until the output formatter has printed every value sent to it so far, so that the prompt is
+not drawn in the middle of the output or on top of the pager.
+*/
+pub fn wait_for_output(output: &ValueSender, global_state: &GlobalState) {
+    let mut lock = global_state.output_lock();
+    while !output.is_empty() {
+        lock = global_state.wait_for_printed(lock);
+    }
+}
+
 pub struct PrettyPrinter {
     printer: Printer,
     grouping: Grouping,
+    format: TableFormat,
+    value_format: ValueFormat,
+    pager: Option<Pager>,
+    /** The theme that values are coloured according to, or None if colours are turned off. */
+    theme: Option<Dict>,
 }
 
 fn is_printable(v: u8) -> bool {
@@ -120,7 +228,60 @@ fn is_text(buff: &[u8]) -> bool {
 
 impl PrettyPrinter {
     pub fn new(printer: Printer, grouping: Grouping) -> PrettyPrinter {
-        PrettyPrinter { printer, grouping }
+        PrettyPrinter {
+            printer,
+            grouping,
+            format: TableFormat::default(),
+            value_format: ValueFormat::default(),
+            pager: None,
+            theme: None,
+        }
+    }
+
+    /** Use the specified table rendering options instead of the defaults. */
+    pub fn with_format(mut self, format: TableFormat) -> PrettyPrinter {
+        self.format = format;
+        self
+    }
+
+    /** Use the specified value formatting options instead of the defaults. */
+    pub fn with_value_format(mut self, value_format: ValueFormat) -> PrettyPrinter {
+        self.value_format = value_format;
+        self
+    }
+
+    /** Colour values according to the specified theme, if output is coloured at all. */
+    pub fn with_theme(mut self, theme: Dict) -> PrettyPrinter {
+        self.theme = if theme::enabled() { Some(theme) } else { None };
+        self
+    }
+
+    /** The theme style of a value, or None if colours are turned off. */
+    fn value_style(&self, value: &Value) -> Option<String> {
+        self.theme.as_ref().and_then(|theme| theme::value_style(theme, value))
+    }
+
+    /** The specified theme element style, or None if colours are turned off. */
+    fn element_style(&self, element: &str) -> Option<String> {
+        self.theme.as_ref().and_then(|theme| theme::style(theme, element))
+    }
+
+    fn line(&self, line: &str) {
+        match &self.pager {
+            Some(pager) => pager.line(line),
+            None => self.printer.line(line),
+        }
+    }
+
+    fn header(&self, line: &str) {
+        match &self.pager {
+            Some(pager) if !line.starts_with(' ') => pager.header(line),
+            _ => self.line(line),
+        }
+    }
+
+    fn is_quit(&self) -> bool {
+        self.pager.as_ref().map(|p| p.is_quit()).unwrap_or(false)
     }
 
     pub fn print_value(&self, cell: Value) {
@@ -129,25 +290,66 @@ impl PrettyPrinter {
             Value::Table(rows) => self.print_stream(&mut TableReader::new(rows), 0),
             Value::BinaryInputStream(mut b) => self.print_binary(b.as_mut(), 0),
             Value::Empty() => {}
+            Value::Struct(_) | Value::List(_) | Value::Dict(_) if tree::depth(&cell) >= tree::AUTO_DEPTH =>
+                self.print_tree(cell, tree::DEFAULT_MAX_DEPTH),
             Value::Struct(data) => {
                 self.print_struct(data, 0)
             }
             Value::List(list) => {
                 if list.len() < 8 {
-                    self.printer.line(list.to_string().as_str())
+                    self.line(list.to_string().as_str())
                 } else {
                     self.print_stream(&mut ListReader::new(list, "value"), 0)
                 }
             }
-            _ => self.printer.line(cell.to_pretty_string(self.grouping).as_str()),
+            Value::Set(set) => {
+                if set.len() < 8 {
+                    self.line(set.to_string().as_str())
+                } else {
+                    self.print_stream(&mut SetReader::new(set), 0)
+                }
+            }
+            _ => self.line(&theme::paint(&cell.to_pretty_string(self.grouping, &self.value_format), self.value_style(&cell).as_deref())),
         };
     }
 
+    /** Print a value as a tree, see the tree module. */
+    pub fn print_tree(&self, value: Value, max_depth: usize) {
+        match tree::render(value, max_depth, self.grouping, &self.value_format) {
+            Ok(lines) => {
+                for line in lines {
+                    self.line(&line);
+                }
+            }
+            Err(e) => self.printer.crush_error(e),
+        }
+    }
+
+    /** Remove the hidden cells from a row. */
+    fn visible_cells(row: Row, visible: &[usize], column_count: usize) -> Row {
+        if visible.len() == column_count {
+            row
+        } else {
+            let cells = Vec::from(row);
+            Row::new(visible.iter().map(|idx| cells[*idx].clone()).collect())
+        }
+    }
+
     fn print_stream(&self, stream: &mut impl CrushStream, indent: usize) {
         let mut data: Vec<Row> = Vec::new();
         let mut has_table = false;
 
-        for val in stream.types().iter() {
+        let column_count = stream.types().len();
+        let visible = stream.types().iter()
+            .enumerate()
+            .filter(|(_, column)| !self.format.is_hidden(&column.name))
+            .map(|(idx, _)| idx)
+            .collect::<Vec<_>>();
+        let types = visible.iter()
+            .map(|idx| stream.types()[*idx].clone())
+            .collect::<Vec<_>>();
+
+        for val in types.iter() {
             match val.cell_type {
                 ValueType::TableInputStream(_) => has_table = true,
                 ValueType::Table(_) => has_table = true,
@@ -155,12 +357,22 @@ impl PrettyPrinter {
             }
         }
 
+        let mut shown = 0;
+        let mut skipped = 0;
         loop {
+            if self.is_quit() {
+                return;
+            }
             match stream.read_timeout(Duration::milliseconds(100)) {
                 Ok(r) => {
-                    data.push(r);
+                    if self.format.row_limit != 0 && shown == self.format.row_limit {
+                        skipped += 1;
+                        continue;
+                    }
+                    shown += 1;
+                    data.push(PrettyPrinter::visible_cells(r, &visible, column_count));
                     if data.len() == self.printer.height() - 1 || has_table {
-                        self.print_partial(data, stream.types(), indent, has_table);
+                        self.print_partial(data, &types, indent, has_table);
                         data = Vec::new();
                         data.drain(..);
                     }
@@ -169,7 +381,7 @@ impl PrettyPrinter {
                     if e.is_disconnected() {
                         break;
                     } else {
-                        self.print_partial(data, stream.types(), indent, has_table);
+                        self.print_partial(data, &types, indent, has_table);
                         data = Vec::new();
                         data.drain(..);
                     }
@@ -177,39 +389,75 @@ impl PrettyPrinter {
             }
         }
         if !data.is_empty() {
-            self.print_partial(data, stream.types(), indent, has_table);
+            self.print_partial(data, &types, indent, has_table);
+        }
+        if skipped > 0 {
+            self.line(&format!(
+                "{}… {} more row{}",
+                " ".repeat(indent * 4), skipped, if skipped == 1 { "" } else { "s" }));
         }
     }
 
     fn calculate_header_width(&self, w: &mut [usize], types: &[ColumnType]) {
         for (idx, val) in types.iter().enumerate() {
-            w[idx] = max(w[idx], val.name.width());
+            w[idx] = max(w[idx], self.format.fit_line(&val.name).width());
+        }
+    }
+
+    /** Format a cell, taking the format hint of its column into account. */
+    fn format_cell(&self, cell: &Value, column: &ColumnType) -> String {
+        match (cell, column.format) {
+            (Value::Integer(size), ColumnFormat::ByteSize) => self.value_format.format_byte_size(*size)
+                .unwrap_or_else(|| cell.to_pretty_string(self.grouping, &self.value_format)),
+            _ => cell.to_pretty_string(self.grouping, &self.value_format),
         }
     }
 
-    fn calculate_body_width(&self, w: &mut [usize], data: &[Row], col_count: usize) {
+    fn calculate_body_width(&self, w: &mut [usize], data: &[Row], types: &[ColumnType]) {
         for r in data {
             for (idx, c) in r.cells().iter().enumerate() {
-                if idx == col_count {
+                if idx == types.len() {
                     break;
                 }
-                let l = c.to_pretty_string(self.grouping).width();
-                w[idx] = max(w[idx], l);
+                for line in self.format.fit(self.format_cell(c, &types[idx])) {
+                    w[idx] = max(w[idx], line.width());
+                }
             }
         }
     }
 
+    fn print_rule(&self, w: &[usize], rule: Rule, indent: usize) {
+        if let Some(line) = self.format.border.rule(w, rule) {
+            self.line(&format!("{}{}", " ".repeat(indent * 4), line));
+        }
+    }
+
     fn print_header(&self, w: &[usize], types: &[ColumnType], indent: usize) {
+        let border = self.format.border;
         let mut header = " ".repeat(indent * 4);
         let last_idx = types.len() - 1;
+        if border != Border::None {
+            self.print_rule(w, Rule::Top, indent);
+            header += border.vertical();
+            header += " ";
+        }
+        let style = self.element_style("header");
         for (idx, val) in types.iter().enumerate() {
             let is_last = idx == last_idx;
-            header += val.name.as_ref();
-            if !is_last {
-                header += &" ".repeat(w[idx] - val.name.width() + 1);
+            let name = self.format.fit_line(&val.name);
+            header += &theme::paint(&name, style.as_deref());
+            if border != Border::None {
+                header += &" ".repeat(w[idx] - name.width() + 1);
+                header += border.vertical();
+                if !is_last {
+                    header += " ";
+                }
+            } else if !is_last {
+                header += &" ".repeat(w[idx] - name.width() + 1);
             }
         }
-        self.printer.line(header.as_str())
+        self.header(header.as_str());
+        self.print_rule(w, Rule::BelowHeader, indent);
     }
 
     fn print_row(
@@ -220,38 +468,21 @@ impl PrettyPrinter {
         rows: &mut Vec<Table>,
         outputs: &mut Vec<InputStream>,
         binaries: &mut Vec<Box<dyn BinaryReader>>,
-        col_count: usize,
+        types: &[ColumnType],
     ) {
+        let border = self.format.border;
         let cell_len = r.len();
-        let mut row = " ".repeat(indent * 4);
+        let col_count = types.len();
         let last_idx = col_count - 1;
+        let mut cells = Vec::new();
         for (idx, c) in r.drain(..).enumerate() {
             if idx == col_count {
                 break;
             }
-            let formated_cell = c.to_pretty_string(self.grouping);
-            let spaces = if idx == cell_len - 1 {
-                "".to_string()
-            } else {
-                " ".repeat(w[idx] - formated_cell.width())
-            };
-            let is_last = idx == last_idx;
-            match c.alignment() {
-                Alignment::Right => {
-                    row += &spaces;
-                    row += &formated_cell;
-                    if !is_last {
-                        row += " ";
-                    }
-                }
-                _ => {
-                    row += &formated_cell;
-                    if !is_last {
-                        row += &spaces;
-                        row += " ";
-                    }
-                }
-            }
+            cells.push((
+                self.format.fit(self.format_cell(&c, &types[idx])),
+                self.format.alignment(&c),
+                self.value_style(&c)));
 
             match c {
                 Value::Table(r) => rows.push(r),
@@ -260,11 +491,58 @@ impl PrettyPrinter {
                 _ => {}
             }
         }
-        self.printer.line(&row);
+
+        let height = cells.iter().map(|(lines, _, _)| lines.len()).max().unwrap_or(1);
+        for line_idx in 0..height {
+            let mut row = " ".repeat(indent * 4);
+            if border != Border::None {
+                row += border.vertical();
+                row += " ";
+            }
+            for (idx, (lines, alignment, style)) in cells.iter().enumerate() {
+                let plain_cell = lines.get(line_idx).map(|l| l.as_str()).unwrap_or("");
+                let padding = if border == Border::None && idx == cell_len - 1 {
+                    0
+                } else {
+                    w[idx] - plain_cell.width()
+                };
+                let formated_cell = &theme::paint(plain_cell, style.as_deref());
+                let is_last = idx == last_idx;
+                let pad_end = border != Border::None || !is_last;
+                match alignment {
+                    Alignment::Right => {
+                        row += &" ".repeat(padding);
+                        row += formated_cell;
+                    }
+                    Alignment::Center => {
+                        row += &" ".repeat(padding / 2);
+                        row += formated_cell;
+                        if pad_end {
+                            row += &" ".repeat(padding - padding / 2);
+                        }
+                    }
+                    Alignment::Left => {
+                        row += formated_cell;
+                        if pad_end {
+                            row += &" ".repeat(padding);
+                        }
+                    }
+                }
+                if border != Border::None {
+                    row += " ";
+                    row += border.vertical();
+                    if !is_last {
+                        row += " ";
+                    }
+                } else if !is_last {
+                    row += " ";
+                }
+            }
+            self.line(&row);
+        }
     }
 
-    fn print_body(&self, w: &[usize], data: Vec<Row>, indent: usize, last_separate: bool) {
-        let col_count = w.len();
+    fn print_body(&self, w: &[usize], types: &[ColumnType], data: Vec<Row>, indent: usize, last_separate: bool) {
         for r in data.into_iter() {
             let mut rows = Vec::new();
             let mut outputs = Vec::new();
@@ -274,7 +552,7 @@ impl PrettyPrinter {
 
             if last_separate {
                 let last = r_vec.remove(r_vec.len()-1);
-                self.print_row(w, r_vec, indent, &mut rows, &mut outputs, &mut binaries, col_count);
+                self.print_row(w, r_vec, indent, &mut rows, &mut outputs, &mut binaries, types);
                 match last {
                     Value::Struct(s) => {
                         self.print_struct(s, indent+1);
@@ -282,7 +560,7 @@ impl PrettyPrinter {
                     _ => panic!("Invalid data"),
                 }
             } else {
-                self.print_row(w, r_vec, indent, &mut rows, &mut outputs, &mut binaries, col_count);
+                self.print_row(w, r_vec, indent, &mut rows, &mut outputs, &mut binaries, types);
             }
 
             for r in rows {
@@ -328,10 +606,10 @@ impl PrettyPrinter {
     }
 
     fn print_partial(&self, data: Vec<Row>, types: &[ColumnType], indent: usize, has_table: bool) {
-        if data.len() == 0 {
+        if data.len() == 0 || types.is_empty() {
             return;
         }
-        if types.len() == 1 && indent == 0 && !has_table {
+        if types.len() == 1 && indent == 0 && !has_table && self.format.border == Border::None {
             self.print_single_column_table(data, types)
         } else {
             let last_separate = types.len() > 0 && indent == 0 && !has_table && types[types.len()-1].cell_type == ValueType::Struct;
@@ -345,10 +623,11 @@ impl PrettyPrinter {
             let mut w = vec![0; types.len()];
 
             self.calculate_header_width(&mut w, types);
-            self.calculate_body_width(&mut w, &data, types.len());
+            self.calculate_body_width(&mut w, &data, types);
 
             self.print_header(&w, types, indent);
-            self.print_body(&w, data, indent, last_separate)
+            self.print_body(&w, types, data, indent, last_separate);
+            self.print_rule(&w, Rule::Bottom, indent);
         }
     }
 
@@ -356,20 +635,21 @@ impl PrettyPrinter {
         let mut data = s.map();
         if data.len() > 0 {
             let max_name_width = data.keys().map(|n| n.len()).max().unwrap();
+            let key_style = self.element_style("struct_key");
             for (name, value) in data.drain() {
-                let ss = value.to_pretty_string(self.grouping);
+                let ss = value.to_pretty_string(self.grouping, &self.value_format);
                 if indent * 4 + max_name_width + ss.width() + 2 < self.printer.width() {
                     let mut line = " ".repeat(4 * indent);
-                    line.push_str(&name);
+                    line.push_str(&theme::paint(&name, key_style.as_deref()));
                     line.push(':');
                     line.push_str(&" ".repeat(max_name_width - name.width() + 1));
-                    line.push_str(&ss);
-                    self.printer.line(&line);
+                    line.push_str(&theme::paint(&ss, self.value_style(&value).as_deref()));
+                    self.line(&line);
                 } else {
                     let mut line = " ".repeat(4 * indent);
-                    line.push_str(&name);
+                    line.push_str(&theme::paint(&name, key_style.as_deref()));
                     line.push(':');
-                    self.printer.line(&line);
+                    self.line(&line);
                     self.print_struct_value(value, indent+1);
                 }
             }
@@ -377,11 +657,11 @@ impl PrettyPrinter {
     }
 
     fn print_struct_value(&self, value: Value, indent: usize) {
-        let ss = value.to_pretty_string(self.grouping);
+        let ss = value.to_pretty_string(self.grouping, &self.value_format);
         if ss.width() + 4 * indent < self.printer.width() {
             let mut line = " ".repeat(4 * indent);
-            line.push_str(&ss);
-            self.printer.line(&line);
+            line.push_str(&theme::paint(&ss, self.value_style(&value).as_deref()));
+            self.line(&line);
         } else {
             match value {
                 Value::Struct(s) => self.print_struct(s, indent),
@@ -392,21 +672,25 @@ impl PrettyPrinter {
                 _ => {
                     let mut line = " ".repeat(4 * indent);
                     line.push_str(&ss);
-                    self.printer.line(&line);
+                    self.line(&line);
                 }
             }
         }
     }
 
     fn print_single_column_table(&self, data: Vec<Row>, types: &[ColumnType]) {
-        self.printer.line(&types[0].name);
+        self.header(&theme::paint(&self.format.fit_line(&types[0].name), self.element_style("header").as_deref()));
         let max_width = self.printer.width();
         let mut columns = 1;
         let mut widths = vec![];
         let mut items_per_column;
+        let styles = data
+            .iter()
+            .map(|s| self.value_style(&s.cells()[0]))
+            .collect::<Vec<_>>();
         let data = data
             .iter()
-            .map(|s| s.cells()[0].to_pretty_string(self.grouping))
+            .map(|s| self.format.fit_line(&self.format_cell(&s.cells()[0], &types[0])))
             .collect::<Vec<_>>();
 
         for cols in (2..50).rev() {
@@ -427,12 +711,12 @@ impl PrettyPrinter {
         for start_idx in 0..lines {
             let mut line = "".to_string();
             for (off, idx) in (start_idx..data.len()).step_by(lines).enumerate() {
-                line += &data[idx];
+                line += &theme::paint(&data[idx], styles[idx].as_deref());
                 if off + 1 < widths.len() {
                     line += &" ".repeat(widths[off] - data[idx].width() + 1);
                 }
             }
-            self.printer.line(&line);
+            self.line(&line);
         }
     }
 }
@@ -9,6 +9,8 @@ enum PrinterMessage {
     CrushError(CrushError),
     Error(String),
     Line(String),
+    Trace(String),
+    Bytes(Vec<u8>),
     //    Lines(Vec<String>),
 }
 
@@ -17,12 +19,22 @@ use std::thread::JoinHandle;
 use termion::terminal_size;
 use std::cmp::max;
 use crate::lang::ast::Location;
+use std::sync::{Arc, Mutex};
+use std::io::Write;
+use std::collections::HashMap;
+use crate::lang::theme;
+use crate::lang::data::dict::Dict;
+use crate::lang::threads::{current_job, JobId};
 
 #[derive(Clone)]
 pub struct Printer {
     source: Option<(String, Location)>,
     sender: Sender<PrinterMessage>,
     pong_receiver: Receiver<()>,
+    /** The message of the most recently reported error of every job that has reported one. */
+    last_error: Arc<Mutex<HashMap<JobId, String>>>,
+    /** The colour theme, see the theme module. Error messages are styled using it. */
+    theme: Dict,
 }
 
 // Too small terminals mean we can't meaningfully print anything, so assume at least this size
@@ -33,15 +45,29 @@ const TERMINAL_MIN_HEIGHT: usize = 5;
 const TERMINAL_FALLBACK_WIDTH: usize = 80;
 const TERMINAL_FALLBACK_HEIGHT: usize = 30;
 
+/** The line reporting an error, styled according to the theme when standard error is a terminal. */
+fn error_line(theme: &Dict, message: &str) -> String {
+    let line = format!("Error: {}", message);
+    if theme::errors_enabled() {
+        theme::paint(&line, theme::style(theme, "error").as_deref())
+    } else {
+        line
+    }
+}
+
 pub fn init() -> (Printer, JoinHandle<()>) {
     let (sender, receiver) = bounded(128);
     let (pong_sender, pong_receiver) = bounded(1);
+    let theme = theme::create();
+    let thread_theme = theme.clone();
 
     (
         Printer {
             sender,
             pong_receiver,
             source: None,
+            last_error: Arc::from(Mutex::new(HashMap::new())),
+            theme,
         },
         thread::Builder::new()
             .name("printer".to_string())
@@ -49,14 +75,20 @@ pub fn init() -> (Printer, JoinHandle<()>) {
                 while let Ok(message) = receiver.recv() {
                     match message {
                         Ping => { let _ = pong_sender.send(()); }
-                        Error(err) => eprintln!("Error: {}", err),
+                        Error(err) => eprintln!("{}", error_line(&thread_theme, &err)),
                         CrushError(err) => {
-                            eprintln!("Error: {}", err.message());
+                            eprintln!("{}", error_line(&thread_theme, &err.message()));
                             if let Some(ctx) = err.context() {
                                 eprintln!("{}", ctx);
                             }
                         }
                         Line(line) => println!("{}", line),
+                        Trace(line) => eprintln!("{}", line),
+                        Bytes(data) => {
+                            let mut stdout = std::io::stdout();
+                            let _ = stdout.write_all(&data);
+                            let _ = stdout.flush();
+                        }
                         //                        Lines(lines) => for line in lines {println!("{}", line)},
                     }
                 }
@@ -74,6 +106,8 @@ pub fn noop() -> (Printer, JoinHandle<()>) {
             sender,
             source: None,
             pong_receiver,
+            last_error: Arc::from(Mutex::new(HashMap::new())),
+            theme: theme::create(),
         },
         thread::Builder::new()
             .name("printer:noop".to_string())
@@ -95,6 +129,13 @@ impl Printer {
             self.sender.send(PrinterMessage::Line(line.to_string())),
         ));
     }
+    /** Write raw data to standard output, e.g. binary or serialized output. */
+    pub fn bytes(&self, data: &[u8]) {
+        self.handle_error(to_crush_error(
+            self.sender.send(PrinterMessage::Bytes(data.to_vec())),
+        ));
+    }
+
     /*
         pub fn lines(&self, lines: Vec<String>) {
             self.handle_error(to_crush_error(self.sender.send(PrinterMessage::Lines(lines))));
@@ -108,6 +149,29 @@ impl Printer {
         }
     }
 
+    /**
+    Returns the message of the most recently reported error of the specified job, if it reported
+    any. Errors that jobs started before it report after this call are forgotten.
+    */
+    pub fn take_last_error(&self, job: JobId) -> Option<String> {
+        let mut last_error = self.last_error.lock().unwrap();
+        let res = last_error.remove(&job);
+        last_error.retain(|j, _| *j > job);
+        res
+    }
+
+    /** Remember the error as the last one of the job the current thread belongs to, if any. */
+    fn record_error(&self, message: String, job: Option<JobId>) {
+        if let Some(job) = job {
+            self.last_error.lock().unwrap().insert(job, message);
+        }
+    }
+
+    /** The colour theme used by this printer. Changes to its entries take effect immediately. */
+    pub fn theme(&self) -> Dict {
+        self.theme.clone()
+    }
+
     pub fn ping(&self) {
         if let Ok(_) = self.sender.send(PrinterMessage::Ping) {
             let _ = self.pong_receiver.recv();
@@ -119,14 +183,31 @@ impl Printer {
             sender: self.sender.clone(),
             source: Some((def.to_string(), location)),
             pong_receiver: self.pong_receiver.clone(),
+            last_error: self.last_error.clone(),
+            theme: self.theme.clone(),
         }
     }
 
     pub fn crush_error(&self, err: CrushError) {
+        self.job_error(err, current_job());
+    }
+
+    /** Report an error of the specified job, which may not be the job of the current thread. */
+    pub fn job_error(&self, err: CrushError, job: Option<JobId>) {
+        self.record_error(err.message(), job);
         let _ = self.sender.send(PrinterMessage::CrushError(err.with_source(&self.source)));
     }
 
+    /**
+    Print a line of execution trace output. Trace output goes to standard error, so that it
+    doesn't get mixed up with the regular output of the traced commands.
+    */
+    pub fn trace(&self, line: &str) {
+        let _ = self.sender.send(PrinterMessage::Trace(line.to_string()));
+    }
+
     pub fn error(&self, err: &str) {
+        self.record_error(err.to_string(), current_job());
         let _ = self.sender.send(PrinterMessage::Error(err.to_string()));
     }
 
@@ -2,6 +2,7 @@ use crate::lang::data::dict::Dict;
 use crate::lang::errors::{to_crush_error, CrushResult};
 use crate::lang::data::list::List;
 use crate::lang::data::r#struct::Struct;
+use crate::lang::data::set::Set;
 use crate::lang::data::scope::Scope;
 use crate::lang::value::{Value, ValueType};
 use model::Element;
@@ -14,6 +15,7 @@ mod dict_serializer;
 mod integer_serializer;
 mod list_serializer;
 mod scope_serializer;
+mod set_serializer;
 mod string_serializer;
 mod struct_serializer;
 mod table_serializer;
@@ -36,6 +38,7 @@ pub struct DeserializationState {
     pub lists: HashMap<usize, List>,
     pub types: HashMap<usize, ValueType>,
     pub dicts: HashMap<usize, Dict>,
+    pub sets: HashMap<usize, Set>,
     pub structs: HashMap<usize, Struct>,
     pub scopes: HashMap<usize, Scope>,
 }
@@ -79,6 +82,7 @@ pub fn deserialize(buf: &Vec<u8>, env: &Scope) -> CrushResult<Value> {
         types: HashMap::new(),
         lists: HashMap::new(),
         dicts: HashMap::new(),
+        sets: HashMap::new(),
         structs: HashMap::new(),
         scopes: HashMap::new(),
         env: env.clone(),
@@ -2,7 +2,7 @@ use crate::lang::errors::{error, CrushResult};
 use crate::lang::serialization::model;
 use crate::lang::serialization::model::{element, Element};
 use crate::lang::serialization::{DeserializationState, Serializable, SerializationState};
-use crate::lang::data::table::{ColumnType, Row, Table};
+use crate::lang::data::table::{ColumnFormat, ColumnType, Row, Table};
 use crate::lang::value::{Value, ValueType};
 
 impl Serializable<ColumnType> for ColumnType {
@@ -15,7 +15,10 @@ impl Serializable<ColumnType> for ColumnType {
             Ok(ColumnType::new(
                 &String::deserialize(t.name as usize, elements, state)?,
                 ValueType::deserialize(t.r#type as usize, elements, state)?,
-            ))
+            ).with_format(match t.format {
+                1 => ColumnFormat::ByteSize,
+                _ => ColumnFormat::Plain,
+            }))
         } else {
             error("Expected a table")
         }
@@ -31,6 +34,10 @@ impl Serializable<ColumnType> for ColumnType {
         let mut stype = model::ColumnType::default();
         stype.name = self.name.serialize(elements, state)? as u64;
         stype.r#type = self.cell_type.serialize(elements, state)? as u64;
+        stype.format = match self.format {
+            ColumnFormat::Plain => model::column_type::Format::Plain,
+            ColumnFormat::ByteSize => model::column_type::Format::ByteSize,
+        } as i32;
         elements[idx].element = Some(element::Element::ColumnType(stype));
         Ok(idx)
     }
@@ -1,8 +1,9 @@
 use crate::lang::command::CrushCommand;
 use crate::lang::data::dict::Dict;
-use crate::lang::errors::{error, to_crush_error, CrushResult};
+use crate::lang::errors::{error, mandate, to_crush_error, CrushResult};
 use crate::lang::data::list::List;
 use crate::lang::data::r#struct::Struct;
+use crate::lang::data::set::Set;
 use crate::lang::data::scope::Scope;
 use crate::lang::serialization::model;
 use crate::lang::serialization::model::{element, Element};
@@ -10,8 +11,10 @@ use crate::lang::serialization::{DeserializationState, Serializable, Serializati
 use crate::lang::data::table::Table;
 use crate::lang::value::{Value, ValueType};
 use crate::util::glob::Glob;
+use crate::util::ip::{parse_ip, Cidr};
+use crate::util::time;
 use chrono::offset::TimeZone;
-use chrono::{Duration, Local};
+use chrono::{Duration, FixedOffset, Local};
 use regex::Regex;
 use std::ffi::OsStr;
 use std::os::unix::ffi::OsStrExt;
@@ -35,8 +38,9 @@ fn serialize_simple(
             Value::Float(f) => element::Element::Float(*f),
             Value::Bool(b) => element::Element::Bool(*b),
             Value::Empty() => element::Element::Empty(false),
-            Value::Time(d) => element::Element::Time(d.timestamp_nanos()),
             Value::Field(f) => element::Element::Field(f.serialize(elements, state)? as u64),
+            Value::Ip(ip) => element::Element::Ip(ip.to_string()),
+            Value::Cidr(c) => element::Element::Cidr(c.to_string()),
             _ => return error("Expected simple value"),
         }),
     };
@@ -60,6 +64,8 @@ impl Serializable<Value> for Value {
                 Ok(Value::Regex(v.clone(), to_crush_error(Regex::new(v))?))
             }
             element::Element::Bool(v) => Ok(Value::Bool(*v)),
+            element::Element::Ip(v) => Ok(Value::Ip(parse_ip(v)?)),
+            element::Element::Cidr(v) => Ok(Value::Cidr(Cidr::parse(v)?)),
             element::Element::Empty(_) => Ok(Value::Empty()),
 
             element::Element::SmallInteger(_) | element::Element::LargeInteger(_) => {
@@ -70,7 +76,10 @@ impl Serializable<Value> for Value {
                 Duration::seconds(d.secs) + Duration::nanoseconds(d.nanos as i64),
             )),
 
-            element::Element::Time(t) => Ok(Value::Time(Local.timestamp_nanos(*t))),
+            element::Element::Time(t) => Ok(Value::Time(time::local(Local.timestamp_nanos(*t)))),
+            element::Element::ZonedTime(t) => Ok(Value::Time(
+                mandate(FixedOffset::east_opt(t.offset), "Invalid time offset")?
+                    .timestamp_nanos(t.nanos))),
             element::Element::List(_) => Ok(Value::List(List::deserialize(id, elements, state)?)),
             element::Element::Type(_) => {
                 Ok(Value::Type(ValueType::deserialize(id, elements, state)?))
@@ -93,6 +102,7 @@ impl Serializable<Value> for Value {
                 Ok(Value::Scope(Scope::deserialize(id, elements, state)?))
             }
             element::Element::Dict(_) => Ok(Value::Dict(Dict::deserialize(id, elements, state)?)),
+            element::Element::Set(_) => Ok(Value::Set(Set::deserialize(id, elements, state)?)),
 
             element::Element::TrackedString(_)
             | element::Element::Strings(_)
@@ -120,11 +130,23 @@ impl Serializable<Value> for Value {
             | Value::Float(_)
             | Value::Bool(_)
             | Value::Empty()
-            | Value::Time(_)
+            | Value::Ip(_)
+            | Value::Cidr(_)
             | Value::Field(_) => serialize_simple(self, elements, state),
 
             Value::Integer(s) => s.serialize(elements, state),
 
+            Value::Time(t) => {
+                let mut node = Element::default();
+                let mut tt = model::Time::default();
+                tt.nanos = t.timestamp_nanos();
+                tt.offset = t.offset().local_minus_utc();
+                node.element = Some(element::Element::ZonedTime(tt));
+                let idx = elements.len();
+                elements.push(node);
+                Ok(idx)
+            }
+
             Value::Duration(d) => {
                 let mut node = Element::default();
                 let mut dd = model::Duration::default();
@@ -143,6 +165,7 @@ impl Serializable<Value> for Value {
             Value::Command(c) => c.serialize(elements, state),
             Value::Struct(s) => s.serialize(elements, state),
             Value::Dict(d) => d.serialize(elements, state),
+            Value::Set(s) => s.serialize(elements, state),
             Value::Scope(s) => s.serialize(elements, state),
             Value::TableOutputStream(_) | Value::TableInputStream(_) |
             Value::BinaryInputStream(_) => error("Can't serialize streams"),
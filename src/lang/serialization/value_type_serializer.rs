@@ -33,6 +33,8 @@ impl Serializable<ValueType> for ValueType {
                     14 => ValueType::Time,
                     15 => ValueType::Struct,
                     16 => ValueType::Any,
+                    18 => ValueType::Ip,
+                    19 => ValueType::Cidr,
                     _ => return error("Unrecognised type"),
                 }),
                 model::r#type::Type::ListType(l) => Ok(ValueType::List(Box::from(
@@ -50,6 +52,9 @@ impl Serializable<ValueType> for ValueType {
                         state,
                     )?),
                 )),
+                model::r#type::Type::SetType(s) => Ok(ValueType::Set(Box::from(
+                    ValueType::deserialize(s.element_type as usize, elements, state)?,
+                ))),
                 model::r#type::Type::TableType(tt) => Ok(ValueType::Table(
                     tt.column_types
                         .iter()
@@ -97,6 +102,8 @@ impl Serializable<ValueType> for ValueType {
             ValueType::Any => SimpleTypeKind::Any,
             ValueType::Binary => SimpleTypeKind::Binary,
             ValueType::Type => SimpleTypeKind::Type,
+            ValueType::Ip => SimpleTypeKind::Ip,
+            ValueType::Cidr => SimpleTypeKind::Cidr,
             ValueType::List(t) => {
                 let l = model::ListType {
                     element_type: t.serialize(elements, state)? as u64,
@@ -122,6 +129,18 @@ impl Serializable<ValueType> for ValueType {
                 });
                 return Ok(idx);
             }
+            ValueType::Set(t) => {
+                let s = model::SetType {
+                    element_type: t.serialize(elements, state)? as u64,
+                };
+                let idx = elements.len();
+                elements.push(model::Element {
+                    element: Some(element::Element::Type(model::Type {
+                        r#type: Some(model::r#type::Type::SetType(s)),
+                    })),
+                });
+                return Ok(idx);
+            }
             ValueType::TableOutputStream(col) | ValueType::Table(col) |
             ValueType::TableInputStream(col) => {
                 let d = model::TableType {
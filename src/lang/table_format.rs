@@ -0,0 +1,335 @@
+/**
+Options controlling how the output formatter renders tables: column widths, alignment, borders,
+how many rows to show and which columns to hide.
+
+The global options live in the crush:table struct. Assigning to one of its fields validates the
+new value and updates the options used by the output formatter. The same options can be given to
+table:show to render a single table differently.
+*/
+
+use crate::lang::command::CrushCommand;
+use crate::lang::command::OutputType::Known;
+use crate::lang::data::list::List;
+use crate::lang::data::dict::Dict;
+use crate::lang::data::r#struct::Struct;
+use crate::lang::errors::{argument_error_legacy, mandate, CrushResult};
+use crate::lang::execution_context::{ArgumentVector, CommandContext, This};
+use crate::lang::value::{Alignment, Value, ValueType};
+use std::collections::HashMap;
+use unicode_width::UnicodeWidthChar;
+
+pub const OVERFLOW_STYLES: &[&str] = &["ellipsis", "wrap"];
+pub const BORDER_STYLES: &[&str] = &["none", "ascii", "unicode", "markdown"];
+const ALIGNMENTS: &[&str] = &["left", "right", "center"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Overflow {
+    /** Cut the cell short and end it with an ellipsis. */
+    Ellipsis,
+    /** Wrap the cell over multiple lines. */
+    Wrap,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Border {
+    None,
+    Ascii,
+    Unicode,
+    Markdown,
+}
+
+/** The horizontal lines of a table with borders. */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rule {
+    Top,
+    BelowHeader,
+    Bottom,
+}
+
+impl Border {
+    fn parse(name: &str) -> Border {
+        match name {
+            "ascii" => Border::Ascii,
+            "unicode" => Border::Unicode,
+            "markdown" => Border::Markdown,
+            _ => Border::None,
+        }
+    }
+
+    pub fn vertical(&self) -> &'static str {
+        match self {
+            Border::Unicode => "│",
+            _ => "|",
+        }
+    }
+
+    /**
+    The specified horizontal line for a table with the specified column widths, or None if this
+    border style doesn't have one.
+    */
+    pub fn rule(&self, widths: &[usize], rule: Rule) -> Option<String> {
+        let (left, middle, right, line) = match (self, rule) {
+            (Border::None, _) => return None,
+            (Border::Markdown, Rule::BelowHeader) => ("|", "|", "|", "-"),
+            (Border::Markdown, _) => return None,
+            (Border::Ascii, _) => ("+", "+", "+", "-"),
+            (Border::Unicode, Rule::Top) => ("┌", "┬", "┐", "─"),
+            (Border::Unicode, Rule::BelowHeader) => ("├", "┼", "┤", "─"),
+            (Border::Unicode, Rule::Bottom) => ("└", "┴", "┘", "─"),
+        };
+        Some(format!(
+            "{}{}{}",
+            left,
+            widths.iter().map(|w| line.repeat(w + 2)).collect::<Vec<_>>().join(middle),
+            right))
+    }
+}
+
+#[derive(Clone)]
+pub struct TableFormat {
+    /** The maximum width of a column, or 0 for no limit. */
+    pub max_column_width: usize,
+    pub overflow: Overflow,
+    /** Alignment overrides, by type name. */
+    pub alignment: HashMap<String, Alignment>,
+    pub border: Border,
+    /** The maximum number of rows to show of every table, or 0 for no limit. */
+    pub row_limit: usize,
+    pub hidden_columns: Vec<String>,
+}
+
+impl Default for TableFormat {
+    fn default() -> TableFormat {
+        TableFormat {
+            max_column_width: 0,
+            overflow: Overflow::Ellipsis,
+            alignment: HashMap::new(),
+            border: Border::None,
+            row_limit: 0,
+            hidden_columns: vec![],
+        }
+    }
+}
+
+fn one_of(name: &str, value: &str, allowed: &[&str]) -> CrushResult<()> {
+    if allowed.contains(&value) {
+        Ok(())
+    } else {
+        argument_error_legacy(format!(
+            "Invalid value {} for {}, expected one of {}", value, name, allowed.join(", ")))
+    }
+}
+
+fn non_negative(name: &str, value: i128) -> CrushResult<usize> {
+    if value < 0 {
+        argument_error_legacy(format!("{} can not be negative", name))
+    } else {
+        Ok(value as usize)
+    }
+}
+
+/** Cut the text short to fit within the specified width, ending it with an ellipsis. */
+fn truncate(text: &str, width: usize) -> String {
+    let mut res = String::new();
+    let mut used = 0;
+    for ch in text.chars() {
+        let w = ch.width().unwrap_or(0);
+        if used + w + 1 > width {
+            break;
+        }
+        used += w;
+        res.push(ch);
+    }
+    res.push('…');
+    res
+}
+
+/** Split the text into lines of at most the specified width. */
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut res = Vec::new();
+    for line in text.split('\n') {
+        let mut current = String::new();
+        let mut used = 0;
+        for ch in line.chars() {
+            let w = ch.width().unwrap_or(0);
+            if used + w > width && !current.is_empty() {
+                res.push(current);
+                current = String::new();
+                used = 0;
+            }
+            used += w;
+            current.push(ch);
+        }
+        res.push(current);
+    }
+    res
+}
+
+fn width(text: &str) -> usize {
+    unicode_width::UnicodeWidthStr::width(text)
+}
+
+impl TableFormat {
+    /** Validate and apply a single option. */
+    pub fn set(&mut self, name: &str, value: &Value) -> CrushResult<()> {
+        match (name, value) {
+            ("max_column_width", Value::Integer(i)) =>
+                self.max_column_width = non_negative("max_column_width", *i)?,
+            ("overflow", Value::String(s)) => {
+                one_of(name, s, OVERFLOW_STYLES)?;
+                self.overflow = if s == "wrap" { Overflow::Wrap } else { Overflow::Ellipsis };
+            }
+            ("alignment", Value::Dict(d)) => {
+                let mut alignment = HashMap::new();
+                for (key, value) in d.elements() {
+                    let value = value.to_string();
+                    one_of("alignment", &value, ALIGNMENTS)?;
+                    alignment.insert(key.to_string(), match value.as_str() {
+                        "right" => Alignment::Right,
+                        "center" => Alignment::Center,
+                        _ => Alignment::Left,
+                    });
+                }
+                self.alignment = alignment;
+            }
+            ("border", Value::String(s)) => {
+                one_of(name, s, BORDER_STYLES)?;
+                self.border = Border::parse(s);
+            }
+            ("row_limit", Value::Integer(i)) => self.row_limit = non_negative("row_limit", *i)?,
+            ("hidden_columns", Value::List(l)) =>
+                self.hidden_columns = l.dump().iter().map(|v| v.to_string()).collect(),
+            (_, value) => return argument_error_legacy(format!(
+                "Invalid table option {} of type {}", name, value.value_type())),
+        }
+        Ok(())
+    }
+
+    /** The alignment of the specified cell, taking the per-type overrides into account. */
+    pub fn alignment(&self, value: &Value) -> Alignment {
+        self.alignment.get(&value.value_type().to_string())
+            .copied()
+            .unwrap_or_else(|| value.alignment())
+    }
+
+    pub fn is_hidden(&self, column: &str) -> bool {
+        self.hidden_columns.iter().any(|c| c == column)
+    }
+
+    /** Cut a single line short if it is wider than the maximum column width. */
+    pub fn fit_line(&self, text: &str) -> String {
+        if self.max_column_width != 0 && width(text) > self.max_column_width {
+            truncate(text, self.max_column_width)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /** Split a formatted cell into the lines to show, respecting the maximum column width. */
+    pub fn fit(&self, text: String) -> Vec<String> {
+        if self.max_column_width == 0 {
+            return vec![text];
+        }
+        match self.overflow {
+            Overflow::Wrap => wrap(&text, self.max_column_width),
+            Overflow::Ellipsis => {
+                let mut lines = text.lines();
+                let first = lines.next().unwrap_or("");
+                if width(first) > self.max_column_width || lines.next().is_some() {
+                    vec![truncate(first, self.max_column_width)]
+                } else {
+                    vec![first.to_string()]
+                }
+            }
+        }
+    }
+}
+
+fn table_set(mut context: CommandContext) -> CrushResult<()> {
+    let this = context.this.r#struct()?;
+    let name = context.arguments.string(0)?;
+    let value = context.arguments.value(1)?;
+    let current = mandate(
+        this.get(&name),
+        format!("Unknown table option crush:table:{}", name))?;
+    if current.value_type() != value.value_type() {
+        return argument_error_legacy(format!(
+            "Expected crush:table:{} to be of type {}, not {}",
+            name, current.value_type(), value.value_type()));
+    }
+    let mut format = context.global_state.table_format();
+    format.set(&name, &value)?;
+    this.set(&name, value);
+    context.global_state.set_table_format(format);
+    context.output.send(Value::Empty())
+}
+
+/**
+Create the crush:table struct, populated with the default options. Assigning to a field updates
+the options used by the output formatter.
+*/
+pub fn create() -> Struct {
+    let class = Struct::new(
+        vec![
+            ("__setattr__", Value::Command(<dyn CrushCommand>::command(
+                table_set, false,
+                vec!["global".to_string(), "crush".to_string(), "table".to_string(), "__setattr__".to_string()],
+                "crush:table:__setattr__ name:string value:any",
+                "Modify the specified table rendering option",
+                None,
+                Known(ValueType::Empty),
+                vec![],
+            ))),
+        ],
+        None,
+    );
+    Struct::new(
+        vec![
+            ("max_column_width", Value::Integer(0)),
+            ("overflow", Value::string("ellipsis")),
+            ("alignment", Value::Dict(Dict::new(ValueType::String, ValueType::String))),
+            ("border", Value::string("none")),
+            ("row_limit", Value::Integer(0)),
+            ("hidden_columns", Value::List(List::new(ValueType::String, vec![]))),
+        ],
+        Some(class),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_fit() {
+        let mut format = TableFormat::default();
+        assert_eq!(format.fit("abcdefgh".to_string()), vec!["abcdefgh"]);
+        format.max_column_width = 5;
+        assert_eq!(format.fit("abc".to_string()), vec!["abc"]);
+        assert_eq!(format.fit("abcdefgh".to_string()), vec!["abcd…"]);
+        assert_eq!(format.fit("ab\ncd".to_string()), vec!["ab…"]);
+        format.overflow = Overflow::Wrap;
+        assert_eq!(format.fit("abcdefgh".to_string()), vec!["abcde", "fgh"]);
+        assert_eq!(format.fit("ab\ncd".to_string()), vec!["ab", "cd"]);
+    }
+
+    #[test]
+    fn check_rule() {
+        assert_eq!(Border::Ascii.rule(&[1, 2], Rule::Top), Some("+---+----+".to_string()));
+        assert_eq!(Border::Unicode.rule(&[1], Rule::Bottom), Some("└───┘".to_string()));
+        assert_eq!(Border::Markdown.rule(&[1, 1], Rule::BelowHeader), Some("|---|---|".to_string()));
+        assert_eq!(Border::Markdown.rule(&[1], Rule::Top), None);
+        assert_eq!(Border::None.rule(&[1], Rule::BelowHeader), None);
+    }
+
+    #[test]
+    fn check_set() {
+        let mut format = TableFormat::default();
+        assert!(format.set("border", &Value::string("unicode")).is_ok());
+        assert_eq!(format.border, Border::Unicode);
+        assert!(format.set("border", &Value::string("fancy")).is_err());
+        assert!(format.set("row_limit", &Value::Integer(-1)).is_err());
+        assert!(format.set("row_limit", &Value::string("10")).is_err());
+        assert!(format.set("colour", &Value::Integer(1)).is_err());
+    }
+}
@@ -0,0 +1,213 @@
+/**
+The colour theme of the output formatter. The theme is a dict from element names to the escape
+sequences that style them. It is kept in the global state and available as crush:theme. Cells are styled by type, e.g. numbers,
+times and booleans, and files are styled by file type and permissions the same way ls does it.
+The file styles are initialised from the LS_COLORS environment variable if it is set.
+
+Colours are only used when writing to a terminal, and never if the NO_COLOR environment variable
+is set.
+*/
+
+use crate::lang::data::dict::Dict;
+use crate::lang::value::{Value, ValueType};
+use std::fs;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::path::Path;
+use termion::is_tty;
+
+const RESET: &str = "\x1b[0m";
+
+const DEFAULT_THEME: &[(&str, &str)] = &[
+    ("header", "\x1b[1;4m"),
+    ("struct_key", "\x1b[1m"),
+    ("error", "\x1b[31m"),
+    ("empty", "\x1b[2m"),
+    ("string", ""),
+    ("number", "\x1b[35m"),
+    ("bool", "\x1b[33m"),
+    ("time", "\x1b[34m"),
+    ("duration", "\x1b[36m"),
+    ("file", ""),
+    ("directory", "\x1b[1;34m"),
+    ("symlink", "\x1b[1;36m"),
+    ("executable", "\x1b[1;32m"),
+    ("fifo", "\x1b[33m"),
+    ("socket", "\x1b[1;35m"),
+    ("block_device", "\x1b[1;33m"),
+    ("char_device", "\x1b[1;33m"),
+    ("setuid", "\x1b[37;41m"),
+    ("setgid", "\x1b[30;43m"),
+    ("sticky", "\x1b[37;44m"),
+    ("other_writable", "\x1b[34;42m"),
+    ("sticky_other_writable", "\x1b[30;42m"),
+];
+
+/** The names LS_COLORS uses for the file styles of the theme. */
+const LS_COLORS_NAMES: &[(&str, &str)] = &[
+    ("fi", "file"),
+    ("di", "directory"),
+    ("ln", "symlink"),
+    ("ex", "executable"),
+    ("pi", "fifo"),
+    ("so", "socket"),
+    ("bd", "block_device"),
+    ("cd", "char_device"),
+    ("su", "setuid"),
+    ("sg", "setgid"),
+    ("st", "sticky"),
+    ("ow", "other_writable"),
+    ("tw", "sticky_other_writable"),
+];
+
+/**
+Parse the content of an LS_COLORS variable into theme entries. File extension patterns like
+*.tar are kept as they are, so that they can be looked up by extension.
+*/
+fn parse_ls_colors(ls_colors: &str) -> Vec<(String, String)> {
+    ls_colors.split(':')
+        .filter_map(|entry| {
+            let mut parts = entry.splitn(2, '=');
+            let (name, codes) = (parts.next()?, parts.next()?);
+            let style = format!("\x1b[{}m", codes);
+            if name.starts_with("*.") {
+                Some((name.to_lowercase(), style))
+            } else {
+                LS_COLORS_NAMES.iter()
+                    .find(|(code, _)| *code == name)
+                    .map(|(_, element)| (element.to_string(), style))
+            }
+        })
+        .collect()
+}
+
+/** Create the default theme, with the file styles taken from LS_COLORS if it is set. */
+pub fn create() -> Dict {
+    let theme = Dict::new(ValueType::String, ValueType::String);
+    let mut entries = DEFAULT_THEME.iter()
+        .map(|(name, style)| (name.to_string(), style.to_string()))
+        .collect::<Vec<_>>();
+    if let Ok(ls_colors) = std::env::var("LS_COLORS") {
+        entries.append(&mut parse_ls_colors(&ls_colors));
+    }
+    for (name, style) in entries {
+        let _ = theme.insert(Value::String(name), Value::String(style));
+    }
+    theme
+}
+
+/** True if output to standard output should be coloured. */
+pub fn enabled() -> bool {
+    std::env::var_os("NO_COLOR").is_none() && is_tty(&std::io::stdout())
+}
+
+/** True if error messages should be coloured. */
+pub fn errors_enabled() -> bool {
+    std::env::var_os("NO_COLOR").is_none() && is_tty(&std::io::stderr())
+}
+
+/** The style of the specified element in the theme, or None if it is not styled. */
+pub fn style(theme: &Dict, name: &str) -> Option<String> {
+    match theme.get(&Value::string(name)) {
+        Some(Value::String(s)) if !s.is_empty() => Some(s),
+        _ => None,
+    }
+}
+
+/** Wrap the text in the specified style. */
+pub fn paint(text: &str, style: Option<&str>) -> String {
+    match style {
+        Some(style) if !text.is_empty() => format!("{}{}{}", style, text, RESET),
+        _ => text.to_string(),
+    }
+}
+
+/** The theme element that describes a file, in the order of precedence ls uses. */
+fn file_element(theme: &Dict, path: &Path) -> Option<String> {
+    let metadata = fs::symlink_metadata(path).ok()?;
+    let file_type = metadata.file_type();
+    let mode = metadata.permissions().mode();
+    let element = if file_type.is_symlink() {
+        "symlink"
+    } else if file_type.is_dir() {
+        match (mode & 0o1000 != 0, mode & 0o002 != 0) {
+            (true, true) => "sticky_other_writable",
+            (false, true) => "other_writable",
+            (true, false) => "sticky",
+            (false, false) => "directory",
+        }
+    } else if file_type.is_fifo() {
+        "fifo"
+    } else if file_type.is_socket() {
+        "socket"
+    } else if file_type.is_block_device() {
+        "block_device"
+    } else if file_type.is_char_device() {
+        "char_device"
+    } else if mode & 0o4000 != 0 {
+        "setuid"
+    } else if mode & 0o2000 != 0 {
+        "setgid"
+    } else if mode & 0o111 != 0 {
+        "executable"
+    } else {
+        return path.extension()
+            .map(|ext| format!("*.{}", ext.to_string_lossy().to_lowercase()))
+            .filter(|pattern| style(theme, pattern).is_some())
+            .or_else(|| Some("file".to_string()));
+    };
+    Some(element.to_string())
+}
+
+/** The style of a value in the theme, based on its type. */
+pub fn value_style(theme: &Dict, value: &Value) -> Option<String> {
+    match value {
+        Value::Integer(_) | Value::Float(_) => style(theme, "number"),
+        Value::Bool(_) => style(theme, "bool"),
+        Value::Time(_) => style(theme, "time"),
+        Value::Duration(_) => style(theme, "duration"),
+        Value::Empty() => style(theme, "empty"),
+        Value::String(_) => style(theme, "string"),
+        Value::File(path) => file_element(theme, path).and_then(|element| style(theme, &element)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_parse_ls_colors() {
+        assert_eq!(
+            parse_ls_colors("di=01;34:ln=01;36:*.TAR=01;31:xx=1:bogus"),
+            vec![
+                ("directory".to_string(), "\x1b[01;34m".to_string()),
+                ("symlink".to_string(), "\x1b[01;36m".to_string()),
+                ("*.tar".to_string(), "\x1b[01;31m".to_string()),
+            ]);
+    }
+
+    #[test]
+    fn check_paint() {
+        assert_eq!(paint("abc", Some("\x1b[1m")), "\x1b[1mabc\x1b[0m");
+        assert_eq!(paint("abc", None), "abc");
+        assert_eq!(paint("", Some("\x1b[1m")), "");
+    }
+
+    #[test]
+    fn check_file_element() {
+        let theme = create();
+        assert_eq!(file_element(&theme, Path::new("src")), Some("directory".to_string()));
+        assert_eq!(file_element(&theme, Path::new("no/such/file")), None);
+    }
+
+    #[test]
+    fn check_value_style() {
+        let theme = create();
+        theme.insert(Value::string("number"), Value::string("\x1b[1m")).unwrap();
+        theme.insert(Value::string("bool"), Value::string("")).unwrap();
+        assert_eq!(value_style(&theme, &Value::Integer(1)), Some("\x1b[1m".to_string()));
+        assert_eq!(value_style(&theme, &Value::Bool(true)), None);
+        assert_eq!(value_style(&create(), &Value::Integer(1)), Some("\x1b[35m".to_string()));
+    }
+}
@@ -1,17 +1,40 @@
 use std::thread::{JoinHandle, ThreadId};
 use crate::lang::printer::Printer;
-use crate::lang::errors::{to_crush_error, CrushResult};
+use crate::lang::errors::{to_crush_error, CrushErrorType, CrushResult};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use crossbeam::channel::Sender;
 use crossbeam::channel::Receiver;
 use crossbeam::channel::unbounded;
 use std::time::Duration;
+use std::cell::Cell;
 use chrono::{DateTime, Local};
 
+/**
+Identifies an interactive command. Every thread belongs to the job of the thread that spawned it,
+so that errors can be attributed to the command that caused them, even when they are reported
+after another command has started.
+*/
+pub type JobId = usize;
+
+thread_local! {
+    static CURRENT_JOB: Cell<Option<JobId>> = Cell::new(None);
+}
+
+/** The job the current thread belongs to, if any. */
+pub fn current_job() -> Option<JobId> {
+    CURRENT_JOB.with(|job| job.get())
+}
+
+/** Make the current thread, and all threads it spawns from now on, belong to the specified job. */
+pub fn set_current_job(job: Option<JobId>) {
+    CURRENT_JOB.with(|current| current.set(job));
+}
+
 struct ThreadData {
     handle: JoinHandle<CrushResult<()>>,
     creation_time: DateTime<Local>,
+    job: Option<JobId>,
 }
 
 struct ThreadStoreInternal {
@@ -25,9 +48,10 @@ pub struct ThreadDescription {
     pub creation_time: DateTime<Local>,
 }
 
-fn join_handle(handle: JoinHandle<CrushResult<()>>, printer: &Printer) {
-    match handle.join() {
-        Ok(res) => { printer.handle_error(res) }
+fn join_handle(thread: ThreadData, printer: &Printer) {
+    match thread.handle.join() {
+        Ok(Err(e)) if !e.is(CrushErrorType::SendError) => printer.job_error(e, thread.job),
+        Ok(_) => {}
         Err(_) => printer.error("Unknown error while waiting for command to exit"),
     }
 }
@@ -64,9 +88,11 @@ impl ThreadStore {
             F: Send + 'static,
     {
         let slef = self.clone();
+        let job = current_job();
         let handle = to_crush_error(thread::Builder::new()
             .name(name.to_string())
             .spawn(move || {
+                set_current_job(job);
                 let res = f();
                 slef.exit();
                 res
@@ -76,6 +102,7 @@ impl ThreadStore {
         data.threads.push(ThreadData {
             handle,
             creation_time: Local::now(),
+            job,
         });
         Ok(id)
     }
@@ -90,7 +117,7 @@ impl ThreadStore {
                 None => break,
                 Some(h) => {
                     drop(data);
-                    join_handle(h.handle, printer);
+                    join_handle(h, printer);
                 }
             }
         }
@@ -126,7 +153,7 @@ impl ThreadStore {
         if let Some(idx) = kill_idx {
             let h = data.threads.remove(idx);
             drop(data);
-            join_handle(h.handle, printer);
+            join_handle(h, printer);
         }
     }
 
@@ -0,0 +1,168 @@
+/**
+Render nested values, like structs of structs, lists of structs and tables, as a tree drawn with
+box-drawing characters. Each compound value becomes a node whose children are its fields,
+elements, entries or rows. Lists of simple values are shown inline, the same way the output
+formatter shows them elsewhere.
+*/
+
+use crate::lang::errors::CrushResult;
+use crate::lang::value::Value;
+use crate::lang::value_format::ValueFormat;
+use num_format::Grouping;
+
+/** How many levels are shown before nested values are collapsed into a summary. */
+pub const DEFAULT_MAX_DEPTH: usize = 4;
+
+/** Structs, lists and dicts nested at least this deep are shown as a tree by the output formatter. */
+pub const AUTO_DEPTH: usize = 2;
+
+/** True if the value is shown as a node with children rather than on a single line. */
+fn is_compound(value: &Value) -> bool {
+    match value {
+        Value::Struct(_) | Value::Dict(_) | Value::Table(_) => true,
+        Value::List(list) => list.dump().iter().any(is_compound),
+        _ => false,
+    }
+}
+
+/** The labelled children of a compound value, or None for values that are shown on one line. */
+fn children(value: &Value) -> Option<Vec<(String, Value)>> {
+    if !is_compound(value) {
+        return None;
+    }
+    match value {
+        Value::Struct(s) => Some(s.local_elements()),
+        Value::Dict(d) => Some(d.elements().into_iter().map(|(k, v)| (k.to_string(), v)).collect()),
+        Value::List(l) => Some(
+            l.dump().into_iter()
+                .enumerate()
+                .map(|(idx, v)| (format!("[{}]", idx), v))
+                .collect()),
+        Value::Table(t) => Some(
+            t.rows().iter()
+                .enumerate()
+                .map(|(idx, row)| (format!("[{}]", idx), Value::Struct(row.clone().into_struct(t.types()))))
+                .collect()),
+        _ => None,
+    }
+}
+
+fn plural(count: usize, unit: &str) -> String {
+    format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" })
+}
+
+/** A one line summary of a collapsed compound value. */
+fn summary(value: &Value, count: usize) -> String {
+    match value {
+        Value::Struct(_) => format!("{{{}}}", plural(count, "field")),
+        Value::Dict(_) => format!("{{{}}}", if count == 1 { "1 entry".to_string() } else { format!("{} entries", count) }),
+        Value::List(_) => format!("[{}]", plural(count, "item")),
+        _ => format!("({})", plural(count, "row")),
+    }
+}
+
+/**
+How deeply compound values are nested in the specified value. Simple values and lists of simple
+values have depth 0, and e.g. a struct with only simple fields has depth 1.
+*/
+pub fn depth(value: &Value) -> usize {
+    match children(value) {
+        None => 0,
+        Some(nodes) => 1 + nodes.iter().map(|(_, child)| depth(child)).max().unwrap_or(0),
+    }
+}
+
+fn render_children(
+    nodes: Vec<(String, Value)>,
+    prefix: &str,
+    depth: usize,
+    max_depth: usize,
+    grouping: Grouping,
+    format: &ValueFormat,
+    lines: &mut Vec<String>,
+) {
+    let count = nodes.len();
+    for (idx, (label, child)) in nodes.into_iter().enumerate() {
+        let (branch, indent) = if idx + 1 == count { ("└── ", "    ") } else { ("├── ", "│   ") };
+        match children(&child) {
+            None => lines.push(format!("{}{}{}: {}", prefix, branch, label, child.to_pretty_string(grouping, format))),
+            Some(grandchildren) if depth >= max_depth || grandchildren.is_empty() =>
+                lines.push(format!("{}{}{}: {}", prefix, branch, label, summary(&child, grandchildren.len()))),
+            Some(grandchildren) => {
+                lines.push(format!("{}{}{}", prefix, branch, label));
+                render_children(grandchildren, &format!("{}{}", prefix, indent), depth + 1, max_depth, grouping, format, lines);
+            }
+        }
+    }
+}
+
+/**
+Render the specified value as a tree. Compound values nested deeper than max_depth are collapsed
+into a summary like {3 fields}. Streams are read to the end before rendering.
+*/
+pub fn render(value: Value, max_depth: usize, grouping: Grouping, format: &ValueFormat) -> CrushResult<Vec<String>> {
+    let value = value.materialize()?;
+    Ok(match children(&value) {
+        None => vec![value.to_pretty_string(grouping, format)],
+        Some(nodes) if max_depth == 0 => vec![summary(&value, nodes.len())],
+        Some(nodes) => {
+            let mut lines = vec![value.value_type().to_string()];
+            render_children(nodes, "", 1, max_depth, grouping, format, &mut lines);
+            lines
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lang::data::list::List;
+    use crate::lang::data::r#struct::Struct;
+    use crate::lang::value::ValueType;
+
+    fn sample() -> Value {
+        let address = Struct::new(vec![
+            ("city", Value::string("Oslo")),
+            ("zip", Value::string("0150")),
+        ], None);
+        Value::Struct(Struct::new(vec![
+            ("name", Value::string("Alice")),
+            ("address", Value::Struct(address)),
+            ("tags", Value::List(List::new(ValueType::String, vec![Value::string("a"), Value::string("b")]))),
+        ], None))
+    }
+
+    #[test]
+    fn check_depth() {
+        assert_eq!(depth(&Value::Integer(1)), 0);
+        assert_eq!(depth(&Value::List(List::new(ValueType::Integer, vec![Value::Integer(1)]))), 0);
+        assert_eq!(depth(&sample()), 2);
+    }
+
+    #[test]
+    fn check_render() {
+        assert_eq!(
+            render(sample(), DEFAULT_MAX_DEPTH, Grouping::Posix, &ValueFormat::default()).unwrap(),
+            vec![
+                "struct",
+                "├── name: Alice",
+                "├── address",
+                "│   ├── city: Oslo",
+                "│   └── zip: 0150",
+                "└── tags: [a, b]",
+            ]);
+    }
+
+    #[test]
+    fn check_collapse() {
+        assert_eq!(
+            render(sample(), 1, Grouping::Posix, &ValueFormat::default()).unwrap(),
+            vec![
+                "struct",
+                "├── name: Alice",
+                "├── address: {2 fields}",
+                "└── tags: [a, b]",
+            ]);
+        assert_eq!(render(sample(), 0, Grouping::Posix, &ValueFormat::default()).unwrap(), vec!["{3 fields}"]);
+    }
+}
@@ -6,10 +6,11 @@ mod value_type;
 
 use std::cmp::Ordering;
 use std::hash::Hasher;
+use std::net::IpAddr;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-use chrono::{DateTime, Local};
+use chrono::{DateTime, FixedOffset};
 use regex::Regex;
 
 use crate::lang::errors::{argument_error_legacy, mandate, CrushResult};
@@ -19,14 +20,17 @@ use crate::lang::data::scope::Scope;
 use crate::lang::pipe::{streams, InputStream, Stream, OutputStream};
 use crate::lang::data::{
     binary::BinaryReader, dict::Dict, dict::DictReader, list::List, list::ListReader,
+    set::Set, set::SetReader,
     table::ColumnType, table::TableReader,
 };
+use crate::lang::value_format::ValueFormat;
 use crate::util::time::duration_format;
 use crate::{
     lang::errors::{error, to_crush_error},
     lang::data::table::Table,
     util::file::cwd,
     util::glob::Glob,
+    util::ip::{parse_ip, Cidr},
 };
 use chrono::Duration;
 
@@ -41,13 +45,14 @@ pub use value_type::ValueType;
 use std::fmt::{Display, Formatter, Debug};
 use num_format::Grouping;
 use crate::util::escape::escape;
+use crate::util::time::{parse_duration, parse_time};
 
 pub type Field = Vec<String>;
 
 pub enum Value {
     String(String),
     Integer(i128),
-    Time(DateTime<Local>),
+    Time(DateTime<FixedOffset>),
     Duration(Duration),
     Field(Field),
     Glob(Glob),
@@ -60,6 +65,7 @@ pub enum Value {
     Struct(Struct),
     List(List),
     Dict(Dict),
+    Set(Set),
     Scope(Scope),
     Bool(bool),
     Float(f64),
@@ -67,6 +73,8 @@ pub enum Value {
     BinaryInputStream(Box<dyn BinaryReader + Send + Sync>),
     Binary(Vec<u8>),
     Type(ValueType),
+    Ip(IpAddr),
+    Cidr(Cidr),
 }
 
 impl Display for Value {
@@ -91,10 +99,13 @@ impl Display for Value {
             Value::Scope(env) => env.fmt(f),
             Value::Bool(v) => std::fmt::Display::fmt(if *v { "true" } else { "false" }, f),
             Value::Dict(d) => d.fmt(f),
+            Value::Set(s) => s.fmt(f),
             Value::Float(val) => std::fmt::Display::fmt(val, f),
             Value::Binary(v) => f.write_str(&format_buffer(v, true)),
             Value::Type(t) => std::fmt::Display::fmt(t, f),
             Value::Struct(s) => s.fmt(f),
+            Value::Ip(ip) => std::fmt::Display::fmt(ip, f),
+            Value::Cidr(c) => std::fmt::Display::fmt(c, f),
             _ => {
                 f.write_str("<")?;
                 std::fmt::Display::fmt(&self.value_type(), f)?;
@@ -224,6 +235,7 @@ impl Value {
             Value::Table(r) => Some(Box::from(TableReader::new(r.clone()))),
             Value::List(l) => Some(Box::from(ListReader::new(l.clone(), "value"))),
             Value::Dict(d) => Some(Box::from(DictReader::new(d.clone()))),
+            Value::Set(s) => Some(Box::from(SetReader::new(s.clone()))),
             Value::Struct(s) => Some(Box::from(StructReader::new(s.clone()))),
             _ => None,
         }
@@ -248,11 +260,14 @@ impl Value {
             Value::Scope(_) => ValueType::Scope,
             Value::Bool(_) => ValueType::Bool,
             Value::Dict(d) => d.dict_type(),
+            Value::Set(s) => s.set_type(),
             Value::Float(_) => ValueType::Float,
             Value::Empty() => ValueType::Empty,
             Value::BinaryInputStream(_) => ValueType::BinaryInputStream,
             Value::Binary(_) => ValueType::Binary,
             Value::Type(_) => ValueType::Type,
+            Value::Ip(_) => ValueType::Ip,
+            Value::Cidr(_) => ValueType::Cidr,
         }
     }
 
@@ -306,6 +321,7 @@ impl Value {
             }
             Value::Table(r) => Value::Table(r.materialize()?),
             Value::Dict(d) => Value::Dict(d.materialize()?),
+            Value::Set(s) => Value::Set(s.materialize()?),
             Value::Struct(r) => Value::Struct(r.materialize()?),
             Value::List(l) => Value::List(l.materialize()?),
             _ => self,
@@ -320,6 +336,8 @@ impl Value {
         match (&self, &new_type) {
             (Value::Integer(i), ValueType::Bool) => return Ok(Value::Bool(*i != 0)),
             (Value::Float(f), ValueType::Integer) => return Ok(Value::Integer(*f as i128)),
+            (Value::Ip(ip), ValueType::Cidr) => return Ok(Value::Cidr(Cidr::new(*ip, if ip.is_ipv4() { 32 } else { 128 })?)),
+            (Value::Cidr(c), ValueType::Ip) => return Ok(Value::Ip(c.address())),
             _ => {}
         }
 
@@ -343,10 +361,8 @@ impl Value {
                 }
             })),
             ValueType::String => Ok(Value::String(str_val)),
-            ValueType::Time => error("invalid convert"),
-            ValueType::Duration => Ok(Value::Duration(Duration::seconds(to_crush_error(
-                i64::from_str(&str_val),
-            )?))),
+            ValueType::Time => Ok(Value::Time(parse_time(&str_val)?)),
+            ValueType::Duration => Ok(Value::Duration(parse_duration(&str_val)?)),
             ValueType::Command => error("invalid convert"),
             ValueType::TableInputStream(_) => error("invalid convert"),
             ValueType::TableOutputStream(_) => error("invalid convert"),
@@ -354,11 +370,14 @@ impl Value {
             ValueType::Struct => error("invalid convert"),
             ValueType::List(_) => error("invalid convert"),
             ValueType::Dict(_, _) => error("invalid convert"),
+            ValueType::Set(_) => error("invalid convert"),
             ValueType::Scope => error("Invalid convert"),
             ValueType::Empty => error("Invalid convert"),
             ValueType::Any => error("Invalid convert"),
             ValueType::BinaryInputStream => error("invalid convert"),
             ValueType::Type => error("invalid convert"),
+            ValueType::Ip => Ok(Value::Ip(parse_ip(&str_val)?)),
+            ValueType::Cidr => Ok(Value::Cidr(Cidr::parse(&str_val)?)),
         }
     }
 
@@ -369,8 +388,9 @@ impl Value {
         * Respect integer grouping, but use _ intead of whatever number group
           separator the locale prescribes, so that the number can be copied
           and pasted into the terminal again.
+        * Format times, durations and floats according to the value format
     */
-    pub fn to_pretty_string(&self, grouping: Grouping) -> String {
+    pub fn to_pretty_string(&self, grouping: Grouping, format: &ValueFormat) -> String {
         match self {
             Value::String(val) =>
                 if has_non_printable(val) {
@@ -422,6 +442,9 @@ impl Value {
                 }
                 Grouping::Posix => i.to_string(),
             }
+            Value::Time(t) => format.format_time(t),
+            Value::Duration(d) => format.format_duration(d),
+            Value::Float(f) => format.format_float(*f),
             _ => self.to_string(),
         }
     }
@@ -456,11 +479,14 @@ impl Clone for Value {
             Value::Scope(e) => Value::Scope(e.clone()),
             Value::Bool(v) => Value::Bool(*v),
             Value::Dict(d) => Value::Dict(d.clone()),
+            Value::Set(s) => Value::Set(s.clone()),
             Value::Float(f) => Value::Float(*f),
             Value::Empty() => Value::Empty(),
             Value::BinaryInputStream(v) => Value::BinaryInputStream(v.as_ref().clone()),
             Value::Binary(v) => Value::Binary(v.clone()),
             Value::Type(t) => Value::Type(t.clone()),
+            Value::Ip(ip) => Value::Ip(*ip),
+            Value::Cidr(c) => Value::Cidr(*c),
         }
     }
 }
@@ -499,6 +525,7 @@ impl std::hash::Hash for Value {
             Value::Struct(v) => v.hash(state),
             Value::Scope(_)
             | Value::Dict(_)
+            | Value::Set(_)
             | Value::Table(_)
             | Value::List(_)
             | Value::TableInputStream(_)
@@ -512,6 +539,8 @@ impl std::hash::Hash for Value {
             }
             Value::Empty() => {}
             Value::Type(v) => v.to_string().hash(state),
+            Value::Ip(v) => v.hash(state),
+            Value::Cidr(v) => v.hash(state),
         }
     }
 }
@@ -543,17 +572,22 @@ impl std::cmp::PartialEq for Value {
             (Value::Struct(val1), Value::Struct(val2)) => val1 == val2,
             (Value::List(val1), Value::List(val2)) => val1 == val2,
             (Value::Dict(val1), Value::Dict(val2)) => val1 == val2,
+            (Value::Set(val1), Value::Set(val2)) => val1 == val2,
             (Value::Bool(val1), Value::Bool(val2)) => val1 == val2,
             (Value::Float(val1), Value::Float(val2)) => val1 == val2,
             (Value::Binary(val1), Value::Binary(val2)) => val1 == val2,
+            (Value::Ip(val1), Value::Ip(val2)) => val1 == val2,
+            (Value::Cidr(val1), Value::Cidr(val2)) => val1 == val2,
             _ => false,
         }
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Alignment {
     Left,
     Right,
+    Center,
 }
 
 impl std::cmp::PartialOrd for Value {
@@ -577,9 +611,12 @@ impl std::cmp::PartialOrd for Value {
             (Value::Struct(val1), Value::Struct(val2)) => val1.partial_cmp(val2),
             (Value::List(val1), Value::List(val2)) => val1.partial_cmp(val2),
             (Value::Dict(val1), Value::Dict(val2)) => val1.partial_cmp(val2),
+            (Value::Set(val1), Value::Set(val2)) => val1.partial_cmp(val2),
             (Value::Bool(val1), Value::Bool(val2)) => Some(val1.cmp(val2)),
             (Value::Float(val1), Value::Float(val2)) => val1.partial_cmp(val2),
             (Value::Binary(val1), Value::Binary(val2)) => Some(val1.cmp(val2)),
+            (Value::Ip(val1), Value::Ip(val2)) => Some(val1.cmp(val2)),
+            (Value::Cidr(val1), Value::Cidr(val2)) => Some(val1.cmp(val2)),
             _ => None,
         }
     }
@@ -681,34 +718,34 @@ mod tests {
 
     #[test]
     fn test_number_format_standard() {
-        assert_eq!(Value::Integer(0).to_pretty_string(Grouping::Standard), "0");
-        assert_eq!(Value::Integer(123).to_pretty_string(Grouping::Standard), "123");
-        assert_eq!(Value::Integer(-123).to_pretty_string(Grouping::Standard), "-123");
-        assert_eq!(Value::Integer(1234).to_pretty_string(Grouping::Standard), "1_234");
-        assert_eq!(Value::Integer(-1234).to_pretty_string(Grouping::Standard), "-1_234");
-        assert_eq!(Value::Integer(123_456_789).to_pretty_string(Grouping::Standard), "123_456_789");
-        assert_eq!(Value::Integer(-123_456_789).to_pretty_string(Grouping::Standard), "-123_456_789");
+        assert_eq!(Value::Integer(0).to_pretty_string(Grouping::Standard, &ValueFormat::default()), "0");
+        assert_eq!(Value::Integer(123).to_pretty_string(Grouping::Standard, &ValueFormat::default()), "123");
+        assert_eq!(Value::Integer(-123).to_pretty_string(Grouping::Standard, &ValueFormat::default()), "-123");
+        assert_eq!(Value::Integer(1234).to_pretty_string(Grouping::Standard, &ValueFormat::default()), "1_234");
+        assert_eq!(Value::Integer(-1234).to_pretty_string(Grouping::Standard, &ValueFormat::default()), "-1_234");
+        assert_eq!(Value::Integer(123_456_789).to_pretty_string(Grouping::Standard, &ValueFormat::default()), "123_456_789");
+        assert_eq!(Value::Integer(-123_456_789).to_pretty_string(Grouping::Standard, &ValueFormat::default()), "-123_456_789");
     }
 
     #[test]
     fn test_number_format_indian() {
-        assert_eq!(Value::Integer(0).to_pretty_string(Grouping::Indian), "0");
-        assert_eq!(Value::Integer(123).to_pretty_string(Grouping::Indian), "123");
-        assert_eq!(Value::Integer(-123).to_pretty_string(Grouping::Indian), "-123");
-        assert_eq!(Value::Integer(1234).to_pretty_string(Grouping::Indian), "1_234");
-        assert_eq!(Value::Integer(-1234).to_pretty_string(Grouping::Indian), "-1_234");
-        assert_eq!(Value::Integer(123_456_789).to_pretty_string(Grouping::Indian), "12_34_56_789");
-        assert_eq!(Value::Integer(-123_456_789).to_pretty_string(Grouping::Indian), "-12_34_56_789");
+        assert_eq!(Value::Integer(0).to_pretty_string(Grouping::Indian, &ValueFormat::default()), "0");
+        assert_eq!(Value::Integer(123).to_pretty_string(Grouping::Indian, &ValueFormat::default()), "123");
+        assert_eq!(Value::Integer(-123).to_pretty_string(Grouping::Indian, &ValueFormat::default()), "-123");
+        assert_eq!(Value::Integer(1234).to_pretty_string(Grouping::Indian, &ValueFormat::default()), "1_234");
+        assert_eq!(Value::Integer(-1234).to_pretty_string(Grouping::Indian, &ValueFormat::default()), "-1_234");
+        assert_eq!(Value::Integer(123_456_789).to_pretty_string(Grouping::Indian, &ValueFormat::default()), "12_34_56_789");
+        assert_eq!(Value::Integer(-123_456_789).to_pretty_string(Grouping::Indian, &ValueFormat::default()), "-12_34_56_789");
     }
 
     #[test]
     fn test_number_format_posix() {
-        assert_eq!(Value::Integer(0).to_pretty_string(Grouping::Posix), "0");
-        assert_eq!(Value::Integer(123).to_pretty_string(Grouping::Posix), "123");
-        assert_eq!(Value::Integer(1234).to_pretty_string(Grouping::Posix), "1234");
-        assert_eq!(Value::Integer(123_456_789).to_pretty_string(Grouping::Posix), "123456789");
-        assert_eq!(Value::Integer(-123).to_pretty_string(Grouping::Posix), "-123");
-        assert_eq!(Value::Integer(-1234).to_pretty_string(Grouping::Posix), "-1234");
-        assert_eq!(Value::Integer(-123_456_789).to_pretty_string(Grouping::Posix), "-123456789");
+        assert_eq!(Value::Integer(0).to_pretty_string(Grouping::Posix, &ValueFormat::default()), "0");
+        assert_eq!(Value::Integer(123).to_pretty_string(Grouping::Posix, &ValueFormat::default()), "123");
+        assert_eq!(Value::Integer(1234).to_pretty_string(Grouping::Posix, &ValueFormat::default()), "1234");
+        assert_eq!(Value::Integer(123_456_789).to_pretty_string(Grouping::Posix, &ValueFormat::default()), "123456789");
+        assert_eq!(Value::Integer(-123).to_pretty_string(Grouping::Posix, &ValueFormat::default()), "-123");
+        assert_eq!(Value::Integer(-1234).to_pretty_string(Grouping::Posix, &ValueFormat::default()), "-1234");
+        assert_eq!(Value::Integer(-123_456_789).to_pretty_string(Grouping::Posix, &ValueFormat::default()), "-123456789");
     }
 }
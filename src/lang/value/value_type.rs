@@ -5,6 +5,8 @@ use crate::lang::parser::parse_name;
 use crate::lang::{data::table::ColumnType, value::Value};
 use crate::lib::types;
 use crate::util::glob::Glob;
+use crate::util::ip::{parse_ip, Cidr};
+use crate::util::time::parse_duration;
 use lazy_static::lazy_static;
 use ordered_map::OrderedMap;
 use regex::Regex;
@@ -28,6 +30,7 @@ pub enum ValueType {
     Struct,
     List(Box<ValueType>),
     Dict(Box<ValueType>, Box<ValueType>),
+    Set(Box<ValueType>),
     Scope,
     Bool,
     Float,
@@ -36,6 +39,8 @@ pub enum ValueType {
     BinaryInputStream,
     Binary,
     Type,
+    Ip,
+    Cidr,
 }
 
 lazy_static! {
@@ -51,6 +56,7 @@ impl ValueType {
         match self {
             ValueType::List(_) => &types::list::METHODS,
             ValueType::Dict(_, _) => &types::dict::METHODS,
+            ValueType::Set(_) => &types::set::METHODS,
             ValueType::String => &types::string::METHODS,
             ValueType::File => &types::file::METHODS,
             ValueType::Regex => &types::re::METHODS,
@@ -64,6 +70,8 @@ impl ValueType {
             ValueType::TableOutputStream(_) => &types::table_output_stream::METHODS,
             ValueType::Binary => &types::binary::METHODS,
             ValueType::Scope => &types::scope::METHODS,
+            ValueType::Ip => &types::ip::METHODS,
+            ValueType::Cidr => &types::cidr::METHODS,
             _ => &EMPTY_METHODS,
         }
     }
@@ -94,6 +102,8 @@ impl ValueType {
             | ValueType::Binary
             | ValueType::Type
             | ValueType::Struct
+            | ValueType::Ip
+            | ValueType::Cidr
             | ValueType::Bool => self.clone(),
             ValueType::BinaryInputStream => ValueType::Binary,
             ValueType::TableInputStream(o) => ValueType::Table(ColumnType::materialize(o)?),
@@ -103,6 +113,7 @@ impl ValueType {
             ValueType::Dict(k, v) => {
                 ValueType::Dict(Box::from(k.materialize()?), Box::from(v.materialize()?))
             }
+            ValueType::Set(t) => ValueType::Set(Box::from(t.materialize()?)),
         })
     }
 
@@ -111,6 +122,7 @@ impl ValueType {
             ValueType::Scope
             | ValueType::List(_)
             | ValueType::Dict(_, _)
+            | ValueType::Set(_)
             | ValueType::Command
             | ValueType::BinaryInputStream
             | ValueType::TableInputStream(_)
@@ -137,6 +149,9 @@ impl ValueType {
             ValueType::File => Ok(Value::string(s)),
             ValueType::Float => Ok(Value::Float(to_crush_error(s.parse::<f64>())?)),
             ValueType::Bool => Ok(Value::Bool(to_crush_error(s.parse::<bool>())?)),
+            ValueType::Ip => Ok(Value::Ip(parse_ip(s)?)),
+            ValueType::Cidr => Ok(Value::Cidr(Cidr::parse(s)?)),
+            ValueType::Duration => Ok(Value::Duration(parse_duration(s)?)),
             _ => error("Failed to parse cell"),
         }
     }
@@ -166,6 +181,7 @@ impl Help for ValueType {
             ValueType::Struct => "A mapping from name to value",
             ValueType::List(_) => "A mutable list of items, usually of the same type",
             ValueType::Dict(_, _) => "A mutable mapping from one set of values to another",
+            ValueType::Set(_) => "A mutable collection of unique values",
             ValueType::Scope => "A scope in the Crush namespace",
             ValueType::Bool => "True or false",
             ValueType::Float => {
@@ -176,6 +192,8 @@ impl Help for ValueType {
             ValueType::BinaryInputStream => "A stream of binary data",
             ValueType::Binary => "Binary data",
             ValueType::Type => "A type",
+            ValueType::Ip => "An IPv4 or IPv6 address",
+            ValueType::Cidr => "A network of IP addresses, in CIDR notation",
         }
             .to_string()
     }
@@ -183,7 +201,7 @@ impl Help for ValueType {
     fn long_help(&self) -> Option<String> {
         let mut lines = match self {
             ValueType::Time => {
-                vec!["    All time instances use the local time zone.\n".to_string()]
+                vec!["    Every time instance has a UTC offset. Use in_zone to show a time in another time zone.\n".to_string()]
             },
             _ => {Vec::new()}
         };
@@ -258,6 +276,10 @@ impl Display for ValueType {
                 f.write_str(" ")?;
                 v.fmt(f)
             }
+            ValueType::Set(t) => {
+                f.write_str("set ")?;
+                t.fmt(f)
+            }
             ValueType::Scope => f.write_str("scope"),
             ValueType::Bool => f.write_str("bool"),
             ValueType::Float => f.write_str("float"),
@@ -266,6 +288,8 @@ impl Display for ValueType {
             ValueType::BinaryInputStream => f.write_str("binary_stream"),
             ValueType::Binary => f.write_str("binary"),
             ValueType::Type => f.write_str("type"),
+            ValueType::Ip => f.write_str("ip"),
+            ValueType::Cidr => f.write_str("cidr"),
         }
     }
 }
@@ -0,0 +1,483 @@
+/**
+Options controlling how individual values are formatted for humans: times, durations, byte sizes
+and floating point numbers. They are only used by the output formatter. Converting a value to a
+string always gives the same result, regardless of these options, so that e.g. TSV output and
+`convert` don't depend on them.
+
+The options live in the crush:value_format struct. Assigning to one of its fields validates the
+new value and updates the options kept in the global state.
+*/
+
+use crate::lang::command::CrushCommand;
+use crate::lang::command::OutputType::Known;
+use crate::lang::data::r#struct::Struct;
+use crate::lang::errors::{argument_error_legacy, mandate, CrushResult};
+use crate::lang::execution_context::{ArgumentVector, CommandContext, This};
+use crate::lang::value::{Value, ValueType};
+use crate::util::time::{duration_format, now};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Duration, FixedOffset};
+
+pub const DURATION_STYLES: &[&str] = &["clock", "short", "verbose", "iso", "seconds"];
+pub const BYTE_SIZE_STYLES: &[&str] = &["bytes", "binary", "decimal"];
+pub const DEFAULT_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S %z";
+
+/** The largest number of decimals float_precision can be set to. */
+const MAX_PRECISION: i128 = 17;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DurationStyle {
+    /** Days, then hours, minutes and seconds separated by colons, e.g. 1d2:03:04. */
+    Clock,
+    /** The two most significant units, e.g. 1h 30m or 850ms. */
+    Short,
+    /** All units, written out, e.g. 1 hour 30 minutes. */
+    Verbose,
+    /** ISO 8601, e.g. PT1H30M. */
+    Iso,
+    /** The number of seconds, e.g. 5400.5. */
+    Seconds,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ByteSizeStyle {
+    /** The plain number of bytes. */
+    Bytes,
+    /** Powers of 1024, e.g. 1.5 MiB. */
+    Binary,
+    /** Powers of 1000, e.g. 1.5 MB. */
+    Decimal,
+}
+
+#[derive(Clone)]
+pub struct ValueFormat {
+    pub time_format: String,
+    /** Show times relative to now, e.g. 3 min ago, instead of using the time format. */
+    pub relative_time: bool,
+    pub duration_style: DurationStyle,
+    /** How to show the cells of columns that hold byte sizes. */
+    pub byte_size: ByteSizeStyle,
+    /** The number of decimals to show of floats, or None for as many as needed. */
+    pub float_precision: Option<usize>,
+    /** The decimal separator, or None to use the one of the current locale. */
+    pub decimal_separator: Option<String>,
+    /** The decimal separator of the current locale, kept in sync with crush:locale. */
+    locale_decimal: String,
+}
+
+impl Default for ValueFormat {
+    fn default() -> ValueFormat {
+        ValueFormat {
+            time_format: DEFAULT_TIME_FORMAT.to_string(),
+            relative_time: false,
+            duration_style: DurationStyle::Clock,
+            byte_size: ByteSizeStyle::Bytes,
+            float_precision: None,
+            decimal_separator: Some(".".to_string()),
+            locale_decimal: ".".to_string(),
+        }
+    }
+}
+
+fn one_of(name: &str, value: &str, allowed: &[&str]) -> CrushResult<()> {
+    if allowed.contains(&value) {
+        Ok(())
+    } else {
+        argument_error_legacy(format!(
+            "Invalid value {} for {}, expected one of {}", value, name, allowed.join(", ")))
+    }
+}
+
+fn plural(count: i64, unit: &str) -> String {
+    format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" })
+}
+
+/** A description of how long before or after now a time is, e.g. 3 min ago or in 2 days. */
+fn relative(time: &DateTime<FixedOffset>, now: &DateTime<FixedOffset>) -> String {
+    let seconds = now.signed_duration_since(*time).num_seconds();
+    let abs = seconds.abs();
+    let text = if abs < 5 {
+        return "just now".to_string();
+    } else if abs < 60 {
+        format!("{} sec", abs)
+    } else if abs < 3600 {
+        format!("{} min", abs / 60)
+    } else if abs < 86400 {
+        plural(abs / 3600, "hour")
+    } else if abs < 86400 * 30 {
+        plural(abs / 86400, "day")
+    } else if abs < 86400 * 365 {
+        plural(abs / (86400 * 30), "month")
+    } else {
+        plural(abs / (86400 * 365), "year")
+    };
+    if seconds > 0 {
+        format!("{} ago", text)
+    } else {
+        format!("in {}", text)
+    }
+}
+
+impl DurationStyle {
+    pub fn parse(s: &str) -> CrushResult<DurationStyle> {
+        one_of("duration_style", s, DURATION_STYLES)?;
+        Ok(match s {
+            "short" => DurationStyle::Short,
+            "verbose" => DurationStyle::Verbose,
+            "iso" => DurationStyle::Iso,
+            "seconds" => DurationStyle::Seconds,
+            _ => DurationStyle::Clock,
+        })
+    }
+}
+
+/** The length of a duration in nanoseconds, or as close as it can be represented. */
+pub fn nanos(d: &Duration) -> i128 {
+    d.num_nanoseconds()
+        .map(|n| n as i128)
+        .unwrap_or_else(|| d.num_milliseconds() as i128 * 1_000_000)
+}
+
+/** Every non-zero unit of a duration, written out. */
+fn verbose_duration(d: &Duration) -> String {
+    const UNITS: &[(&str, i128)] = &[
+        ("year", 365 * 86_400_000_000_000),
+        ("day", 86_400_000_000_000),
+        ("hour", 3_600_000_000_000),
+        ("minute", 60_000_000_000),
+        ("second", 1_000_000_000),
+        ("millisecond", 1_000_000),
+        ("microsecond", 1_000),
+        ("nanosecond", 1),
+    ];
+    let nanos = nanos(d);
+    let mut rest = nanos.abs();
+    let mut parts = Vec::new();
+    for (unit, size) in UNITS {
+        if rest >= *size {
+            parts.push(plural((rest / size) as i64, unit));
+            rest %= size;
+        }
+    }
+    match (parts.is_empty(), nanos < 0) {
+        (true, _) => "0 seconds".to_string(),
+        (false, true) => format!("-{}", parts.join(" ")),
+        (false, false) => parts.join(" "),
+    }
+}
+
+/** A duration in ISO 8601 format. Days are the largest unit, since years vary in length. */
+fn iso_duration(d: &Duration) -> String {
+    let nanos = nanos(d);
+    let mut rest = nanos.abs();
+    let days = rest / 86_400_000_000_000;
+    rest %= 86_400_000_000_000;
+    let hours = rest / 3_600_000_000_000;
+    rest %= 3_600_000_000_000;
+    let minutes = rest / 60_000_000_000;
+    rest %= 60_000_000_000;
+    let mut res = if nanos < 0 { "-P".to_string() } else { "P".to_string() };
+    if days != 0 {
+        res.push_str(&format!("{}D", days));
+    }
+    if hours != 0 || minutes != 0 || rest != 0 || days == 0 {
+        res.push('T');
+        if hours != 0 {
+            res.push_str(&format!("{}H", hours));
+        }
+        if minutes != 0 {
+            res.push_str(&format!("{}M", minutes));
+        }
+        if rest != 0 || (hours == 0 && minutes == 0) {
+            let seconds = format!("{}.{:09}", rest / 1_000_000_000, rest % 1_000_000_000);
+            res.push_str(seconds.trim_end_matches('0').trim_end_matches('.'));
+            res.push('S');
+        }
+    }
+    res
+}
+
+/** The two most significant units of a duration. */
+fn short_duration(d: &Duration) -> String {
+    const UNITS: &[(&str, i128)] = &[
+        ("y", 365 * 86_400_000_000_000),
+        ("d", 86_400_000_000_000),
+        ("h", 3_600_000_000_000),
+        ("m", 60_000_000_000),
+        ("s", 1_000_000_000),
+        ("ms", 1_000_000),
+        ("µs", 1_000),
+        ("ns", 1),
+    ];
+    let nanos = nanos(d);
+    let sign = if nanos < 0 { "-" } else { "" };
+    let nanos = nanos.abs();
+    match UNITS.iter().position(|(_, size)| nanos >= *size) {
+        None => "0s".to_string(),
+        Some(idx) => {
+            let (unit, size) = UNITS[idx];
+            let mut res = format!("{}{}{}", sign, nanos / size, unit);
+            if let Some((next_unit, next_size)) = UNITS.get(idx + 1) {
+                let rest = nanos % size / next_size;
+                if rest != 0 {
+                    res.push_str(&format!(" {}{}", rest, next_unit));
+                }
+            }
+            res
+        }
+    }
+}
+
+impl ValueFormat {
+    /** Validate and apply a single option. */
+    pub fn set(&mut self, name: &str, value: &Value) -> CrushResult<()> {
+        match (name, value) {
+            ("time_format", Value::String(s)) => {
+                if StrftimeItems::new(s).any(|item| item == Item::Error) {
+                    return argument_error_legacy(format!("Invalid time format {}", s));
+                }
+                self.time_format = s.to_string();
+            }
+            ("relative_time", Value::Bool(b)) => self.relative_time = *b,
+            ("duration_style", Value::String(s)) => {
+                self.duration_style = DurationStyle::parse(s)?;
+            }
+            ("byte_size", Value::String(s)) => {
+                one_of(name, s, BYTE_SIZE_STYLES)?;
+                self.byte_size = match s.as_str() {
+                    "binary" => ByteSizeStyle::Binary,
+                    "decimal" => ByteSizeStyle::Decimal,
+                    _ => ByteSizeStyle::Bytes,
+                };
+            }
+            ("float_precision", Value::Integer(i)) => {
+                if *i < -1 || *i > MAX_PRECISION {
+                    return argument_error_legacy(format!(
+                        "float_precision must be between -1 and {}", MAX_PRECISION));
+                }
+                self.float_precision = if *i < 0 { None } else { Some(*i as usize) };
+            }
+            ("decimal_separator", Value::String(s)) => {
+                if s.is_empty() {
+                    return argument_error_legacy("decimal_separator can not be empty");
+                }
+                self.decimal_separator = if s == "locale" { None } else { Some(s.to_string()) };
+            }
+            (_, value) => return argument_error_legacy(format!(
+                "Invalid value format option {} of type {}", name, value.value_type())),
+        }
+        Ok(())
+    }
+
+    /** Update the decimal separator used when decimal_separator is set to "locale". */
+    pub fn set_locale_decimal(&mut self, separator: &str) {
+        self.locale_decimal = separator.to_string();
+    }
+
+    fn decimal_separator(&self) -> &str {
+        self.decimal_separator.as_deref().unwrap_or(&self.locale_decimal)
+    }
+
+    /** Replace the decimal point of a formatted number with the configured separator. */
+    fn with_separator(&self, number: String) -> String {
+        match self.decimal_separator() {
+            "." => number,
+            separator => number.replacen('.', separator, 1),
+        }
+    }
+
+    /** Format a time according to these options. */
+    pub fn format_time(&self, time: &DateTime<FixedOffset>) -> String {
+        self.time(time, &now())
+    }
+
+    fn time(&self, time: &DateTime<FixedOffset>, now: &DateTime<FixedOffset>) -> String {
+        if self.relative_time {
+            relative(time, now)
+        } else {
+            time.format(&self.time_format).to_string()
+        }
+    }
+
+    /** Format a duration according to these options. */
+    pub fn format_duration(&self, d: &Duration) -> String {
+        self.format_duration_with_style(d, self.duration_style)
+    }
+
+    /** Format a duration in the specified style, regardless of the configured one. */
+    pub fn format_duration_with_style(&self, d: &Duration, style: DurationStyle) -> String {
+        match style {
+            DurationStyle::Clock => duration_format(d),
+            DurationStyle::Short => short_duration(d),
+            DurationStyle::Verbose => verbose_duration(d),
+            DurationStyle::Iso => iso_duration(d),
+            DurationStyle::Seconds => self.format_float(
+                d.num_nanoseconds()
+                    .map(|n| n as f64 / 1e9)
+                    .unwrap_or_else(|| d.num_milliseconds() as f64 / 1e3)),
+        }
+    }
+
+    /** Format a float according to these options. */
+    pub fn format_float(&self, f: f64) -> String {
+        self.with_separator(match self.float_precision {
+            Some(precision) => format!("{:.*}", precision, f),
+            None => f.to_string(),
+        })
+    }
+
+    /**
+    Format a byte size according to these options, or None if byte sizes should be shown as plain
+    integers.
+    */
+    pub fn format_byte_size(&self, size: i128) -> Option<String> {
+        let (base, units) = match self.byte_size {
+            ByteSizeStyle::Bytes => return None,
+            ByteSizeStyle::Binary => (1024.0, ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"]),
+            ByteSizeStyle::Decimal => (1000.0, ["B", "kB", "MB", "GB", "TB", "PB", "EB"]),
+        };
+        if (size.abs() as f64) < base {
+            return Some(format!("{} B", size));
+        }
+        let mut value = size as f64;
+        let mut unit = 0;
+        while value.abs() >= base && unit + 1 < units.len() {
+            value /= base;
+            unit += 1;
+        }
+        Some(format!("{} {}", self.with_separator(format!("{:.1}", value)), units[unit]))
+    }
+}
+
+fn value_format_set(mut context: CommandContext) -> CrushResult<()> {
+    let this = context.this.r#struct()?;
+    let name = context.arguments.string(0)?;
+    let value = context.arguments.value(1)?;
+    let current = mandate(
+        this.get(&name),
+        format!("Unknown value format option crush:value_format:{}", name))?;
+    if current.value_type() != value.value_type() {
+        return argument_error_legacy(format!(
+            "Expected crush:value_format:{} to be of type {}, not {}",
+            name, current.value_type(), value.value_type()));
+    }
+    let mut format = context.global_state.value_format();
+    format.set(&name, &value)?;
+    this.set(&name, value);
+    context.global_state.set_value_format(format);
+    context.output.send(Value::Empty())
+}
+
+/**
+Create the crush:value_format struct, populated with the default options. Assigning to a field
+updates the options used by the output formatter.
+*/
+pub fn create() -> Struct {
+    let class = Struct::new(
+        vec![
+            ("__setattr__", Value::Command(<dyn CrushCommand>::command(
+                value_format_set, false,
+                vec!["global".to_string(), "crush".to_string(), "value_format".to_string(), "__setattr__".to_string()],
+                "crush:value_format:__setattr__ name:string value:any",
+                "Modify the specified value formatting option",
+                None,
+                Known(ValueType::Empty),
+                vec![],
+            ))),
+        ],
+        None,
+    );
+    Struct::new(
+        vec![
+            ("time_format", Value::string(DEFAULT_TIME_FORMAT)),
+            ("relative_time", Value::Bool(false)),
+            ("duration_style", Value::string("clock")),
+            ("byte_size", Value::string("bytes")),
+            ("float_precision", Value::Integer(-1)),
+            ("decimal_separator", Value::string(".")),
+        ],
+        Some(class),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Offset, TimeZone, Utc};
+
+    #[test]
+    fn check_relative() {
+        let now = Utc.timestamp_nanos(1_591_012_800_000_000_000).with_timezone(&Utc.fix());
+        assert_eq!(relative(&now, &now), "just now");
+        assert_eq!(relative(&(now - Duration::seconds(30)), &now), "30 sec ago");
+        assert_eq!(relative(&(now - Duration::minutes(3)), &now), "3 min ago");
+        assert_eq!(relative(&(now - Duration::hours(1)), &now), "1 hour ago");
+        assert_eq!(relative(&(now + Duration::days(2)), &now), "in 2 days");
+        assert_eq!(relative(&(now - Duration::days(400)), &now), "1 year ago");
+    }
+
+    #[test]
+    fn check_short_duration() {
+        assert_eq!(short_duration(&Duration::seconds(5400)), "1h 30m");
+        assert_eq!(short_duration(&Duration::milliseconds(850)), "850ms");
+        assert_eq!(short_duration(&Duration::seconds(60)), "1m");
+        assert_eq!(short_duration(&Duration::seconds(-90)), "-1m 30s");
+        assert_eq!(short_duration(&Duration::zero()), "0s");
+    }
+
+    #[test]
+    fn check_verbose_duration() {
+        assert_eq!(verbose_duration(&Duration::seconds(5400)), "1 hour 30 minutes");
+        assert_eq!(verbose_duration(&Duration::seconds(86401)), "1 day 1 second");
+        assert_eq!(verbose_duration(&Duration::milliseconds(-1500)), "-1 second 500 milliseconds");
+        assert_eq!(verbose_duration(&Duration::zero()), "0 seconds");
+    }
+
+    #[test]
+    fn check_iso_duration() {
+        assert_eq!(iso_duration(&Duration::seconds(5400)), "PT1H30M");
+        assert_eq!(iso_duration(&Duration::hours(60)), "P2DT12H");
+        assert_eq!(iso_duration(&Duration::days(2)), "P2D");
+        assert_eq!(iso_duration(&Duration::milliseconds(1500)), "PT1.5S");
+        assert_eq!(iso_duration(&Duration::minutes(-5)), "-PT5M");
+        assert_eq!(iso_duration(&Duration::zero()), "PT0S");
+        for d in &[Duration::seconds(5400), Duration::hours(60), Duration::milliseconds(1500), Duration::minutes(-5)] {
+            assert_eq!(crate::util::time::parse_duration(&iso_duration(d)).unwrap(), *d);
+        }
+    }
+
+    #[test]
+    fn check_float() {
+        let mut format = ValueFormat::default();
+        assert_eq!(format.format_float(1.25), "1.25");
+        format.set("float_precision", &Value::Integer(1)).unwrap();
+        format.set("decimal_separator", &Value::string(",")).unwrap();
+        assert_eq!(format.format_float(3.14159), "3,1");
+        format.set("decimal_separator", &Value::string("locale")).unwrap();
+        assert_eq!(format.format_float(3.14159), "3.1");
+        assert!(format.set("float_precision", &Value::Integer(-2)).is_err());
+    }
+
+    #[test]
+    fn check_byte_size() {
+        let mut format = ValueFormat::default();
+        assert_eq!(format.format_byte_size(2048), None);
+        format.byte_size = ByteSizeStyle::Binary;
+        assert_eq!(format.format_byte_size(512), Some("512 B".to_string()));
+        assert_eq!(format.format_byte_size(1536), Some("1.5 KiB".to_string()));
+        assert_eq!(format.format_byte_size(3 * 1024 * 1024), Some("3.0 MiB".to_string()));
+        format.byte_size = ByteSizeStyle::Decimal;
+        assert_eq!(format.format_byte_size(1500), Some("1.5 kB".to_string()));
+    }
+
+    #[test]
+    fn check_set() {
+        let mut format = ValueFormat::default();
+        assert!(format.set("time_format", &Value::string("%H:%M")).is_ok());
+        assert!(format.set("time_format", &Value::string("%Q")).is_err());
+        assert!(format.set("duration_style", &Value::string("short")).is_ok());
+        assert_eq!(format.duration_style, DurationStyle::Short);
+        assert!(format.set("byte_size", &Value::string("huge")).is_err());
+        assert!(format.set("byte_size", &Value::Integer(1)).is_err());
+    }
+}
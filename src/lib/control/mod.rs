@@ -1,4 +1,4 @@
-use crate::lang::errors::{argument_error_legacy, to_crush_error, CrushResult, mandate, data_error};
+use crate::lang::errors::{argument_error_legacy, error, to_crush_error, CrushResult, mandate, data_error};
 use crate::lang::data::scope::Scope;
 use crate::lang::{
     data::binary::BinaryReader, execution_context::CommandContext, data::list::List, value::Value,
@@ -8,19 +8,29 @@ use signature::signature;
 use std::env;
 
 use crate::lang::command::OutputType::Known;
-use chrono::Duration;
+use chrono::{Duration, Local};
 use std::path::PathBuf;
+use crate::util::time::parse_duration;
 use crate::lang::data::table::{ColumnType, Row};
 use std::io::{Write, Read};
-use std::process::Stdio;
+use std::process::{Child, Stdio};
+use std::cmp::min;
+use std::collections::HashMap;
+use nix::sys::signal::{self, Signal};
+use nix::unistd::Pid;
 use std::borrow::BorrowMut;
 use crate::lang::value::Value::BinaryInputStream;
 use os_pipe::PipeReader;
+use crate::lang::debugger;
+use crate::lang::threads::ThreadStore;
+use crossbeam::channel::{bounded, RecvTimeoutError};
 
 mod r#for;
 mod r#if;
 mod r#loop;
+mod retry;
 mod sudo;
+mod timeout;
 mod timer;
 mod r#while;
 
@@ -48,6 +58,19 @@ fn r#continue(context: CommandContext) -> CrushResult<()> {
     context.output.empty()
 }
 
+#[signature(
+breakpoint,
+can_block = false,
+short = "Pause execution and start the debugger.",
+long = "The debugger is a nested prompt running in the scope of the breakpoint, where local variables\n    can be inspected and modified. Use \"step\" to run until the next job in the current closure,\n    \"step_into\" to also stop inside of called closures, \"continue\" to run until the next\n    breakpoint and \"backtrace\" to show the closure invocations leading up to the breakpoint.",
+output = Known(ValueType::Empty))]
+struct Breakpoint {}
+
+fn breakpoint(context: CommandContext) -> CrushResult<()> {
+    debugger::pause("breakpoint", &context.scope, &context.global_state)?;
+    context.output.empty()
+}
+
 fn cmd(mut context: CommandContext) -> CrushResult<()> {
     if context.arguments.is_empty() {
         return argument_error_legacy("No command given");
@@ -84,8 +107,8 @@ fn cmd(mut context: CommandContext) -> CrushResult<()> {
                     .stdout(Stdio::inherit())
                     .stderr(Stdio::inherit());
 
-                to_crush_error(to_crush_error(cmd.spawn())?.wait())?;
-                Ok(())
+                let child = to_crush_error(cmd.spawn())?;
+                wait_for_child(child, &context.scope, context.global_state.threads())
             } else {
                 let input = context.input.recv()?;
 
@@ -100,6 +123,7 @@ fn cmd(mut context: CommandContext) -> CrushResult<()> {
                 let mut stdin = mandate(child.stdin.take(), "Expected stdin stream")?;
 
                 let threads = context.global_state.threads().clone();
+                let scope = context.scope.clone();
 
                 match input {
                     Value::Empty() => {
@@ -135,15 +159,83 @@ fn cmd(mut context: CommandContext) -> CrushResult<()> {
                     Ok(())
                 })?;
 
-                child.wait()?;
-
-                Ok(())
+                wait_for_child(child, &scope, &threads)
             }
         }
         _ => argument_error_legacy("Not a valid command"),
     }
 }
 
+/** How often a command waiting for a child process checks if it has been cancelled. */
+const CANCEL_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_millis(50);
+
+/**
+    Wait for a child process to exit, killing it if the scope it runs in is cancelled. The child is
+    waited for on a separate thread, so its exit is noticed right away, while the cancellation flag
+    of the scope is checked between waits.
+*/
+fn wait_for_child(mut child: Child, scope: &Scope, threads: &ThreadStore) -> CrushResult<()> {
+    let pid = child.id();
+    let (sender, receiver) = bounded(1);
+    threads.spawn("cmd:wait", move || {
+        let _ = sender.send(child.wait());
+        Ok(())
+    })?;
+    loop {
+        match receiver.recv_timeout(CANCEL_CHECK_INTERVAL) {
+            Ok(res) => return to_crush_error(res).map(|_| ()),
+            Err(RecvTimeoutError::Timeout) => {
+                if scope.is_cancelled() {
+                    kill_process_tree(pid);
+                    return to_crush_error(mandate(receiver.recv().ok(), "Lost track of child process")?)
+                        .map(|_| ());
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => return error("Lost track of child process"),
+        }
+    }
+}
+
+/**
+    Kill a process along with all of its descendants.
+*/
+fn kill_process_tree(pid: u32) {
+    let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+    if let Ok(processes) = psutil::process::processes() {
+        for process in processes.into_iter().flatten() {
+            if let Ok(Some(ppid)) = process.ppid() {
+                children.entry(ppid).or_default().push(process.pid());
+            }
+        }
+    }
+
+    let mut pending = vec![pid];
+    while let Some(pid) = pending.pop() {
+        if let Some(descendants) = children.get(&pid) {
+            for descendant in descendants {
+                let _ = signal::kill(Pid::from_raw(*descendant as i32), Signal::SIGKILL);
+                pending.push(*descendant);
+            }
+        }
+    }
+
+    let _ = signal::kill(Pid::from_raw(pid as i32), Signal::SIGKILL);
+}
+
+/**
+    Sleep for the specified amount of time, waking up early if the scope is cancelled.
+*/
+fn cancellable_sleep(duration: Duration, scope: &Scope) -> CrushResult<()> {
+    let deadline = Local::now() + duration;
+    loop {
+        let remaining = deadline - Local::now();
+        if remaining <= Duration::zero() || scope.is_cancelled() {
+            return Ok(());
+        }
+        std::thread::sleep(to_crush_error(min(remaining, Duration::milliseconds(50)).to_std())?);
+    }
+}
+
 impl BinaryReader for PipeReader {
     fn clone(&self) -> Box<dyn BinaryReader + Send + Sync> {
         self.clone()
@@ -154,16 +246,22 @@ impl BinaryReader for PipeReader {
 sleep,
 can_block = true,
 short = "Pause execution of commands for the specified amount of time",
-long = "    Execute the specified command all specified hosts"
+long = "The duration can also be given as a string, like \"1h30m\" or \"PT5M\".",
+example = "sleep \"2.5s\""
 )]
 struct Sleep {
     #[description("the time to sleep for.")]
-    duration: Duration,
+    duration: Value,
 }
 
 fn sleep(context: CommandContext) -> CrushResult<()> {
     let cfg = Sleep::parse(context.arguments, &context.global_state.printer())?;
-    std::thread::sleep(to_crush_error(cfg.duration.to_std())?);
+    let duration = match cfg.duration {
+        Value::Duration(d) => d,
+        Value::String(s) => parse_duration(&s)?,
+        v => return argument_error_legacy(format!("Expected a duration, got a {}", v.value_type())),
+    };
+    cancellable_sleep(duration, &context.scope)?;
     context.output.send(Value::Empty())?;
     Ok(())
 }
@@ -247,7 +345,10 @@ pub fn declare(root: &Scope) -> CrushResult<()> {
             )?;
             Break::declare(env)?;
             timer::Timer::declare(env)?;
+            timeout::Timeout::declare(env)?;
+            retry::Retry::declare(env)?;
             Continue::declare(env)?;
+            Breakpoint::declare(env)?;
             Sleep::declare(env)?;
             Bg::declare(env)?;
             Fg::declare(env)?;
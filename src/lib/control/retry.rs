@@ -0,0 +1,89 @@
+use crate::lang::command::Command;
+use crate::lang::errors::{argument_error_legacy, CrushResult};
+use crate::lang::execution_context::CommandContext;
+use crate::lang::pipe::{empty_channel, pipe};
+use crate::lang::value::Value;
+use chrono::Duration;
+use crossbeam::channel::bounded;
+use rand::Rng;
+use signature::signature;
+
+#[signature(
+retry,
+can_block = true,
+short = "Run a command, retrying it if it fails",
+long = "After every failed attempt, retry waits before trying again. The delay starts out at the\n    specified backoff and is doubled after every attempt. A random jitter of up to 50% in either\n    direction is applied to each delay, to avoid many clients retrying in lockstep. If the last\n    attempt fails, its error is returned.\n\n    The output of an attempt is only passed on once the attempt has succeeded, so output streams\n    are read to the end first. For the same reason, any input is read in full before the first\n    attempt, and every attempt gets its own copy of it.",
+example = "retry times=5 backoff=(duration:of seconds=2) {remote:exec {hostname} host=\"flaky.example.com\"}")]
+pub struct Retry {
+    #[description("the command to run.")]
+    body: Command,
+    #[description("the maximum number of attempts.")]
+    #[default(3usize)]
+    times: usize,
+    #[description("the delay before the first retry. Defaults to one second.")]
+    backoff: Option<Duration>,
+}
+
+fn jitter(delay: Duration) -> Duration {
+    let factor: f64 = rand::thread_rng().gen_range(0.5, 1.5);
+    Duration::milliseconds((delay.num_milliseconds() as f64 * factor) as i64)
+}
+
+fn retry(context: CommandContext) -> CrushResult<()> {
+    let cfg: Retry = Retry::parse(context.arguments.clone(), &context.global_state.printer())?;
+    if cfg.times == 0 {
+        return argument_error_legacy("The number of attempts must be at least one");
+    }
+    let mut delay = cfg.backoff.unwrap_or_else(|| Duration::seconds(1));
+
+    // Every attempt needs to see the same input, so it can't be passed on as a stream
+    let input = context.input.recv()?.materialize()?;
+
+    let mut attempt = 1;
+    loop {
+        let scope = context.scope.create_child(&context.scope, false);
+        scope.trap_errors();
+        let input_receiver = if context.input.is_pipeline() {
+            let (input_sender, input_receiver) = pipe();
+            input_sender.send(input.clone())?;
+            input_receiver
+        } else {
+            empty_channel()
+        };
+        let (sender, receiver) = pipe();
+        let (output_sender, output_receiver) = bounded(1);
+        // The output is read on a separate thread, so that a body producing more output than the
+        // pipe can hold doesn't block forever
+        context.global_state.threads().spawn("retry:output", move || {
+            let _ = output_sender.send(receiver.recv().map(|value| value.materialize()));
+            Ok(())
+        })?;
+        let res = cfg.body.copy().invoke(CommandContext {
+            input: input_receiver,
+            output: sender,
+            arguments: vec![],
+            scope: scope.clone(),
+            this: None,
+            global_state: context.global_state.clone(),
+        });
+        let output = output_receiver.recv();
+
+        let mut errors = scope.take_errors();
+        if let Err(err) = res {
+            errors.insert(0, err);
+        }
+        if errors.is_empty() {
+            return match output {
+                Ok(Ok(value)) => context.output.send(value?),
+                _ => context.output.send(Value::Empty()),
+            };
+        }
+        if attempt == cfg.times || context.scope.is_cancelled() {
+            return Err(errors.remove(0));
+        }
+
+        super::cancellable_sleep(jitter(delay), &context.scope)?;
+        delay = delay.checked_add(&delay).unwrap_or(delay);
+        attempt += 1;
+    }
+}
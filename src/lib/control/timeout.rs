@@ -0,0 +1,68 @@
+use crate::lang::command::Command;
+use crate::lang::errors::{error, timeout_error, to_crush_error, CrushResult};
+use crate::lang::execution_context::CommandContext;
+use crate::lang::value::Value;
+use crate::lang::data::scope::Scope;
+use crate::lang::pipe::pipe;
+use chrono::{Duration, Local};
+use std::cmp::max;
+use crossbeam::channel::{bounded, RecvTimeoutError};
+use signature::signature;
+
+#[signature(
+timeout,
+can_block = true,
+short = "Run a command, cancelling it if it does not finish in time",
+long = "If the command has not finished when the timeout expires, the command and any external\n    processes started by it are cancelled, and timeout fails with a timeout error.\n\n    Commands that don't check for cancellation, like remote:exec and http, can not be cancelled.\n    Timeout still fails once the timeout expires, but such commands keep running in the\n    background until they finish on their own.",
+example = "timeout (duration:of seconds=10) {http \"https://example.com\"}")]
+pub struct Timeout {
+    #[description("the maximum amount of time the command may run for.")]
+    duration: Duration,
+    #[description("the command to run.")]
+    body: Command,
+}
+
+fn timed_out(scope: &Scope, duration: Duration) -> CrushResult<()> {
+    scope.cancel();
+    timeout_error(format!("Command timed out after {}", Value::Duration(duration)))
+}
+
+fn timeout(context: CommandContext) -> CrushResult<()> {
+    let cfg: Timeout = Timeout::parse(context.arguments.clone(), &context.global_state.printer())?;
+    let deadline = Local::now() + cfg.duration;
+    let scope = context.scope.create_child(&context.scope, false);
+    // The body gets its own output, so that nothing it sends after the timeout gets through
+    let (body_output, body_value) = pipe();
+    let body_context = CommandContext {
+        input: context.input.clone(),
+        output: body_output,
+        arguments: vec![],
+        scope: scope.clone(),
+        this: None,
+        global_state: context.global_state.clone(),
+    };
+
+    let (sender, receiver) = bounded(1);
+    let body = cfg.body;
+    context.global_state.threads().spawn(
+        "timeout",
+        move || {
+            let _ = sender.send(body.invoke(body_context));
+            Ok(())
+        },
+    )?;
+
+    let remaining = || max(deadline - Local::now(), Duration::zero());
+    match body_value.recv_timeout(remaining()) {
+        Ok(value) => context.output.send(value)?,
+        Err(RecvTimeoutError::Timeout) => return timed_out(&scope, cfg.duration),
+        // The body finished without any output, its result tells how
+        Err(RecvTimeoutError::Disconnected) => {}
+    }
+
+    match receiver.recv_timeout(to_crush_error(remaining().to_std())?) {
+        Ok(res) => res,
+        Err(RecvTimeoutError::Timeout) => timed_out(&scope, cfg.duration),
+        Err(RecvTimeoutError::Disconnected) => error("Command exited unexpectedly"),
+    }
+}
@@ -1,15 +1,22 @@
-use crate::lang::errors::{CrushResult};
+use crate::lang::errors::{CrushResult, argument_error_legacy};
 use crate::lang::execution_context::CommandContext;
 use crate::lang::data::scope::Scope;
 use crate::lang::value::{Value, ValueType};
 use crate::lang::data::table::{ColumnType, Row};
 use signature::signature;
+use crate::util::time;
 use crate::lang::command::OutputType::Known;
 use nix::unistd::Pid;
 use crate::lang::data::dict::Dict;
 use std::env;
 use lazy_static::lazy_static;
 use crate::lang::command::Command;
+use crate::lang::interactive::config;
+use crate::lang::hooks;
+use crate::lang::table_format;
+use crate::lang::value_format;
+use crate::lang::global_state::GlobalState;
+use crate::lang::output_format::OutputFormat;
 
 fn make_env() -> Value {
     let e = Dict::new(ValueType::String, ValueType::String);
@@ -24,6 +31,14 @@ lazy_static! {
         ColumnType::new("created", ValueType::Time),
         ColumnType::new("name", ValueType::String),
     ];
+    static ref HISTORY_OUTPUT_TYPE: Vec<ColumnType> = vec![
+        ColumnType::new("command", ValueType::String),
+        ColumnType::new("cwd", ValueType::File),
+        ColumnType::new("host", ValueType::String),
+        ColumnType::new("start", ValueType::Time),
+        ColumnType::new("duration", ValueType::Duration),
+        ColumnType::new("error", ValueType::Any),
+    ];
 }
 
 #[signature(threads, output = Known(ValueType::TableInputStream(THREADS_OUTPUT_TYPE.clone())), short = "All the subthreads crush is currently running")]
@@ -34,13 +49,37 @@ fn threads(context: CommandContext) -> CrushResult<()> {
 
     for t in context.global_state.threads().current()? {
         output.send(Row::new(vec![
-            Value::Time(t.creation_time),
+            Value::Time(time::local(t.creation_time)),
             Value::String(t.name),
         ]))?;
     }
     Ok(())
 }
 
+#[signature(
+history,
+output = Known(ValueType::TableInputStream(HISTORY_OUTPUT_TYPE.clone())),
+short = "All previous interactive commands",
+long = "Every entry contains the command, the working directory and host it was run in, when it was\n    started, how long it ran for and the last error it reported, if any.",
+example = "crush:history | tail 10")]
+struct History {}
+
+fn history(context: CommandContext) -> CrushResult<()> {
+    let output = context.output.initialize(HISTORY_OUTPUT_TYPE.clone())?;
+
+    for entry in context.global_state.history().entries() {
+        output.send(Row::new(vec![
+            Value::String(entry.command),
+            Value::File(entry.cwd),
+            Value::String(entry.host),
+            Value::Time(time::local(entry.start)),
+            Value::Duration(entry.duration),
+            entry.error.map(Value::String).unwrap_or(Value::Empty()),
+        ]))?;
+    }
+    Ok(())
+}
+
 #[signature(exit, output = Known(ValueType::Empty), short = "Exit the shell")]
 struct Exit {
     #[default(0)]
@@ -54,7 +93,12 @@ fn exit(context: CommandContext) -> CrushResult<()> {
     context.output.send(Value::Empty())
 }
 
-#[signature(prompt, can_block=false, short = "Set or get the prompt")]
+#[signature(
+prompt,
+can_block=false,
+short = "Set or get the prompt",
+long = "Without a closure, the default prompt is restored. The default prompt shows the current user,\n    host, working directory and git branch. The commands in the crush:segment namespace can be used\n    to build a custom prompt.",
+example = "crush:prompt {\"{}:{}> \":format (crush:segment:host) (crush:segment:directory)}")]
 struct Prompt {
     prompt: Option<Command>,
 }
@@ -65,6 +109,151 @@ fn prompt(context: CommandContext) -> CrushResult<()> {
     context.output.send(Value::Empty())
 }
 
+#[signature(
+right_prompt,
+can_block=false,
+short = "Set the prompt shown at the right edge of the terminal",
+long = "Without a closure, the default right prompt is restored. It shows if the last command failed,\n    and how long it ran for if it took more than a second.",
+example = "crush:right_prompt {time:now | time:format \"%H:%M\"}")]
+struct RightPrompt {
+    prompt: Option<Command>,
+}
+
+fn right_prompt(context: CommandContext) -> CrushResult<()> {
+    let cfg: RightPrompt = RightPrompt::parse(context.arguments, &context.global_state.printer())?;
+    context.global_state.set_right_prompt(cfg.prompt);
+    context.output.send(Value::Empty())
+}
+
+#[signature(
+trace,
+can_block = false,
+short = "Turn execution tracing on or off",
+long = "When tracing is on, every command is printed to standard error before it is invoked, along with\n    its fully evaluated arguments, the closure nesting depth and the location of the command in\n    the source. Without an argument, the current tracing state is returned.",
+example = "crush:trace \"on\"")]
+struct Trace {
+    #[description("turn tracing on or off. Either a boolean or one of the strings \"on\" and \"off\".")]
+    enabled: Option<Value>,
+}
+
+fn trace(context: CommandContext) -> CrushResult<()> {
+    let cfg: Trace = Trace::parse(context.arguments, &context.global_state.printer())?;
+    match cfg.enabled {
+        None => context.output.send(Value::Bool(context.global_state.trace())),
+        Some(value) => {
+            let enabled = match value {
+                Value::Bool(b) => b,
+                Value::String(s) if s == "on" => true,
+                Value::String(s) if s == "off" => false,
+                _ => return argument_error_legacy("Expected a boolean or one of the strings \"on\" and \"off\""),
+            };
+            context.global_state.set_trace(enabled);
+            context.output.send(Value::Empty())
+        }
+    }
+}
+
+#[signature(
+output_format,
+can_block = false,
+short = "Set the format the output formatter writes values in",
+long = "The pretty format prints human readable tables. The json format prints one JSON document per\n    line, and one line per row of a table. The tsv format prints tab separated values with a header\n    line for every table, and the pup format writes every value as a pup document.\n\n    The default is pretty if standard output is a terminal and json otherwise. It can also be set\n    using the --format command line option. Without an argument, the current format is returned.",
+example = "crush:output_format \"tsv\"")]
+struct SetOutputFormat {
+    #[description("one of \"pretty\", \"json\", \"tsv\" and \"pup\".")]
+    format: Option<String>,
+}
+
+fn output_format(context: CommandContext) -> CrushResult<()> {
+    let cfg: SetOutputFormat = SetOutputFormat::parse(context.arguments, &context.global_state.printer())?;
+    match cfg.format {
+        None => context.output.send(Value::string(context.global_state.output_format().name())),
+        Some(format) => {
+            context.global_state.set_output_format(OutputFormat::parse(&format)?);
+            context.output.send(Value::Empty())
+        }
+    }
+}
+
+mod segment {
+    use super::*;
+    use crate::lang::interactive::prompt;
+    use chrono::Duration;
+
+    #[signature(user, can_block = false, output = Known(ValueType::String), short = "The name of the current user.")]
+    pub struct User {}
+
+    fn user(context: CommandContext) -> CrushResult<()> {
+        context.output.send(Value::String(prompt::user()))
+    }
+
+    #[signature(host, can_block = false, output = Known(ValueType::String), short = "The name of this host.")]
+    pub struct Host {}
+
+    fn host(context: CommandContext) -> CrushResult<()> {
+        context.output.send(Value::String(prompt::host()))
+    }
+
+    #[signature(
+    directory,
+    can_block = false,
+    output = Known(ValueType::String),
+    short = "The current working directory, with the home directory shown as ~.",
+    example = "crush:segment:directory abbreviate=false")]
+    pub struct Directory {
+        #[default(true)]
+        #[description("shorten all but the last path component to a single character.")]
+        abbreviate: bool,
+    }
+
+    fn directory(context: CommandContext) -> CrushResult<()> {
+        let cfg: Directory = Directory::parse(context.arguments, &context.global_state.printer())?;
+        context.output.send(Value::String(prompt::directory(cfg.abbreviate)))
+    }
+
+    #[signature(
+    git,
+    can_block = false,
+    output = Known(ValueType::String),
+    short = "The git branch of the current working directory.",
+    long = "The branch is followed by a * if any tracked file has been modified. Outside of a git\n    repository, the empty string is returned. The state is read directly from the .git directory,\n    git itself is never run.")]
+    pub struct Git {}
+
+    fn git(context: CommandContext) -> CrushResult<()> {
+        context.output.send(Value::String(prompt::git(&context.global_state)))
+    }
+
+    #[signature(
+    status,
+    can_block = false,
+    output = Known(ValueType::String),
+    short = "A marker if the last interactive command failed, otherwise the empty string.")]
+    pub struct Status {}
+
+    fn status(context: CommandContext) -> CrushResult<()> {
+        context.output.send(Value::String(prompt::status(&context.global_state)))
+    }
+
+    #[signature(
+    duration,
+    can_block = false,
+    output = Known(ValueType::String),
+    short = "How long the last interactive command ran for.",
+    long = "The empty string is returned if the command was quicker than the minimum duration.",
+    example = "crush:segment:duration minimum=(duration:of seconds=5)")]
+    pub struct JobDuration {
+        #[description("the shortest duration to show.")]
+        minimum: Option<Duration>,
+    }
+
+    fn duration(context: CommandContext) -> CrushResult<()> {
+        let cfg: JobDuration = JobDuration::parse(context.arguments, &context.global_state.printer())?;
+        context.output.send(Value::String(prompt::duration(
+            &context.global_state,
+            cfg.minimum.unwrap_or_else(|| Duration::seconds(0)))))
+    }
+}
+
 mod locale {
     use super::*;
     use num_format::SystemLocale;
@@ -94,7 +283,7 @@ mod locale {
 
     fn locale_complete(
         cmd: &PartialCommandResult,
-        _cursor: usize,
+        cursor: usize,
         _scope: &Scope,
         res: &mut Vec<Completion>,
     ) -> CrushResult<()> {
@@ -105,7 +294,7 @@ mod locale {
                     res.push(Completion::new(
                         escape(&name),
                         name,
-                        0,
+                        cursor,
                     ))
                 }
 
@@ -114,7 +303,7 @@ mod locale {
                         res.push(Completion::new(
                             format!("{}\" ", escape_without_quotes(&name[stripped_prefix.len()..])),
                             name,
-                            0,
+                            cursor,
                         ));
                     }
                 }
@@ -148,7 +337,8 @@ mod locale {
     }
 }
 
-pub fn declare(root: &Scope) -> CrushResult<()> {
+pub fn declare(root: &Scope, global_state: &GlobalState) -> CrushResult<()> {
+    let theme = global_state.theme();
     root.create_namespace(
         "crush",
         "Metadata about this Crush shell instance",
@@ -158,16 +348,46 @@ pub fn declare(root: &Scope) -> CrushResult<()> {
 
             let highlight = Dict::new(ValueType::String, ValueType::String);
             highlight.insert(Value::string("operator"), Value::string(""))?;
-            highlight.insert(Value::string("string_literal"), Value::string(""))?;
-            highlight.insert(Value::string("file_literal"), Value::string(""))?;
+            highlight.insert(Value::string("string_literal"), Value::string("\x1b[33m"))?;
+            highlight.insert(Value::string("file_literal"), Value::string("\x1b[35m"))?;
             highlight.insert(Value::string("label"), Value::string(""))?;
-            highlight.insert(Value::string("numeric_literal"), Value::string(""))?;
+            highlight.insert(Value::string("numeric_literal"), Value::string("\x1b[35m"))?;
+            highlight.insert(Value::string("field"), Value::string("\x1b[32m"))?;
+            highlight.insert(Value::string("command"), Value::string("\x1b[1;32m"))?;
+            highlight.insert(Value::string("namespace"), Value::string("\x1b[1;34m"))?;
+            highlight.insert(Value::string("variable"), Value::string("\x1b[36m"))?;
+            highlight.insert(Value::string("argument"), Value::string("\x1b[34m"))?;
+            highlight.insert(Value::string("unknown"), Value::string("\x1b[31m"))?;
+            highlight.insert(Value::string("error"), Value::string("\x1b[1;41m"))?;
             crush.declare("highlight", Value::Dict(highlight))?;
 
+            crush.declare("config", Value::Struct(config::create()))?;
+            crush.declare("hooks", Value::Struct(hooks::create()))?;
+            crush.declare("table", Value::Struct(table_format::create()))?;
+            crush.declare("value_format", Value::Struct(value_format::create()))?;
+            crush.declare("theme", Value::Dict(theme))?;
             crush.declare("env", make_env())?;
             Prompt::declare(crush)?;
+            RightPrompt::declare(crush)?;
             Threads::declare(crush)?;
+            History::declare(crush)?;
             Exit::declare(crush)?;
+            Trace::declare(crush)?;
+            SetOutputFormat::declare(crush)?;
+
+            crush.create_namespace(
+                "segment",
+                "Building blocks for a custom prompt",
+                Box::new(move |env| {
+                    segment::User::declare(env)?;
+                    segment::Host::declare(env)?;
+                    segment::Directory::declare(env)?;
+                    segment::Git::declare(env)?;
+                    segment::Status::declare(env)?;
+                    segment::JobDuration::declare(env)?;
+                    Ok(())
+                }),
+            )?;
 
             crush.create_namespace(
                 "locale",
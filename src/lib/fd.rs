@@ -15,7 +15,7 @@ use crate::util::hex::from_hex;
 use dns_lookup::lookup_addr;
 use std::collections::hash_map::Entry;
 use crate::lang::pipe::OutputStream;
-use std::net::Ipv6Addr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use crate::lang::printer::Printer;
 use std::path::PathBuf;
 
@@ -79,10 +79,10 @@ mod procfs {
     lazy_static! {
     static ref NET_OUTPUT_TYPE: Vec<ColumnType> = vec![
         ColumnType::new("type", ValueType::String),
-        ColumnType::new("local_ip", ValueType::String),
+        ColumnType::new("local_ip", ValueType::Ip),
         ColumnType::new("local_port", ValueType::Integer),
         ColumnType::new("remote_host", ValueType::String),
-        ColumnType::new("remote_ip", ValueType::String),
+        ColumnType::new("remote_ip", ValueType::Ip),
         ColumnType::new("remote_port", ValueType::Integer),
         ColumnType::new("inode", ValueType::Integer),
         ColumnType::new("creator", ValueType::String),
@@ -103,7 +103,7 @@ mod procfs {
     long = "fd:network accepts no arguments.")]
     pub struct Network {}
 
-    fn parse_addr(addr: &str) -> CrushResult<(String, u16)> {
+    fn parse_addr(addr: &str) -> CrushResult<(IpAddr, u16)> {
         let parts = addr.split(':').collect::<Vec<_>>();
         if parts.len() != 2 {
             return data_error("Invalid address");
@@ -114,9 +114,8 @@ mod procfs {
         let ip = match parts[0].len() {
             8 => {
                 let ip_bytes = from_hex(parts[0])?;
-                format!(
-                    "{}.{}.{}.{}",
-                    ip_bytes[3], ip_bytes[2], ip_bytes[1], ip_bytes[0])
+                IpAddr::V4(Ipv4Addr::new(
+                    ip_bytes[3], ip_bytes[2], ip_bytes[1], ip_bytes[0]))
             }
             32 => {
                 let obtuse = format!(
@@ -130,8 +129,7 @@ mod procfs {
                     &parts[0][24..28],
                     &parts[0][28..32],
                 );
-                let ip = to_crush_error(obtuse.parse::<Ipv6Addr>())?;
-                ip.to_string()
+                IpAddr::V6(to_crush_error(obtuse.parse::<Ipv6Addr>())?)
             }
             _ => return data_error(format!("Invalid ip address {}", parts[0])),
         };
@@ -139,14 +137,13 @@ mod procfs {
         Ok((ip, port))
     }
 
-    fn lookup(ip: &str, cache: &mut HashMap<String, String>) -> CrushResult<String> {
-        match cache.entry(ip.to_string()) {
+    fn lookup(ip: &IpAddr, cache: &mut HashMap<IpAddr, String>) -> CrushResult<String> {
+        match cache.entry(*ip) {
             Entry::Occupied(e) => {
                 Ok(e.get().clone())
             }
             Entry::Vacant(e) => {
-                let ip: std::net::IpAddr = ip.parse().unwrap();
-                let host = lookup_addr(&ip).unwrap_or_else(|_| "?".to_string());
+                let host = lookup_addr(ip).unwrap_or_else(|_| "?".to_string());
                 e.insert(host.clone());
                 Ok(host)
             }
@@ -211,7 +208,7 @@ mod procfs {
     fn handle_socket_file(
         users: &HashMap<Uid, String>,
         pids: &mut HashMap<u32, Vec<u32>>,
-        hosts: &mut HashMap<String, String>,
+        hosts: &mut HashMap<IpAddr, String>,
         file_type: &str,
         printer: &Printer,
         output: &OutputStream) -> CrushResult<()> {
@@ -241,10 +238,10 @@ mod procfs {
                     for pid in e.get().iter() {
                         output.send(Row::new(vec![
                             Value::string(file_type),
-                            Value::string(&local_ip),
+                            Value::Ip(local_ip),
                             Value::Integer(local_port as i128),
                             Value::String(lookup(&remote_ip, hosts)?),
-                            Value::string(&remote_ip),
+                            Value::Ip(remote_ip),
                             Value::Integer(remote_port as i128),
                             Value::Integer(inode as i128),
                             users.get(&nix::unistd::Uid::from_raw(uid)).map(|s| Value::string(s)).unwrap_or_else(|| Value::string("?")),
@@ -255,10 +252,10 @@ mod procfs {
                 Entry::Vacant(_) => {
                     output.send(Row::new(vec![
                         Value::string(file_type),
-                        Value::String(local_ip),
+                        Value::Ip(local_ip),
                         Value::Integer(local_port as i128),
                         Value::String(lookup(&remote_ip, hosts)?),
-                        Value::String(remote_ip),
+                        Value::Ip(remote_ip),
                         Value::Integer(remote_port as i128),
                         Value::Integer(inode as i128),
                         users.get(&nix::unistd::Uid::from_raw(uid)).map(|s| Value::string(s)).unwrap_or_else(|| Value::string("?")),
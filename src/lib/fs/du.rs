@@ -7,7 +7,7 @@ use crate::lang::pipe::OutputStream;
 use crate::lang::data::table::Row;
 use crate::lang::value::Value;
 use crate::lang::value::ValueType;
-use crate::lang::data::table::ColumnType;
+use crate::lang::data::table::{ColumnFormat, ColumnType};
 use lazy_static::lazy_static;
 use crate::lang::command::OutputType::Known;
 use crate::util::directory_lister::{DirectoryLister, directory_lister};
@@ -15,7 +15,7 @@ use  std::os::unix::fs::MetadataExt;
 
 lazy_static! {
     static ref OUTPUT_TYPE: Vec<ColumnType> = vec![
-        ColumnType::new("size", ValueType::Integer),
+        ColumnType::new("size", ValueType::Integer).with_format(ColumnFormat::ByteSize),
         ColumnType::new("blocks", ValueType::Integer),
         ColumnType::new("file", ValueType::File),
     ];
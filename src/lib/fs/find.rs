@@ -13,7 +13,8 @@ use crate::lang::errors::{error, to_crush_error, CrushResult};
 use crate::lang::execution_context::CommandContext;
 use crate::lang::files::Files;
 use crate::lang::pipe::OutputStream;
-use crate::lang::{data::table::ColumnType, data::table::Row, value::Value, value::ValueType};
+use crate::lang::{data::table::ColumnFormat, data::table::ColumnType, data::table::Row, value::Value, value::ValueType};
+use crate::util::time;
 use crate::util::user_map::{create_user_map, create_group_map};
 use signature::signature;
 use std::os::unix::fs::PermissionsExt;
@@ -24,7 +25,7 @@ lazy_static! {
         ColumnType::new("permissions", ValueType::String),
         ColumnType::new("user", ValueType::String),
         ColumnType::new("group", ValueType::String),
-        ColumnType::new("size", ValueType::Integer),
+        ColumnType::new("size", ValueType::Integer).with_format(ColumnFormat::ByteSize),
         ColumnType::new("modified", ValueType::Time),
         ColumnType::new("type", ValueType::String),
         ColumnType::new("file", ValueType::File),
@@ -85,7 +86,7 @@ fn insert_entity(
         users.get(&Uid::from_raw(meta.uid())).map(|n| Value::string(n)).unwrap_or_else(|| Value::string("?")),
         groups.get(&Gid::from_raw(meta.gid())).map(|n| Value::string(n)).unwrap_or_else(|| Value::string("?")),
         Value::Integer(i128::from(meta.len())),
-        Value::Time(modified_datetime),
+        Value::Time(time::local(modified_datetime)),
         Value::string(type_str),
         Value::File(f),
     ]))?;
@@ -1,44 +1,241 @@
 use crate::lang::command::OutputType::Known;
-use crate::lang::errors::{to_crush_error, CrushResult};
+use crate::lang::completion::Completion;
+use crate::lang::completion::parse::{LastArgument, PartialCommandResult};
+use crate::lang::data::table::{ColumnType, Row};
+use crate::lang::directory_history::DirectoryHistory;
+use crate::lang::errors::{error, mandate, CrushResult};
 use crate::lang::execution_context::CommandContext;
-use crate::lang::help::Help;
+use crate::lang::global_state::GlobalState;
+use crate::lang::hooks::{self, Hook};
+use crate::lang::help::{Help, full_help};
 use crate::lang::printer::Printer;
 use crate::lang::data::scope::Scope;
 use crate::lang::value::Value;
 use crate::lang::value::ValueType;
 use crate::util::file::{cwd, home};
+use crate::util::time;
 use signature::signature;
 use crate::lang::files::Files;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::convert::TryFrom;
+use crate::lang::interactive::crush_directories_file;
+use crate::util::escape::escape_without_quotes;
+use lazy_static::lazy_static;
+use chrono::Local;
+
+lazy_static! {
+    static ref DIRS_OUTPUT_TYPE: Vec<ColumnType> = vec![
+        ColumnType::new("index", ValueType::Integer),
+        ColumnType::new("directory", ValueType::File),
+    ];
+    static ref JUMP_OUTPUT_TYPE: Vec<ColumnType> = vec![
+        ColumnType::new("directory", ValueType::File),
+        ColumnType::new("score", ValueType::Float),
+        ColumnType::new("last_visit", ValueType::Time),
+    ];
+}
 
 mod du;
 mod find;
 
+/**
+Change the working directory, recording it in the directory history, and run the cd hooks.
+*/
+fn change_directory(dir: &Path, scope: &Scope, global_state: &GlobalState) -> CrushResult<()> {
+    let previous = cwd()?;
+    global_state.directories().change_directory(dir, global_state.printer())?;
+    hooks::run(
+        Hook::Cd,
+        &[("previous", Value::File(previous)), ("directory", Value::File(cwd()?))],
+        scope,
+        global_state);
+    Ok(())
+}
+
 #[signature(
 cd,
 can_block=false,
 output = Known(ValueType::Empty),
 short = "Change to the specified working directory.",
+long = "Without a destination, change to the home directory. With the string \"-\" as the destination,\n    change back to the previous working directory. The dash has to be quoted, because a bare - is\n    the subtraction operator.",
+example = "cd \"-\"",
 )]
 struct Cd {
     #[unnamed()]
-    #[description("the new working directory.")]
+    #[description("the new working directory, or \"-\" for the previous one.")]
     destination: Files,
 }
 
 fn cd(context: CommandContext) -> CrushResult<()> {
-    let cfg: Cd = Cd::parse(context.arguments, &context.global_state.printer())?;
+    let directories = context.global_state.directories();
+    let back = match context.arguments.as_slice() {
+        [argument] => argument.argument_type.is_none() && argument.value == Value::string("-"),
+        _ => false,
+    };
+
+    let dir = if back {
+        mandate(directories.previous(), "No previous working directory")?
+    } else {
+        let cfg: Cd = Cd::parse(context.arguments, &context.global_state.printer())?;
+        match cfg.destination.had_entries() {
+            true => PathBuf::try_from(cfg.destination),
+            false => home(),
+        }?
+    };
+
+    change_directory(&dir, &context.scope, &context.global_state)?;
+    context.output.send(Value::Empty())
+}
+
+#[signature(
+pushd,
+can_block = false,
+output = Known(ValueType::Empty),
+short = "Push the working directory onto the directory stack and change to the specified directory.",
+long = "Without a destination, swap the working directory with the top of the directory stack.",
+example = "pushd /tmp",
+)]
+struct Pushd {
+    #[unnamed()]
+    #[description("the new working directory.")]
+    destination: Files,
+}
+
+fn pushd(context: CommandContext) -> CrushResult<()> {
+    let cfg: Pushd = Pushd::parse(context.arguments, &context.global_state.printer())?;
+    let directories = context.global_state.directories();
+    let popped = !cfg.destination.had_entries();
+    let dir = match popped {
+        true => directories.pop()?,
+        false => PathBuf::try_from(cfg.destination)?,
+    };
+    let old = cwd()?;
+    if let Err(e) = change_directory(&dir, &context.scope, &context.global_state) {
+        if popped {
+            directories.push(dir);
+        }
+        return Err(e);
+    }
+    directories.push(old);
+    context.output.send(Value::Empty())
+}
 
-    let dir = match cfg.destination.had_entries() {
-        true => PathBuf::try_from(cfg.destination),
-        false => home(),
-    }?;
+#[signature(
+popd,
+can_block = false,
+output = Known(ValueType::Empty),
+short = "Pop a directory off the directory stack and change to it.",
+)]
+struct Popd {}
 
-    to_crush_error(std::env::set_current_dir(dir))?;
+fn popd(context: CommandContext) -> CrushResult<()> {
+    let directories = context.global_state.directories();
+    let dir = directories.pop()?;
+    if let Err(e) = change_directory(&dir, &context.scope, &context.global_state) {
+        directories.push(dir);
+        return Err(e);
+    }
     context.output.send(Value::Empty())
 }
 
+#[signature(
+dirs,
+can_block = false,
+output = Known(ValueType::TableInputStream(DIRS_OUTPUT_TYPE.clone())),
+short = "The directory stack.",
+long = "The working directory has index 0, followed by the most recently pushed directory.",
+)]
+struct Dirs {}
+
+fn dirs(context: CommandContext) -> CrushResult<()> {
+    let output = context.output.initialize(DIRS_OUTPUT_TYPE.clone())?;
+    let mut all = vec![cwd()?];
+    all.append(&mut context.global_state.directories().stack());
+    for (idx, dir) in all.into_iter().enumerate() {
+        output.send(Row::new(vec![Value::Integer(idx as i128), Value::File(dir)]))?;
+    }
+    Ok(())
+}
+
+fn directory_complete(
+    cmd: &PartialCommandResult,
+    cursor: usize,
+    _scope: &Scope,
+    res: &mut Vec<Completion>,
+) -> CrushResult<()> {
+    let entries = DirectoryHistory::read(&crush_directories_file()?)?;
+    match &cmd.last_argument {
+        LastArgument::Unknown => {
+            for entry in DirectoryHistory::rank(entries, &[]) {
+                let path = entry.path.to_string_lossy().to_string();
+                res.push(Completion::new(
+                    format!("\"{}\" ", escape_without_quotes(&path)),
+                    path,
+                    cursor,
+                ));
+            }
+        }
+
+        LastArgument::QuotedString(prefix) => {
+            let start = cursor.saturating_sub(escape_without_quotes(prefix).len() + 1);
+            for entry in DirectoryHistory::rank(entries, &[prefix.clone()]) {
+                let path = entry.path.to_string_lossy().to_string();
+                res.push(Completion::replacing(
+                    format!("\"{}\" ", escape_without_quotes(&path)),
+                    path,
+                    start,
+                    cursor,
+                ));
+            }
+        }
+
+        _ => {}
+    }
+    Ok(())
+}
+
+#[signature(
+jump,
+can_block = false,
+output = Known(ValueType::TableInputStream(JUMP_OUTPUT_TYPE.clone())),
+short = "Change to the most frecent directory matching the search terms.",
+long = "Every directory that is changed to is recorded in a database, ranked by frecency, i.e. how\n    frequently and recently it was visited. jump changes to the highest ranked directory whose\n    path contains all of the search terms, in order. If no directory matches exactly, directories\n    containing the letters of the search terms in order are considered. Without search terms, the\n    whole database is returned. jump is also available as z.",
+example = "jump \"crush\" \"src\"",
+)]
+struct Jump {
+    #[unnamed()]
+    #[custom_completion(directory_complete)]
+    #[description("the search terms.")]
+    terms: Vec<String>,
+}
+
+fn jump(context: CommandContext) -> CrushResult<()> {
+    let cfg: Jump = Jump::parse(context.arguments, &context.global_state.printer())?;
+    let directories = context.global_state.directories();
+    let matches = directories.matches(&cfg.terms);
+    if cfg.terms.is_empty() {
+        let output = context.output.initialize(JUMP_OUTPUT_TYPE.clone())?;
+        let now = Local::now();
+        for entry in matches {
+            let score = entry.score(now);
+            output.send(Row::new(vec![
+                Value::File(entry.path),
+                Value::Float(score),
+                Value::Time(time::local(entry.last_visit)),
+            ]))?;
+        }
+        Ok(())
+    } else {
+        match matches.first() {
+            Some(entry) => {
+                change_directory(&entry.path, &context.scope, &context.global_state)?;
+                context.output.send(Value::Empty())
+            }
+            None => error(format!("No directory matching {}", cfg.terms.join(" "))),
+        }
+    }
+}
+
 #[signature(
 pwd,
 can_block=false,
@@ -52,18 +249,7 @@ fn pwd(context: CommandContext) -> CrushResult<()> {
 }
 
 fn halp(o: &dyn Help, printer: &Printer) {
-    printer.line(
-        match o.long_help() {
-            None => format!("{}\n\n    {}", o.signature(), o.short_help()),
-            Some(long_help) => format!(
-                "{}\n\n    {}\n\n{}",
-                o.signature(),
-                o.short_help(),
-                long_help
-            ),
-        }
-        .as_str(),
-    );
+    printer.line(&full_help(o));
 }
 
 #[signature(
@@ -120,6 +306,11 @@ pub fn declare(root: &Scope) -> CrushResult<()> {
         Box::new(move |env| {
             find::Find::declare(env)?;
             Cd::declare(env)?;
+            Pushd::declare(env)?;
+            Popd::declare(env)?;
+            Dirs::declare(env)?;
+            Jump::declare(env)?;
+            env.alias("z", "jump")?;
             Pwd::declare(env)?;
             HelpSignature::declare(env)?;
             du::Du::declare(env)?;
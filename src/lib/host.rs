@@ -11,6 +11,8 @@ use lazy_static::lazy_static;
 use battery::State;
 use chrono::Duration;
 use crate::lang::command::OutputType::Known;
+use crate::lang::data::list::List;
+use dns_lookup::lookup_host;
 
 #[signature(name, can_block = false, short = "name of this host")]
 struct Name {}
@@ -21,6 +23,30 @@ fn name(context: CommandContext) -> CrushResult<()> {
         .send(Value::String(to_crush_error(sys_info::hostname())?))
 }
 
+#[signature(
+lookup,
+can_block = true,
+output = Known(ValueType::List(Box::from(ValueType::Ip))),
+short = "Look up the ip addresses of a host name",
+example = "host:lookup \"example.com\"")]
+struct Lookup {
+    #[description("the host name to look up.")]
+    name: String,
+}
+
+fn lookup(context: CommandContext) -> CrushResult<()> {
+    let cfg: Lookup = Lookup::parse(context.arguments, &context.global_state.printer())?;
+    let mut addresses = Vec::new();
+    for ip in to_crush_error(lookup_host(&cfg.name))? {
+        if !addresses.contains(&ip) {
+            addresses.push(ip);
+        }
+    }
+    context.output.send(Value::List(List::new(
+        ValueType::Ip,
+        addresses.into_iter().map(Value::Ip).collect())))
+}
+
 lazy_static! {
     static ref BATTERY_OUTPUT_TYPE: Vec<ColumnType> = vec![
         ColumnType::new("vendor", ValueType::String),
@@ -169,6 +195,7 @@ pub fn declare(root: &Scope) -> CrushResult<()> {
         "Metadata about this host",
         Box::new(move |host| {
             Battery::declare(host)?;
+            Lookup::declare(host)?;
             Mem::declare(host)?;
             Name::declare(host)?;
             host.create_namespace(
@@ -82,7 +82,7 @@ fn from_json(json_value: &serde_json::Value) -> CrushResult<Value> {
     }
 }
 
-fn to_json(value: Value) -> CrushResult<serde_json::Value> {
+pub fn to_json(value: Value) -> CrushResult<serde_json::Value> {
     match value.materialize()? {
         Value::File(s) => Ok(serde_json::Value::from(mandate(
             s.to_str(),
@@ -100,6 +100,13 @@ fn to_json(value: Value) -> CrushResult<serde_json::Value> {
                 .collect::<CrushResult<Vec<_>>>()?,
         )),
 
+        Value::Set(s) => Ok(serde_json::Value::Array(
+            s.elements()
+                .drain(..)
+                .map(to_json)
+                .collect::<CrushResult<Vec<_>>>()?,
+        )),
+
         Value::Table(t) => {
             let types = t.types().to_vec();
             let structs = t
@@ -113,6 +120,10 @@ fn to_json(value: Value) -> CrushResult<serde_json::Value> {
 
         Value::Bool(b) => Ok(serde_json::Value::from(b)),
 
+        Value::Ip(ip) => Ok(serde_json::Value::from(ip.to_string())),
+
+        Value::Cidr(c) => Ok(serde_json::Value::from(c.to_string())),
+
         Value::Float(f) => Ok(serde_json::Value::from(f)),
 
         Value::Struct(s) => {
@@ -1,7 +1,8 @@
 use crate::lang::command::OutputType::Known;
 use crate::lang::errors::{argument_error_legacy, data_error, mandate, CrushResult, to_crush_error};
 use crate::lang::data::list::List;
-use crate::lang::pretty::PrettyPrinter;
+use crate::lang::pretty::{self, PrettyPrinter};
+use crate::lang::tree;
 use crate::lang::data::scope::Scope;
 use crate::lang::value::{Field, ValueType};
 use crate::lang::{execution_context::CommandContext, value::Value};
@@ -13,7 +14,7 @@ use crate::lang::interactive::config_dir;
 mod bin;
 mod csv;
 mod http;
-mod json;
+pub mod json;
 mod lines;
 mod pup;
 mod split;
@@ -71,7 +72,9 @@ struct Echo {
 
 fn echo(context: CommandContext) -> CrushResult<()> {
     let cfg: Echo = Echo::parse(context.arguments, &context.global_state.printer())?;
-    let pretty = PrettyPrinter::new(context.global_state.printer().clone(), context.global_state.grouping());
+    let pretty = PrettyPrinter::new(context.global_state.printer().clone(), context.global_state.grouping())
+        .with_value_format(context.global_state.value_format())
+        .with_theme(context.global_state.theme());
     for value in cfg.values {
         match (cfg.raw, &value) {
             (true, Value::String(s)) =>
@@ -147,6 +150,35 @@ fn readline(context: CommandContext) -> CrushResult<()> {
     context.output.send(Value::String(line))
 }
 
+#[signature(
+tree,
+can_block = true,
+short = "Print a value as a tree",
+long = "Nested structs, lists, dicts and tables are drawn as a tree, with one line per field, element,\n    entry or row. Values nested deeper than the specified depth are collapsed into a summary.\n    The output formatter uses the same layout for deeply nested values.",
+output = Known(ValueType::Empty),
+example = "json:from data.json | tree depth=2")]
+struct Tree {
+    #[description("the value to print. If not specified, the input is printed.")]
+    value: Option<Value>,
+    #[description("the number of levels to show before collapsing nested values.")]
+    #[default(tree::DEFAULT_MAX_DEPTH)]
+    depth: usize,
+}
+
+fn tree(context: CommandContext) -> CrushResult<()> {
+    let cfg: Tree = Tree::parse(context.arguments, &context.global_state.printer())?;
+    let depth = cfg.depth;
+    let value = match cfg.value {
+        Some(value) => value,
+        None => context.input.recv()?,
+    };
+    pretty::print_formatted(
+        value,
+        context.global_state.table_format(),
+        &context.global_state,
+        |pp, value| pp.print_tree(value, depth));
+    context.output.send(Value::Empty())
+}
 
 pub fn declare(root: &Scope) -> CrushResult<()> {
     let e = root.create_namespace(
@@ -165,6 +197,7 @@ pub fn declare(root: &Scope) -> CrushResult<()> {
 
             http::Http::declare(env)?;
             Echo::declare(env)?;
+            Tree::declare(env)?;
             Member::declare(env)?;
             Val::declare(env)?;
             Dir::declare(env)?;
@@ -14,6 +14,8 @@ use crate::lang::{data::list::List, data::r#struct::Struct, data::table::Table};
 use signature::signature;
 use std::collections::HashSet;
 use std::convert::TryFrom;
+use crate::util::time::{parse_time, Zone};
+use chrono::NaiveDateTime;
 
 fn from_toml(toml_value: &toml::Value) -> CrushResult<Value> {
     match toml_value {
@@ -69,10 +71,17 @@ fn from_toml(toml_value: &toml::Value) -> CrushResult<Value> {
                 .collect::<Result<Vec<(String, Value)>, CrushError>>()?,
             None,
         ))),
-        toml::Value::Datetime(_) => {
-            unimplemented!(
-                "Dates in the TOML library are currently opaque blobs, dates are unsupported"
-            );
+        toml::Value::Datetime(d) => {
+            // The toml library doesn't expose the parts of a datetime, so go via its string form.
+            // Dates and times of day without a date have no time type in crush.
+            let s = d.to_string();
+            match parse_time(&s) {
+                Ok(t) => Ok(Value::Time(t)),
+                Err(_) => match NaiveDateTime::parse_from_str(&s, "%Y-%m-%dT%H:%M:%S%.f") {
+                    Ok(t) => Ok(Value::Time(Zone::Local.localize(&t)?)),
+                    Err(_) => Ok(Value::String(s)),
+                },
+            }
         }
     }
 }
@@ -82,7 +91,7 @@ from,
 can_block = true,
 output = Unknown,
 short = "Parse toml format",
-long = "Input can either be a binary stream or a file. All Toml types except\n    datetime are supported. Datetime is not supported because the rust toml\n    currently doesn't support accessing the internal state of a datetime.",
+long = "Input can either be a binary stream or a file. Datetimes become time values, keeping their\n    UTC offset. Datetimes without an offset are in the local time zone, and dates or\n    times of day on their own become strings.",
 example = "toml:from Cargo.toml")]
 struct From {
     #[unnamed()]
@@ -142,7 +151,7 @@ fn to_toml(value: Value) -> CrushResult<toml::Value> {
 
         Value::Duration(d) => Ok(toml::Value::from(d.num_seconds())),
 
-        Value::Time(t) => Ok(toml::Value::from(t.to_rfc3339())),
+        Value::Time(t) => Ok(toml::Value::Datetime(to_crush_error(t.to_rfc3339().parse())?)),
 
         Value::Binary(b) => Ok(toml::Value::from(b)),
 
@@ -1,5 +1,6 @@
 use crate::lang::errors::to_crush_error;
 use crate::lang::execute;
+use crate::lang::debugger;
 use crate::lang::pipe::ValueSender;
 use crate::lang::{errors::CrushResult, data::scope::Scope};
 use std::fs::read_dir;
@@ -19,7 +20,7 @@ mod dbus;
 mod fd;
 mod fs;
 mod host;
-mod io;
+pub mod io;
 mod math;
 #[cfg(target_os = "linux")]
 mod proc;
@@ -84,11 +85,11 @@ fn load_external_namespace(
         "",
         Box::new(move |env| {
             let tmp_env: Scope = env.create_temporary_namespace();
-            execute::file(
+            debugger::without_stepping(&local_state, || execute::file(
                 &tmp_env,
                 &local_file,
                 &local_output,
-                &local_state)?;
+                &local_state))?;
             let data = tmp_env.export()?;
             for (k, v) in data.mapping {
                 env.declare(&k, v)?;
@@ -107,7 +108,7 @@ pub fn declare(
     cond::declare(root)?;
     constants::declare(root)?;
     control::declare(root)?;
-    crush::declare(root)?;
+    crush::declare(root, global_state)?;
     #[cfg(target_os = "linux")]
         dbus::declare(root)?;
     fd::declare(root)?;
@@ -2,7 +2,7 @@ use crate::lang::command::OutputType::Known;
 use crate::lang::errors::{error, to_crush_error, CrushResult};
 use crate::lang::execution_context::CommandContext;
 use crate::lang::data::scope::Scope;
-use crate::lang::data::table::ColumnType;
+use crate::lang::data::table::{ColumnFormat, ColumnType};
 use crate::util::user_map::create_user_map;
 use crate::{data::table::Row, lang::value::Value, lang::value::ValueType};
 use chrono::Duration;
@@ -22,8 +22,8 @@ lazy_static! {
         ColumnType::new("status", ValueType::String),
         ColumnType::new("user", ValueType::String),
         ColumnType::new("cpu", ValueType::Duration),
-        ColumnType::new("rss", ValueType::Integer),
-        ColumnType::new("vms", ValueType::Integer),
+        ColumnType::new("rss", ValueType::Integer).with_format(ColumnFormat::ByteSize),
+        ColumnType::new("vms", ValueType::Integer).with_format(ColumnFormat::ByteSize),
         ColumnType::new("name", ValueType::String),
     ];
 }
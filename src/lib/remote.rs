@@ -131,7 +131,7 @@ fn run_remote(
 
 fn ssh_host_complete(
     cmd: &PartialCommandResult,
-    _cursor: usize,
+    cursor: usize,
     _scope: &Scope,
     res: &mut Vec<Completion>,
 ) -> CrushResult<()> {
@@ -148,7 +148,7 @@ fn ssh_host_complete(
                 res.push(Completion::new(
                     completion,
                     host.name().unwrap_or(""),
-                    0,
+                    cursor,
                 ))
             }
 
@@ -158,7 +158,7 @@ fn ssh_host_complete(
                     res.push(Completion::new(
                         format!("{}\" ", escape_without_quotes(&completion[stripped_prefix.len()..])),
                         host.name().unwrap_or(""),
-                        0,
+                        cursor,
                     ));
                 }
             }
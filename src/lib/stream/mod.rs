@@ -9,6 +9,7 @@ mod enumerate;
 mod group;
 mod head;
 mod join;
+mod pmap;
 mod reverse;
 mod select;
 mod seq;
@@ -27,6 +28,7 @@ pub fn declare(root: &Scope) -> CrushResult<()> {
             count::Count::declare(env)?;
             drop::Drop::declare(env)?;
             each::Each::declare(env)?;
+            pmap::PMap::declare(env)?;
             enumerate::Enumerate::declare(env)?;
             head::Head::declare(env)?;
             tail::Tail::declare(env)?;
@@ -0,0 +1,186 @@
+use crate::lang::command::Command;
+use crate::lang::command::OutputType::Known;
+use crate::lang::errors::{argument_error_legacy, error, CrushResult};
+use crate::lang::execution_context::CommandContext;
+use crate::lang::pipe::{black_hole, empty_channel, pipe, OutputStream};
+use crate::lang::{argument::Argument, data::table::ColumnType};
+use crate::lang::{data::table::Row, value::Value, value::ValueType};
+use crate::lang::ast::Location;
+use crossbeam::channel::{bounded, unbounded, Receiver, Sender};
+use lazy_static::lazy_static;
+use signature::signature;
+use std::collections::HashMap;
+
+lazy_static! {
+    static ref OUTPUT_TYPE: Vec<ColumnType> = vec![
+        ColumnType::new("idx", ValueType::Integer),
+        ColumnType::new("value", ValueType::Any),
+        ColumnType::new("error", ValueType::Any),
+    ];
+}
+
+#[signature(
+pmap,
+can_block = true,
+output = Known(ValueType::TableInputStream(OUTPUT_TYPE.clone())),
+short = "Runs a command once for each row of input, using a pool of worker threads",
+long = "The columns of the row are exported to the environment using the column names. The output\n    contains the index of the input row, the output of the command and the error message, if\n    the command failed. A failing command does not stop processing of the remaining rows.",
+example = "files | pmap {sha256 file} parallel=8")]
+pub struct PMap {
+    #[description("the command to run.")]
+    body: Command,
+    #[description("the number of worker threads.")]
+    #[default(4usize)]
+    parallel: usize,
+    #[description("emit results in input order. If false, results are emitted as soon as they are ready.")]
+    #[default(true)]
+    ordered: bool,
+}
+
+fn run(
+    body: Command,
+    location: Location,
+    row: Row,
+    input_type: &[ColumnType],
+    base_context: &CommandContext,
+) -> CrushResult<Value> {
+    let arguments = Vec::from(row)
+        .drain(..)
+        .zip(input_type.iter())
+        .map(|(c, t)| Argument::named(t.name.as_ref(), c, location))
+        .collect();
+
+    // Errors are trapped so that the message of the failure can be put in the error column
+    let scope = base_context.scope.create_child(&base_context.scope, false);
+    scope.trap_errors();
+    let (sender, receiver) = pipe();
+
+    let res = body.invoke(CommandContext {
+        input: base_context.input.clone(),
+        output: sender,
+        arguments,
+        scope: scope.clone(),
+        this: None,
+        global_state: base_context.global_state.clone(),
+    });
+
+    let value = receiver.recv().ok().map(|value| value.materialize());
+    let mut errors = scope.take_errors();
+    if let Err(err) = res {
+        errors.insert(0, err);
+    }
+    if !errors.is_empty() {
+        return Err(errors.remove(0));
+    }
+    match value {
+        Some(value) => value,
+        None => error("The command did not produce any output"),
+    }
+}
+
+fn worker(
+    body: Command,
+    location: Location,
+    input_type: Vec<ColumnType>,
+    base_context: CommandContext,
+    jobs: Receiver<(i128, Row)>,
+    results: Sender<(i128, CrushResult<Value>)>,
+) -> CrushResult<()> {
+    while let Ok((idx, row)) = jobs.recv() {
+        let res = run(body.copy(), location, row, &input_type, &base_context);
+        if results.send((idx, res)).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn result_row(idx: i128, res: CrushResult<Value>) -> Row {
+    match res {
+        Ok(value) => Row::new(vec![Value::Integer(idx), value, Value::Empty()]),
+        Err(e) => Row::new(vec![Value::Integer(idx), Value::Empty(), Value::String(e.message())]),
+    }
+}
+
+fn emit(
+    output: OutputStream,
+    results: Receiver<(i128, CrushResult<Value>)>,
+    ordered: bool,
+) -> CrushResult<()> {
+    if ordered {
+        let mut pending = HashMap::new();
+        let mut next: i128 = 0;
+        while let Ok((idx, res)) = results.recv() {
+            pending.insert(idx, res);
+            while let Some(res) = pending.remove(&next) {
+                if output.send(result_row(next, res)).is_err() {
+                    return Ok(());
+                }
+                next += 1;
+            }
+        }
+    } else {
+        while let Ok((idx, res)) = results.recv() {
+            if output.send(result_row(idx, res)).is_err() {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn pmap(context: CommandContext) -> CrushResult<()> {
+    let cfg: PMap = PMap::parse(context.arguments.clone(), &context.global_state.printer())?;
+    let location = context.arguments[0].location;
+    if cfg.parallel == 0 {
+        return argument_error_legacy("The number of worker threads must be at least one");
+    }
+
+    match context.input.recv()?.stream() {
+        Some(mut input) => {
+            let base_context = CommandContext {
+                input: empty_channel(),
+                output: black_hole(),
+                arguments: vec![],
+                scope: context.scope.clone(),
+                this: None,
+                global_state: context.global_state.clone(),
+            };
+            let threads = context.global_state.threads();
+
+            let (job_sender, job_receiver) = bounded(cfg.parallel);
+            let (result_sender, result_receiver) = unbounded();
+
+            for _ in 0..cfg.parallel {
+                let body = cfg.body.copy();
+                let input_type = input.types().to_vec();
+                let base_context = base_context.clone();
+                let job_receiver = job_receiver.clone();
+                let result_sender = result_sender.clone();
+                threads.spawn(
+                    "pmap:worker",
+                    move || worker(body, location, input_type, base_context, job_receiver, result_sender),
+                )?;
+            }
+            drop(job_receiver);
+            drop(result_sender);
+
+            let output = context.output.initialize(OUTPUT_TYPE.clone())?;
+            let ordered = cfg.ordered;
+            threads.spawn(
+                "pmap:output",
+                move || emit(output, result_receiver, ordered),
+            )?;
+
+            let mut idx: i128 = 0;
+            while let Ok(row) = input.read() {
+                if job_sender.send((idx, row)).is_err() {
+                    break;
+                }
+                idx += 1;
+            }
+            Ok(())
+        }
+        None => error("Expected a stream"),
+    }
+}
@@ -6,7 +6,7 @@ use crate::lang::data::table::ColumnVec;
 use crate::{
     lang::errors::argument_error_legacy,
     lang::errors::CrushResult,
-    data::table::ColumnType,
+    data::table::{ColumnFormat, ColumnType},
     lang::{argument::Argument, data::table::Row, value::Value},
     util::replace::Replace,
 };
@@ -68,9 +68,14 @@ pub fn run(config: Config, mut input: Stream, context: CommandContext) -> CrushR
                     Source::Argument(idx) => row.cells()[*idx].clone(),
                 };
 
+                // Columns that are selected as is keep their format hint, e.g. byte sizes.
+                let format = match source {
+                    Source::Argument(idx) => input_type[*idx].format,
+                    Source::Closure(_) => ColumnFormat::Plain,
+                };
                 match location {
                     Action::Append(name) => {
-                        output_type.push(ColumnType::new(name.as_ref(), value.value_type()));
+                        output_type.push(ColumnType::new(name.as_ref(), value.value_type()).with_format(format));
                         first_result.push(value);
                     }
                     Action::Replace(idx) => {
@@ -9,7 +9,8 @@ use crate::lang::data::table::Row;
 use lazy_static::lazy_static;
 use crate::lang::{data::table::ColumnType, value::ValueType};
 use crate::lang::ordered_string_map::OrderedStringMap;
-use chrono::{DateTime, Local};
+use chrono::{DateTime, FixedOffset, Utc};
+use crate::util::time;
 use std::convert::TryFrom;
 use crate::lang::command::OutputType::Known;
 
@@ -58,9 +59,9 @@ fn parse_files(cfg: &JournalSignature) -> CrushResult<JournalFiles> {
     }
 }
 
-fn usec_since_epoch(tm: DateTime<Local>) -> CrushResult<u64> {
-    let epoch = DateTime::from(std::time::UNIX_EPOCH);
-    let duration = tm - epoch;
+fn usec_since_epoch(tm: DateTime<FixedOffset>) -> CrushResult<u64> {
+    let epoch: DateTime<Utc> = DateTime::from(std::time::UNIX_EPOCH);
+    let duration = tm.signed_duration_since(epoch);
     to_crush_error(u64::try_from(mandate(duration.num_microseconds(), "Time overflow")?))
 }
 
@@ -96,7 +97,7 @@ fn journal(context: CommandContext) -> CrushResult<()> {
                     row.iter().map(|(k, v)| (k, Value::String(v.clone()))).collect(),
                     None));
                 output.send(Row::new(vec![
-                    Value::Time(DateTime::from(journal.timestamp()?)),
+                    Value::Time(time::local(DateTime::from(journal.timestamp()?))),
                     data,
                 ]))?;
             }
@@ -0,0 +1,106 @@
+use crate::lang::command::Command;
+use crate::lang::command::OutputType::Known;
+use crate::lang::command::TypeMap;
+use crate::lang::errors::{argument_error_legacy, CrushResult};
+use crate::lang::execution_context::{ArgumentVector, This};
+use crate::lang::value::ValueType;
+use crate::lang::{execution_context::CommandContext, value::Value};
+use crate::util::ip::{self, Cidr};
+use lazy_static::lazy_static;
+use ordered_map::OrderedMap;
+use signature::signature;
+
+fn full(name: &'static str) -> Vec<&'static str> {
+    vec!["global", "types", "cidr", name]
+}
+
+lazy_static! {
+    pub static ref METHODS: OrderedMap<String, Command> = {
+        let mut res: OrderedMap<String, Command> = OrderedMap::new();
+        let path = vec!["global", "types", "cidr"];
+        Parse::declare_method(&mut res, &path);
+        res.declare(
+            full("contains"),
+            contains,
+            false,
+            "cidr:contains address:(ip|string)",
+            "True if the specified address is part of this network",
+            Some(
+                r#"    Example:
+
+    fd:network | where {(cidr:parse "10.0.0.0/8"):contains remote_ip}"#,
+            ),
+            Known(ValueType::Bool),
+            vec![],
+        );
+        Address::declare_method(&mut res, &path);
+        Prefix::declare_method(&mut res, &path);
+        IsPrivate::declare_method(&mut res, &path);
+        res
+    };
+}
+
+#[signature(
+parse,
+can_block = false,
+output = Known(ValueType::Cidr),
+short = "Parse a network in CIDR notation",
+long = "Any host bits of the address are cleared, so 10.1.2.3/8 becomes 10.0.0.0/8. An address without a prefix length is a network containing only that address.",
+example = "cidr:parse \"192.168.0.0/16\"",
+)]
+struct Parse {
+    #[description("the network to parse.")]
+    text: String,
+}
+
+fn parse(context: CommandContext) -> CrushResult<()> {
+    let cfg: Parse = Parse::parse(context.arguments, &context.global_state.printer())?;
+    context.output.send(Value::Cidr(Cidr::parse(&cfg.text)?))
+}
+
+fn contains(mut context: CommandContext) -> CrushResult<()> {
+    context.arguments.check_len(1)?;
+    let network = context.this.cidr()?;
+    let address = match context.arguments.value(0)? {
+        Value::Ip(address) => address,
+        Value::String(s) => ip::parse_ip(&s)?,
+        v => return argument_error_legacy(format!("Expected an ip address, found a {}", v.value_type())),
+    };
+    context.output.send(Value::Bool(network.contains(&address)))
+}
+
+#[signature(
+address,
+can_block = false,
+output = Known(ValueType::Ip),
+short = "The first address of this network",
+)]
+struct Address {}
+
+fn address(context: CommandContext) -> CrushResult<()> {
+    context.output.send(Value::Ip(context.this.cidr()?.address()))
+}
+
+#[signature(
+prefix,
+can_block = false,
+output = Known(ValueType::Integer),
+short = "The prefix length of this network, i.e. the number of bits in the network mask",
+)]
+struct Prefix {}
+
+fn prefix(context: CommandContext) -> CrushResult<()> {
+    context.output.send(Value::Integer(context.this.cidr()?.prefix() as i128))
+}
+
+#[signature(
+is_private,
+can_block = false,
+output = Known(ValueType::Bool),
+short = "True if this network is part of a private network",
+)]
+struct IsPrivate {}
+
+fn is_private(context: CommandContext) -> CrushResult<()> {
+    context.output.send(Value::Bool(context.this.cidr()?.is_private()))
+}
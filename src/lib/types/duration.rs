@@ -5,6 +5,8 @@ use crate::lang::errors::{argument_error_legacy, CrushResult};
 use crate::lang::execution_context::{ArgumentVector, This};
 use crate::lang::value::ValueType;
 use crate::lang::{execution_context::CommandContext, value::Value};
+use crate::lang::value_format::{nanos, DurationStyle};
+use crate::util::time::parse_duration;
 use chrono::Duration;
 use lazy_static::lazy_static;
 use ordered_map::OrderedMap;
@@ -51,6 +53,10 @@ lazy_static! {
             vec![],
             );
         Of::declare_method(&mut res, &path);
+        Parse::declare_method(&mut res, &path);
+        Format::declare_method(&mut res, &path);
+        Seconds::declare_method(&mut res, &path);
+        Milliseconds::declare_method(&mut res, &path);
         res.declare(
             full("__neg__"), neg, false,
             "neg duration",
@@ -136,3 +142,93 @@ fn neg(context: CommandContext) -> CrushResult<()> {
         .output
         .send(Value::Duration(-context.this.duration()?))
 }
+
+#[signature(
+parse,
+can_block = false,
+output = Known(ValueType::Duration),
+short = "Parse a duration",
+long = "Accepts a number of seconds, human readable durations like \"1h30m\", \"2 days\" or",
+long = "\"1 hour, 30 minutes\", ISO 8601 durations like \"PT5M\" and durations like \"1:30:00\". In ISO",
+long = "8601 durations, a year is 365 days and a month is 30 days.",
+example = "duration:parse \"1h30m\"",
+)]
+struct Parse {
+    #[description("the duration to parse.")]
+    text: String,
+}
+
+fn parse(context: CommandContext) -> CrushResult<()> {
+    let cfg: Parse = Parse::parse(context.arguments, &context.global_state.printer())?;
+    context.output.send(Value::Duration(parse_duration(&cfg.text)?))
+}
+
+#[signature(
+format,
+can_block = false,
+output = Known(ValueType::String),
+short = "Format this duration in the specified style",
+long = "The styles are clock (1:30:00), short (1h 30m), verbose (1 hour 30 minutes), iso (PT1H30M)",
+long = "and seconds (5400).",
+example = "(duration:of minutes=90):format \"iso\"",
+)]
+struct Format {
+    #[description("the style to format the duration in.")]
+    #[values("clock", "short", "verbose", "iso", "seconds")]
+    style: String,
+}
+
+fn format(context: CommandContext) -> CrushResult<()> {
+    let d = context.this.duration()?;
+    let cfg: Format = Format::parse(context.arguments, &context.global_state.printer())?;
+    let style = DurationStyle::parse(&cfg.style)?;
+    context.output.send(Value::String(context.global_state.value_format().format_duration_with_style(&d, style)))
+}
+
+fn total(d: &Duration, unit_nanos: i64, float: bool) -> Value {
+    let nanos = nanos(d);
+    if float {
+        Value::Float(nanos as f64 / unit_nanos as f64)
+    } else {
+        Value::Integer(nanos / unit_nanos as i128)
+    }
+}
+
+#[signature(
+seconds,
+can_block = false,
+output = Unknown,
+short = "The total number of seconds in this duration",
+long = "The result is an integer, rounded towards zero, unless float is true.",
+example = "(duration:parse \"1m30.5s\"):seconds float=true",
+)]
+struct Seconds {
+    #[description("return the number of seconds as a float, including fractions of a second.")]
+    #[default(false)]
+    float: bool,
+}
+
+fn seconds(context: CommandContext) -> CrushResult<()> {
+    let d = context.this.duration()?;
+    let cfg: Seconds = Seconds::parse(context.arguments, &context.global_state.printer())?;
+    context.output.send(total(&d, 1_000_000_000, cfg.float))
+}
+
+#[signature(
+milliseconds,
+can_block = false,
+output = Unknown,
+short = "The total number of milliseconds in this duration",
+long = "The result is an integer, rounded towards zero, unless float is true.",
+)]
+struct Milliseconds {
+    #[description("return the number of milliseconds as a float, including fractions of a millisecond.")]
+    #[default(false)]
+    float: bool,
+}
+
+fn milliseconds(context: CommandContext) -> CrushResult<()> {
+    let d = context.this.duration()?;
+    let cfg: Milliseconds = Milliseconds::parse(context.arguments, &context.global_state.printer())?;
+    context.output.send(total(&d, 1_000_000, cfg.float))
+}
@@ -0,0 +1,76 @@
+use crate::lang::command::Command;
+use crate::lang::command::OutputType::Known;
+use crate::lang::errors::CrushResult;
+use crate::lang::execution_context::This;
+use crate::lang::value::ValueType;
+use crate::lang::{execution_context::CommandContext, value::Value};
+use crate::util::ip;
+use lazy_static::lazy_static;
+use ordered_map::OrderedMap;
+use signature::signature;
+
+lazy_static! {
+    pub static ref METHODS: OrderedMap<String, Command> = {
+        let mut res: OrderedMap<String, Command> = OrderedMap::new();
+        let path = vec!["global", "types", "ip"];
+        Parse::declare_method(&mut res, &path);
+        IsPrivate::declare_method(&mut res, &path);
+        IsLoopback::declare_method(&mut res, &path);
+        Version::declare_method(&mut res, &path);
+        res
+    };
+}
+
+#[signature(
+parse,
+can_block = false,
+output = Known(ValueType::Ip),
+short = "Parse an IPv4 or IPv6 address",
+example = "ip:parse \"192.168.0.1\"",
+)]
+struct Parse {
+    #[description("the address to parse.")]
+    text: String,
+}
+
+fn parse(context: CommandContext) -> CrushResult<()> {
+    let cfg: Parse = Parse::parse(context.arguments, &context.global_state.printer())?;
+    context.output.send(Value::Ip(ip::parse_ip(&cfg.text)?))
+}
+
+#[signature(
+is_private,
+can_block = false,
+output = Known(ValueType::Bool),
+short = "True if this address belongs to a private network",
+long = "The private networks are 10.0.0.0/8, 172.16.0.0/12 and 192.168.0.0/16 for IPv4, and fc00::/7 for IPv6.",
+)]
+struct IsPrivate {}
+
+fn is_private(context: CommandContext) -> CrushResult<()> {
+    context.output.send(Value::Bool(ip::is_private(&context.this.ip()?)))
+}
+
+#[signature(
+is_loopback,
+can_block = false,
+output = Known(ValueType::Bool),
+short = "True if this is a loopback address, like 127.0.0.1 or ::1",
+)]
+struct IsLoopback {}
+
+fn is_loopback(context: CommandContext) -> CrushResult<()> {
+    context.output.send(Value::Bool(context.this.ip()?.is_loopback()))
+}
+
+#[signature(
+version,
+can_block = false,
+output = Known(ValueType::Integer),
+short = "The IP version of this address, either 4 or 6",
+)]
+struct Version {}
+
+fn version(context: CommandContext) -> CrushResult<()> {
+    context.output.send(Value::Integer(if context.this.ip()?.is_ipv4() { 4 } else { 6 }))
+}
@@ -12,15 +12,18 @@ use crate::lang::{data::r#struct::Struct, value::Value};
 use crate::lang::ordered_string_map::OrderedStringMap;
 use signature::signature;
 pub mod binary;
+pub mod cidr;
 pub mod dict;
 pub mod duration;
 pub mod file;
 pub mod float;
 pub mod glob;
 pub mod integer;
+pub mod ip;
 pub mod list;
 pub mod re;
 pub mod scope;
+pub mod set;
 pub mod string;
 pub mod table;
 pub mod table_input_stream;
@@ -229,9 +232,12 @@ pub fn declare(root: &Scope) -> CrushResult<()> {
             env.declare("re", Value::Type(ValueType::Regex))?;
             env.declare("duration", Value::Type(ValueType::Duration))?;
             env.declare("time", Value::Type(ValueType::Time))?;
+            env.declare("ip", Value::Type(ValueType::Ip))?;
+            env.declare("cidr", Value::Type(ValueType::Cidr))?;
             env.declare("dict", Value::Type(ValueType::Dict(
                 Box::from(ValueType::Empty),
                 Box::from(ValueType::Empty))))?;
+            env.declare("set", Value::Type(ValueType::Set(Box::from(ValueType::Empty))))?;
 
             env.declare("table", Value::Type(ValueType::Table(vec![])))?;
             env.declare("table_input_stream", Value::Type(ValueType::TableInputStream(vec![])))?;
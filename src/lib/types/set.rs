@@ -0,0 +1,337 @@
+use crate::lang::command::Command;
+use crate::lang::command::OutputType::{Known, Unknown};
+use crate::lang::command::TypeMap;
+use crate::lang::errors::{argument_error_legacy, data_error, mandate, CrushResult};
+use crate::lang::execution_context::{ArgumentVector, CommandContext, This};
+use crate::lang::value::Value;
+use crate::lang::{data::set::Set, value::ValueType};
+use lazy_static::lazy_static;
+use ordered_map::OrderedMap;
+use signature::signature;
+
+fn full(name: &'static str) -> Vec<&'static str> {
+    vec!["global", "types", "set", name]
+}
+
+lazy_static! {
+    pub static ref METHODS: OrderedMap<String, Command> = {
+        let mut res: OrderedMap<String, Command> = OrderedMap::new();
+        let path = vec!["global", "types", "set"];
+        Len::declare_method(&mut res, &path);
+        Empty::declare_method(&mut res, &path);
+        Call::declare_method(&mut res, &path);
+        Clone::declare_method(&mut res, &path);
+        Clear::declare_method(&mut res, &path);
+        ElementType::declare_method(&mut res, &path);
+        res.declare(
+            full("new"),
+            new,
+            false,
+            "set:new",
+            "Construct a new set",
+            Some(
+                r#"    Examples:
+    my_set := ((set string):new)"#,
+            ),
+            Unknown,
+            vec![],
+        );
+        res.declare(
+            full("of"),
+            of,
+            true,
+            "set:of element:any...",
+            "Create a new set containing the supplied elements",
+            Some(
+                r#"    If no elements are supplied as arguments, input must be a stream with
+    exactly one column. The elements must be of a hashable type.
+
+    Examples:
+    set:of 1 2 3
+    ls | select ^user | set:of"#,
+            ),
+            Unknown,
+            vec![],
+        );
+        res.declare(
+            full("add"),
+            add,
+            false,
+            "set:add element:any...",
+            "Add the specified elements to the set",
+            None,
+            Known(ValueType::Empty),
+            vec![],
+        );
+        res.declare(
+            full("remove"),
+            remove,
+            false,
+            "set:remove element:any...",
+            "Remove the specified elements from the set",
+            None,
+            Known(ValueType::Empty),
+            vec![],
+        );
+        res.declare(
+            full("contains"),
+            contains,
+            false,
+            "set:contains element:any",
+            "True if the specified element is in the set",
+            None,
+            Known(ValueType::Bool),
+            vec![],
+        );
+        res.declare(
+            full("union"),
+            union,
+            false,
+            "set:union other:set",
+            "A new set with the elements that are in either set",
+            Some("    The + operator is a shorthand for this method."),
+            Unknown,
+            vec![],
+        );
+        res.declare(
+            full("__add__"),
+            union,
+            false,
+            "set + other:set",
+            "A new set with the elements that are in either set",
+            None,
+            Unknown,
+            vec![],
+        );
+        res.declare(
+            full("intersection"),
+            intersection,
+            false,
+            "set:intersection other:set",
+            "A new set with the elements that are in both sets",
+            Some("    The * operator is a shorthand for this method."),
+            Unknown,
+            vec![],
+        );
+        res.declare(
+            full("__mul__"),
+            intersection,
+            false,
+            "set * other:set",
+            "A new set with the elements that are in both sets",
+            None,
+            Unknown,
+            vec![],
+        );
+        res.declare(
+            full("difference"),
+            difference,
+            false,
+            "set:difference other:set",
+            "A new set with the elements of this set that are not in the other set",
+            Some("    The - operator is a shorthand for this method."),
+            Unknown,
+            vec![],
+        );
+        res.declare(
+            full("__sub__"),
+            difference,
+            false,
+            "set - other:set",
+            "A new set with the elements of this set that are not in the other set",
+            None,
+            Unknown,
+            vec![],
+        );
+        res
+    };
+}
+
+#[signature(
+__call__,
+can_block = false,
+output = Known(ValueType::Type),
+short = "Returns a set type with the specified element type.",
+)]
+struct Call {
+    #[description("the type of the elements in the set.")]
+    element_type: ValueType,
+}
+
+fn __call__(context: CommandContext) -> CrushResult<()> {
+    match context.this.r#type()? {
+        ValueType::Set(t) => match *t {
+            ValueType::Empty => {
+                let cfg: Call = Call::parse(context.arguments, &context.global_state.printer())?;
+                context.output.send(Value::Type(ValueType::Set(Box::new(cfg.element_type))))
+            }
+            t => {
+                if context.arguments.is_empty() {
+                    context.output.send(Value::Type(ValueType::Set(Box::from(t))))
+                } else {
+                    argument_error_legacy("Tried to set subtype on a set that already has a subtype")
+                }
+            }
+        },
+        _ => argument_error_legacy("Invalid this, expected type set"),
+    }
+}
+
+fn new(context: CommandContext) -> CrushResult<()> {
+    context.arguments.check_len(0)?;
+    let t = context.this.r#type()?;
+    if let ValueType::Set(element_type) = t {
+        if !element_type.is_hashable() {
+            argument_error_legacy("Element type is not hashable")
+        } else {
+            context.output.send(Value::Set(Set::new(*element_type)))
+        }
+    } else {
+        argument_error_legacy("Expected a set type as this value")
+    }
+}
+
+fn of(mut context: CommandContext) -> CrushResult<()> {
+    match context.arguments.len() {
+        0 => {
+            let mut input = mandate(context.input.recv()?.stream(), "Expected a stream")?;
+            if input.types().len() != 1 {
+                return data_error("Expected input with exactly one column");
+            }
+            let element_type = input.types()[0].cell_type.clone();
+            if !element_type.is_hashable() {
+                return argument_error_legacy("Element type is not hashable");
+            }
+            let set = Set::new(element_type);
+            while let Ok(row) = input.read() {
+                set.insert(Vec::from(row).remove(0))?;
+            }
+            context.output.send(Value::Set(set))
+        }
+        _ => {
+            let set = Set::new_without_type(
+                context.arguments.drain(..).map(|a| a.value).collect())?;
+            context.output.send(Value::Set(set))
+        }
+    }
+}
+
+fn add(mut context: CommandContext) -> CrushResult<()> {
+    let set = context.this.set()?;
+    for arg in context.arguments.drain(..) {
+        set.insert(arg.value)?;
+    }
+    context.output.send(Value::Empty())
+}
+
+fn remove(mut context: CommandContext) -> CrushResult<()> {
+    let set = context.this.set()?;
+    for arg in context.arguments.drain(..) {
+        set.remove(&arg.value);
+    }
+    context.output.send(Value::Empty())
+}
+
+fn contains(mut context: CommandContext) -> CrushResult<()> {
+    context.arguments.check_len(1)?;
+    let set = context.this.set()?;
+    let value = context.arguments.value(0)?;
+    context.output.send(Value::Bool(set.contains(&value)))
+}
+
+fn union(mut context: CommandContext) -> CrushResult<()> {
+    context.arguments.check_len(1)?;
+    let set = context.this.set()?;
+    let other = context.arguments.set(0)?;
+    context.output.send(Value::Set(set.union(&other)))
+}
+
+fn intersection(mut context: CommandContext) -> CrushResult<()> {
+    context.arguments.check_len(1)?;
+    let set = context.this.set()?;
+    let other = context.arguments.set(0)?;
+    context.output.send(Value::Set(set.intersection(&other)))
+}
+
+fn difference(mut context: CommandContext) -> CrushResult<()> {
+    context.arguments.check_len(1)?;
+    let set = context.this.set()?;
+    let other = context.arguments.set(0)?;
+    context.output.send(Value::Set(set.difference(&other)))
+}
+
+#[signature(
+len,
+can_block = false,
+output = Known(ValueType::Integer),
+short = "The number of elements in the set.",
+)]
+struct Len {}
+
+fn len(context: CommandContext) -> CrushResult<()> {
+    context.arguments.check_len(0)?;
+    context
+        .output
+        .send(Value::Integer(context.this.set()?.len() as i128))
+}
+
+#[signature(
+clear,
+can_block = false,
+output = Unknown,
+short = "Remove all elements from this set.",
+)]
+struct Clear {
+}
+
+fn clear(context: CommandContext) -> CrushResult<()> {
+    context.arguments.check_len(0)?;
+    let s = context.this.set()?;
+    s.clear();
+    context.output.send(Value::Set(s))
+}
+
+#[signature(
+clone,
+can_block = false,
+output = Unknown,
+short = "Create a new set with the same elements as this one.",
+)]
+struct Clone {
+}
+
+fn clone(context: CommandContext) -> CrushResult<()> {
+    context.arguments.check_len(0)?;
+    let s = context.this.set()?;
+    context.output.send(Value::Set(s.copy()))
+}
+
+#[signature(
+empty,
+can_block = false,
+output = Known(ValueType::Bool),
+short = "True if there are no elements in the set.",
+)]
+struct Empty {}
+
+fn empty(context: CommandContext) -> CrushResult<()> {
+    context.arguments.check_len(0)?;
+    context
+        .output
+        .send(Value::Bool(context.this.set()?.len() == 0))
+}
+
+#[signature(
+element_type,
+can_block = false,
+output = Known(ValueType::Type),
+short = "the type of the elements in this set.",
+)]
+struct ElementType {}
+
+fn element_type(context: CommandContext) -> CrushResult<()> {
+    context.arguments.check_len(0)?;
+    context
+        .output
+        .send(Value::Type(context.this.set()?.element_type()))
+}
@@ -9,6 +9,7 @@ use lazy_static::lazy_static;
 use ordered_map::OrderedMap;
 use signature::signature;
 use crate::lang::ordered_string_map::OrderedStringMap;
+use crate::lang::pretty;
 
 lazy_static! {
     pub static ref METHODS: OrderedMap<String, Command> = {
@@ -17,6 +18,7 @@ lazy_static! {
         Call::declare_method(&mut res, &path);
         Len::declare_method(&mut res, &path);
         GetItem::declare_method(&mut res, &path);
+        Show::declare_method(&mut res, &path);
         res
     };
 }
@@ -86,3 +88,56 @@ fn __getitem__(context: CommandContext) -> CrushResult<()> {
             .into_struct(o.types()),
     ))
 }
+
+#[signature(
+show,
+can_block = true,
+output = Known(ValueType::Empty),
+short = "Print a table using the specified rendering options.",
+long = "Prints the table this method is called on, or else the input. Options that are not given\n    are taken from crush:table.",
+example = "ps | table:show max_column_width=30 border=\"unicode\" row_limit=10 hidden_columns=\"ppid\"",
+)]
+struct Show {
+    #[description("the maximum width of a column, or 0 for no limit.")]
+    max_column_width: Option<i128>,
+    #[description("what to do with cells that are too wide, either \"ellipsis\" or \"wrap\".")]
+    overflow: Option<String>,
+    #[description("a dict from type names to \"left\", \"right\" or \"center\".")]
+    alignment: Option<Value>,
+    #[description("the border style, one of \"none\", \"ascii\", \"unicode\" and \"markdown\".")]
+    border: Option<String>,
+    #[description("the maximum number of rows to show, or 0 for no limit.")]
+    row_limit: Option<i128>,
+    #[description("columns not to show.")]
+    hidden_columns: Vec<String>,
+}
+
+fn show(mut context: CommandContext) -> CrushResult<()> {
+    let cfg: Show = Show::parse(context.arguments, &context.global_state.printer())?;
+    let mut format = context.global_state.table_format();
+    if let Some(width) = cfg.max_column_width {
+        format.set("max_column_width", &Value::Integer(width))?;
+    }
+    if let Some(overflow) = cfg.overflow {
+        format.set("overflow", &Value::String(overflow))?;
+    }
+    if let Some(alignment) = cfg.alignment {
+        format.set("alignment", &alignment)?;
+    }
+    if let Some(border) = cfg.border {
+        format.set("border", &Value::String(border))?;
+    }
+    if let Some(limit) = cfg.row_limit {
+        format.set("row_limit", &Value::Integer(limit))?;
+    }
+    if !cfg.hidden_columns.is_empty() {
+        format.hidden_columns = cfg.hidden_columns;
+    }
+
+    let value = match context.this.take() {
+        Some(table @ Value::Table(_)) => table,
+        _ => context.input.recv()?,
+    };
+    pretty::print_formatted(value, format, &context.global_state, |pp, value| pp.print_value(value));
+    context.output.send(Value::Empty())
+}
@@ -1,11 +1,13 @@
 use crate::lang::command::Command;
 use crate::lang::command::OutputType::Known;
 use crate::lang::command::TypeMap;
-use crate::lang::errors::{argument_error_legacy, to_crush_error, CrushResult};
+use crate::lang::errors::{argument_error_legacy, mandate, to_crush_error, CrushResult};
 use crate::lang::execution_context::{ArgumentVector, This};
 use crate::lang::value::ValueType;
 use crate::lang::{execution_context::CommandContext, value::Value};
-use chrono::{ Local, DateTime};
+use crate::lang::data::table::{ColumnType, Row};
+use crate::util::time::{self, Unit, Zone};
+use chrono::{Datelike, DateTime, Duration, NaiveDateTime};
 use lazy_static::lazy_static;
 use ordered_map::OrderedMap;
 use signature::signature;
@@ -17,7 +19,7 @@ fn full(name: &'static str) -> Vec<&'static str> {
 lazy_static! {
     pub static ref METHODS: OrderedMap<String, Command> = {
         let mut res: OrderedMap<String, Command> = OrderedMap::new();
-        let path = vec!["global", "types", "string"];
+        let path = vec!["global", "types", "time"];
         res.declare(
             full("__add__"),
             add,
@@ -41,6 +43,17 @@ lazy_static! {
         Now::declare_method(&mut res, &path);
         Parse::declare_method(&mut res, &path);
         Format::declare_method(&mut res, &path);
+        InZone::declare_method(&mut res, &path);
+        Offset::declare_method(&mut res, &path);
+        AddMonths::declare_method(&mut res, &path);
+        AddYears::declare_method(&mut res, &path);
+        Truncate::declare_method(&mut res, &path);
+        Round::declare_method(&mut res, &path);
+        Bucket::declare_method(&mut res, &path);
+        Weekday::declare_method(&mut res, &path);
+        IsoWeek::declare_method(&mut res, &path);
+        IsoYear::declare_method(&mut res, &path);
+        Range::declare_method(&mut res, &path);
         res
     };
 }
@@ -62,11 +75,20 @@ now,
 can_block = false,
 output = Known(ValueType::Time),
 short = "The current point in time.",
+example = "time:now zone=\"UTC\"",
 )]
-struct Now {}
+struct Now {
+    #[description("the time zone to show the time in. Defaults to the local time zone.")]
+    zone: Option<String>,
+}
 
 fn now(context: CommandContext) -> CrushResult<()> {
-    context.output.send(Value::Time(Local::now()))
+    let cfg: Now = Now::parse(context.arguments, &context.global_state.printer())?;
+    let now = time::now();
+    context.output.send(Value::Time(match cfg.zone {
+        Some(zone) => Zone::parse(&zone)?.convert(&now),
+        None => now,
+    }))
 }
 
 #[signature(
@@ -74,7 +96,9 @@ parse,
 can_block=false,
 output=Known(ValueType::Time),
 short="Parse a time string using a strptime-style pattern string",
-long="After parsing the date, it will be converted to the local time zone.",
+long="If the pattern contains a UTC offset, the time keeps that offset. Otherwise, the time is",
+long="interpreted as a time in the specified zone, or in the local time zone if no zone is given.",
+long="A zone can be \"local\", \"UTC\", an offset like \"+02:00\" or a name like \"Europe/Stockholm\".",
 long="Date specifiers:",
 long=" * %Y, year with century.",
 long=" * %y, year without century, zero padded.",
@@ -133,13 +157,23 @@ struct Parse {
     format: String,
     #[description("the time string to parse.")]
     time: String,
+    #[description("the time zone of times without a UTC offset.")]
+    zone: Option<String>,
 }
 
 fn parse(context: CommandContext) -> CrushResult<()> {
     let cfg: Parse = Parse::parse(context.arguments, &context.global_state.printer())?;
-    let tm = to_crush_error(DateTime::parse_from_str(&cfg.time, &cfg.format))?;
-    let dt = tm.with_timezone(&Local);
-    context.output.send(Value::Time(dt))
+    let tm = match DateTime::parse_from_str(&cfg.time, &cfg.format) {
+        Ok(tm) => tm,
+        Err(_) => {
+            let naive = to_crush_error(NaiveDateTime::parse_from_str(&cfg.time, &cfg.format))?;
+            match &cfg.zone {
+                Some(zone) => Zone::parse(zone)?,
+                None => Zone::Local,
+            }.localize(&naive)?
+        }
+    };
+    context.output.send(Value::Time(tm))
 }
 
 #[signature(
@@ -171,15 +205,259 @@ long=" * %W, week number of the year (Monday as first day of week).",
 long=" * %c, default data and time representation of current locale.",
 long=" * %x, default date representation of current locale.",
 long=" * %X, default time representation of current locale.",
-long=" * %%, a literal % character."
+long=" * %%, a literal % character.",
+long="",
+long="The time is formatted using its own UTC offset, unless a zone is specified.",
 )]
 struct Format {
     #[description("the format of the time.")]
     format: String,
+    #[description("the time zone to format the time in.")]
+    zone: Option<String>,
 }
 
 fn format(context: CommandContext) -> CrushResult<()> {
     let time = context.this.time()?;
     let cfg: Format = Format::parse(context.arguments, &context.global_state.printer())?;
+    let time = match cfg.zone {
+        Some(zone) => Zone::parse(&zone)?.convert(&time),
+        None => time,
+    };
     context.output.send(Value::String(time.format(&cfg.format).to_string()))
 }
+
+#[signature(
+in_zone,
+can_block = false,
+output = Known(ValueType::Time),
+short = "The same point in time, in the specified time zone",
+long = "The zone can be \"local\", \"UTC\", an offset like \"+02:00\" or a name from the IANA time",
+long = "zone database like \"Europe/Stockholm\". Named zones get the offset they have at this time,",
+long = "taking daylight saving time into account.",
+example = "(time:now):in_zone \"America/New_York\"",
+)]
+struct InZone {
+    #[description("the time zone.")]
+    zone: String,
+}
+
+fn in_zone(context: CommandContext) -> CrushResult<()> {
+    let time = context.this.time()?;
+    let cfg: InZone = InZone::parse(context.arguments, &context.global_state.printer())?;
+    context.output.send(Value::Time(Zone::parse(&cfg.zone)?.convert(&time)))
+}
+
+#[signature(
+offset,
+can_block = false,
+output = Known(ValueType::Duration),
+short = "The UTC offset of this time",
+example = "(time:now zone=\"+05:30\"):offset",
+)]
+struct Offset {}
+
+fn offset(context: CommandContext) -> CrushResult<()> {
+    let time = context.this.time()?;
+    context.output.send(Value::Duration(Duration::seconds(time.offset().local_minus_utc() as i64)))
+}
+
+#[signature(
+add_months,
+can_block = false,
+output = Known(ValueType::Time),
+short = "Add the specified number of calendar months to this time",
+long = "If the day of month does not exist in the resulting month, the last day of that month is used,",
+long = "so e.g. January 31 plus one month is the last day of February.",
+example = "(time:now):add_months (neg 3)",
+)]
+struct AddMonths {
+    #[description("the number of months to add. May be negative.")]
+    months: i64,
+}
+
+fn add_months(context: CommandContext) -> CrushResult<()> {
+    let time = context.this.time()?;
+    let cfg: AddMonths = AddMonths::parse(context.arguments, &context.global_state.printer())?;
+    context.output.send(Value::Time(time::add_months(&time, cfg.months)?))
+}
+
+#[signature(
+add_years,
+can_block = false,
+output = Known(ValueType::Time),
+short = "Add the specified number of calendar years to this time",
+long = "February 29 plus a number of years that isn't a leap year is February 28.",
+)]
+struct AddYears {
+    #[description("the number of years to add. May be negative.")]
+    years: i64,
+}
+
+fn add_years(context: CommandContext) -> CrushResult<()> {
+    let time = context.this.time()?;
+    let cfg: AddYears = AddYears::parse(context.arguments, &context.global_state.printer())?;
+    let months = mandate(cfg.years.checked_mul(12), "Time out of range")?;
+    context.output.send(Value::Time(time::add_months(&time, months)?))
+}
+
+#[signature(
+truncate,
+can_block = false,
+output = Known(ValueType::Time),
+short = "The start of the hour, day, week, month etc. that this time is in",
+long = "Weeks start on Monday. The time keeps its UTC offset, so e.g. a day starts at midnight in that",
+long = "offset. Use in_zone first to truncate to days in another time zone.",
+example = "(time:now):truncate \"day\"",
+)]
+struct Truncate {
+    #[description("the unit to truncate to.")]
+    #[values("second", "minute", "hour", "day", "week", "month", "year")]
+    unit: String,
+}
+
+fn truncate(context: CommandContext) -> CrushResult<()> {
+    let time = context.this.time()?;
+    let cfg: Truncate = Truncate::parse(context.arguments, &context.global_state.printer())?;
+    context.output.send(Value::Time(time::truncate(&time, Unit::parse(&cfg.unit)?)))
+}
+
+#[signature(
+round,
+can_block = false,
+output = Known(ValueType::Time),
+short = "The start of the hour, day, week, month etc. closest to this time",
+long = "Times exactly halfway between two units are rounded up.",
+example = "(time:now):round \"hour\"",
+)]
+struct Round {
+    #[description("the unit to round to.")]
+    #[values("second", "minute", "hour", "day", "week", "month", "year")]
+    unit: String,
+}
+
+fn round(context: CommandContext) -> CrushResult<()> {
+    let time = context.this.time()?;
+    let cfg: Round = Round::parse(context.arguments, &context.global_state.printer())?;
+    context.output.send(Value::Time(time::round(&time, Unit::parse(&cfg.unit)?)?))
+}
+
+#[signature(
+bucket,
+can_block = false,
+output = Known(ValueType::Time),
+short = "The start of the time bucket of the specified size that this time falls into",
+long = "Buckets are counted from midnight, January 1 1970, in the UTC offset of this time. This makes it",
+long = "easy to group times into e.g. five minute intervals for reports.",
+example = "find . | select ^file bucket={modified:bucket (duration:of days=7)} | group ^bucket c={count}",
+)]
+struct Bucket {
+    #[description("the size of the buckets.")]
+    size: Duration,
+}
+
+fn bucket(context: CommandContext) -> CrushResult<()> {
+    let time = context.this.time()?;
+    let cfg: Bucket = Bucket::parse(context.arguments, &context.global_state.printer())?;
+    context.output.send(Value::Time(time::bucket(&time, &cfg.size)?))
+}
+
+#[signature(
+weekday,
+can_block = false,
+output = Known(ValueType::String),
+short = "The name of the day of the week of this time, e.g. Monday",
+)]
+struct Weekday {}
+
+fn weekday(context: CommandContext) -> CrushResult<()> {
+    let time = context.this.time()?;
+    context.output.send(Value::string(&time.format("%A").to_string()))
+}
+
+#[signature(
+iso_week,
+can_block = false,
+output = Known(ValueType::Integer),
+short = "The ISO 8601 week number of this time",
+long = "ISO weeks start on Monday, and week 1 is the week containing the first Thursday of the year.",
+long = "Use iso_year for the year the week belongs to, which differs from the calendar year for some",
+long = "days around new year.",
+)]
+struct IsoWeek {}
+
+fn iso_week(context: CommandContext) -> CrushResult<()> {
+    let time = context.this.time()?;
+    context.output.send(Value::Integer(time.iso_week().week() as i128))
+}
+
+#[signature(
+iso_year,
+can_block = false,
+output = Known(ValueType::Integer),
+short = "The year of the ISO 8601 week of this time",
+)]
+struct IsoYear {}
+
+fn iso_year(context: CommandContext) -> CrushResult<()> {
+    let time = context.this.time()?;
+    context.output.send(Value::Integer(time.iso_week().year() as i128))
+}
+
+#[signature(
+range,
+can_block = true,
+output = Known(ValueType::TableInputStream(vec![ColumnType::new("value", ValueType::Time)])),
+short = "Return a stream of times from one time up to, but not including, another",
+long = "The times are step apart, one day by default. If months is specified, the times are that many\n    calendar months apart instead, using the same end of month rules as add_months.",
+example = "time:range from=((time:now):truncate \"month\") to=(time:now) | select ^value day={value:weekday}",
+)]
+struct Range {
+    #[description("the first time.")]
+    from: Value,
+    #[description("the end of the range.")]
+    to: Value,
+    #[description("the distance between two times.")]
+    step: Option<Duration>,
+    #[description("the number of calendar months between two times.")]
+    months: Option<i64>,
+}
+
+fn range(context: CommandContext) -> CrushResult<()> {
+    let cfg: Range = Range::parse(context.arguments, &context.global_state.printer())?;
+    let (from, to) = match (cfg.from, cfg.to) {
+        (Value::Time(from), Value::Time(to)) => (from, to),
+        _ => return argument_error_legacy("Expected from and to to be times"),
+    };
+    let output = context
+        .output
+        .initialize(vec![ColumnType::new("value", ValueType::Time)])?;
+    match (cfg.step, cfg.months) {
+        (Some(_), Some(_)) => argument_error_legacy("Specify at most one of step and months"),
+        (_, Some(months)) => {
+            if months <= 0 {
+                return argument_error_legacy("months must be positive");
+            }
+            let mut count = 0;
+            loop {
+                let tm = time::add_months(&from, count * months)?;
+                if tm >= to {
+                    break Ok(());
+                }
+                output.send(Row::new(vec![Value::Time(tm)]))?;
+                count += 1;
+            }
+        }
+        (step, None) => {
+            let step = step.unwrap_or_else(|| Duration::days(1));
+            if step <= Duration::zero() {
+                return argument_error_legacy("step must be a positive duration");
+            }
+            let mut tm = from;
+            while tm < to {
+                output.send(Row::new(vec![Value::Time(tm)]))?;
+                tm = tm + step;
+            }
+            Ok(())
+        }
+    }
+}
@@ -15,6 +15,9 @@ use lang::data;
 use crate::lang::interactive;
 use crate::lang::global_state::GlobalState;
 use crate::lang::printer::Printer;
+use crate::lang::debugger::{self, DebugMode};
+use crate::lang::output_format::OutputFormat;
+use termion::is_tty;
 
 #[derive(PartialEq, Eq)]
 enum Mode {
@@ -26,13 +29,20 @@ enum Mode {
 
 struct Config {
     mode: Mode,
+    trace: bool,
+    debug: bool,
+    format: Option<OutputFormat>,
 }
 
 fn parse_args() -> CrushResult<Config> {
     let args = std::env::args().collect::<Vec<_>>();
     let mut mode = Mode::Interactive;
     let mut all_files = false;
-    for arg in &args[1..] {
+    let mut trace = false;
+    let mut debug = false;
+    let mut format = None;
+    let mut args = args[1..].iter();
+    while let Some(arg) = args.next() {
         if all_files {
             mode = Mode::File(PathBuf::from(arg))
         } else {
@@ -40,6 +50,12 @@ fn parse_args() -> CrushResult<Config> {
                 "--pup" | "-p" => mode = Mode::Pup,
                 "--interactive" | "-i" => mode = Mode::Interactive,
                 "--help" | "-h" => mode = Mode::Help,
+                "--trace" | "-t" => trace = true,
+                "--debug" | "-d" => debug = true,
+                "--format" | "-f" => match args.next() {
+                    Some(name) => format = Some(OutputFormat::parse(name)?),
+                    None => return argument_error_legacy("Missing output format"),
+                },
                 "--" => all_files = true,
                 file => {
                     if file.starts_with("-") {
@@ -50,7 +66,7 @@ fn parse_args() -> CrushResult<Config> {
             }
         }
     }
-    Ok(Config { mode })
+    Ok(Config { mode, trace, debug, format })
 }
 
 fn print_help(printer: &Printer) {
@@ -59,6 +75,11 @@ fn print_help(printer: &Printer) {
     printer.line("");
     printer.line("  -h, --help        Print this message and exit");
     printer.line("  -i --interactive  Run in interactive mode (this is the default)");
+    printer.line("  -t --trace        Print every command to standard error before running it");
+    printer.line("  -d --debug        Start the debugger before running the first command");
+    printer.line("  -f --format FMT   Write output in the format FMT, one of pretty, json, tsv");
+    printer.line("                      and pup. The default is pretty if standard output is a");
+    printer.line("                      terminal and json otherwise");
     printer.line("  -p --pup          Read pup-serialized closure from standard input,");
     printer.line("                      execute, and serialize output to pup-format");
     printer.line("                      and send to standard output");
@@ -88,6 +109,12 @@ fn run() -> CrushResult<i32> {
     };
 
     let global_state = GlobalState::new(printer)?;
+    global_state.set_trace(config.trace);
+    global_state.set_output_format(config.format.unwrap_or(
+        if is_tty(&std::io::stdout()) { OutputFormat::Pretty } else { OutputFormat::Json }));
+    if config.debug {
+        global_state.set_debug_mode(DebugMode::Start);
+    }
     let pretty_printer = create_pretty_printer(global_state.printer().clone(), &global_state);
 
     declare(&root_scope, &global_state, &pretty_printer)?;
@@ -110,6 +137,7 @@ fn run() -> CrushResult<i32> {
         }
 
         Mode::File(f) => {
+            debugger::user_code_starting(&global_state);
             execute::file(
                 &local_scope,
                 f.as_path(),
@@ -0,0 +1,313 @@
+/**
+Read the state of a git repository directly from the files in the .git directory, without
+spawning git. This is cheap enough to do every time the prompt is shown.
+*/
+
+use std::convert::TryInto;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+const FLAG_EXTENDED: u16 = 0x4000;
+const FLAG_ASSUME_VALID: u16 = 0x8000;
+const EXTENDED_FLAG_SKIP_WORKTREE: u16 = 0x4000;
+const MODE_GITLINK: u32 = 0o160000;
+
+#[derive(Clone)]
+pub struct GitState {
+    /** The current branch, or the abbreviated commit hash if HEAD is detached. */
+    pub branch: String,
+    /** True if any tracked file in the work tree differs from the index. */
+    pub dirty: bool,
+}
+
+/** An entry in the git index, with the file metadata git uses to detect changes. */
+#[derive(Debug, PartialEq)]
+struct IndexEntry {
+    path: String,
+    mtime: u32,
+    mtime_nanos: u32,
+    size: u32,
+    mode: u32,
+    skip: bool,
+}
+
+/**
+Find the work tree and git directory of the repository containing the specified directory. The
+.git entry of linked work trees and submodules is a file pointing at the real git directory.
+*/
+fn find_repository(dir: &Path) -> Option<(PathBuf, PathBuf)> {
+    for candidate in dir.ancestors() {
+        let git = candidate.join(".git");
+        if git.is_dir() {
+            return Some((candidate.to_path_buf(), git));
+        }
+        if git.is_file() {
+            let content = fs::read_to_string(&git).ok()?;
+            let target = content.trim().strip_prefix("gitdir:")?.trim();
+            return Some((candidate.to_path_buf(), candidate.join(target)));
+        }
+    }
+    None
+}
+
+/** The branch name from the content of a HEAD file. */
+fn parse_head(head: &str) -> Option<String> {
+    let head = head.trim();
+    match head.strip_prefix("ref:") {
+        Some(reference) => {
+            let reference = reference.trim();
+            Some(reference.strip_prefix("refs/heads/").unwrap_or(reference).to_string())
+        }
+        None if head.len() >= 7 => Some(head[..7].to_string()),
+        None => None,
+    }
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+/** Read the variable length integer used for path prefix compression in index version 4. */
+fn varint(data: &[u8], offset: &mut usize) -> Option<usize> {
+    let mut byte = *data.get(*offset)?;
+    *offset += 1;
+    let mut value = (byte & 0x7f) as usize;
+    while byte & 0x80 != 0 {
+        byte = *data.get(*offset)?;
+        *offset += 1;
+        value = ((value + 1) << 7) | (byte & 0x7f) as usize;
+    }
+    Some(value)
+}
+
+/** Parse the entries of a git index file. Versions 2, 3 and 4 are supported. */
+fn parse_index(data: &[u8]) -> Option<Vec<IndexEntry>> {
+    if data.get(0..4)? != b"DIRC" {
+        return None;
+    }
+    let version = u32_at(data, 4)?;
+    if version < 2 || version > 4 {
+        return None;
+    }
+    let count = u32_at(data, 8)? as usize;
+    let mut offset = 12;
+    let mut previous_path: Vec<u8> = Vec::new();
+    let mut res = Vec::with_capacity(count);
+    for _ in 0..count {
+        let start = offset;
+        let mtime = u32_at(data, start + 8)?;
+        let mtime_nanos = u32_at(data, start + 12)?;
+        let mode = u32_at(data, start + 24)?;
+        let size = u32_at(data, start + 36)?;
+        let flags = u16_at(data, start + 60)?;
+        offset = start + 62;
+        let mut skip = flags & FLAG_ASSUME_VALID != 0;
+        if version >= 3 && flags & FLAG_EXTENDED != 0 {
+            skip |= u16_at(data, offset)? & EXTENDED_FLAG_SKIP_WORKTREE != 0;
+            offset += 2;
+        }
+        let path = if version == 4 {
+            let strip = varint(data, &mut offset)?;
+            let end = offset + data.get(offset..)?.iter().position(|b| *b == 0)?;
+            let mut path = previous_path.get(..previous_path.len().checked_sub(strip)?)?.to_vec();
+            path.extend_from_slice(&data[offset..end]);
+            offset = end + 1;
+            path
+        } else {
+            let end = offset + data.get(offset..)?.iter().position(|b| *b == 0)?;
+            let path = data[offset..end].to_vec();
+            // Entries are padded with one to eight NUL bytes to a multiple of eight bytes.
+            offset = start + ((end - start) / 8 + 1) * 8;
+            path
+        };
+        res.push(IndexEntry {
+            path: String::from_utf8_lossy(&path).to_string(),
+            mtime,
+            mtime_nanos,
+            size,
+            mode,
+            skip,
+        });
+        previous_path = path;
+    }
+    Some(res)
+}
+
+/**
+Check if the file of an index entry has been modified, using the same metadata comparison git
+uses before it falls back to comparing content. Only the work tree is compared to the index, so
+staged changes and untracked files are not detected.
+*/
+fn is_modified(work_tree: &Path, entry: &IndexEntry) -> bool {
+    if entry.skip || entry.mode == MODE_GITLINK {
+        return false;
+    }
+    match fs::symlink_metadata(work_tree.join(&entry.path)) {
+        Ok(metadata) =>
+            metadata.size() as u32 != entry.size
+                || metadata.mtime() as u32 != entry.mtime
+                || (entry.mtime_nanos != 0 && metadata.mtime_nsec() as u32 != entry.mtime_nanos),
+        Err(_) => true,
+    }
+}
+
+/**
+The state of the git repository containing the specified directory, or None if the directory is
+not inside of a repository.
+*/
+pub fn state(dir: &Path) -> Option<GitState> {
+    let (work_tree, git_dir) = find_repository(dir)?;
+    let branch = parse_head(&fs::read_to_string(git_dir.join("HEAD")).ok()?)?;
+    let dirty = fs::read(git_dir.join("index"))
+        .ok()
+        .and_then(|data| parse_index(&data))
+        .map(|entries| entries.iter().any(|e| is_modified(&work_tree, e)))
+        .unwrap_or(false);
+    Some(GitState { branch, dirty })
+}
+
+/**
+Remembers the state of repositories while a prompt is being rendered. Checking if the work tree
+has been modified stats every file in the index, so the left and right prompt, which often both
+show the git state, share a single check. Outside of prompt rendering nothing is cached, so the
+state is never stale.
+*/
+#[derive(Clone, Default)]
+pub struct StateCache {
+    data: Arc<Mutex<Option<Vec<(PathBuf, Option<GitState>)>>>>,
+}
+
+impl StateCache {
+    /** Start caching, to be called before rendering a prompt. */
+    pub fn start(&self) {
+        *self.data.lock().unwrap() = Some(Vec::new());
+    }
+
+    /** Stop caching and forget everything cached, to be called once a prompt has been rendered. */
+    pub fn stop(&self) {
+        *self.data.lock().unwrap() = None;
+    }
+
+    /** The state of the repository containing the specified directory, see `state`. */
+    pub fn state(&self, dir: &Path) -> Option<GitState> {
+        let mut data = self.data.lock().unwrap();
+        match data.as_mut() {
+            None => state(dir),
+            Some(cached) => match cached.iter().find(|(d, _)| d == dir) {
+                Some((_, res)) => res.clone(),
+                None => {
+                    let res = state(dir);
+                    cached.push((dir.to_path_buf(), res.clone()));
+                    res
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+    An index entry. With strip set, the path is prefix compressed as in index version 4, removing
+    that many bytes from the end of the previous path before appending this one.
+    */
+    fn entry(path: &str, extended: Option<u16>, strip: Option<u8>) -> Vec<u8> {
+        let mut res = vec![0u8; 8];
+        res.extend_from_slice(&17u32.to_be_bytes());
+        res.extend_from_slice(&42u32.to_be_bytes());
+        res.extend_from_slice(&[0u8; 8]);
+        res.extend_from_slice(&0o100644u32.to_be_bytes());
+        res.extend_from_slice(&[0u8; 8]);
+        res.extend_from_slice(&1234u32.to_be_bytes());
+        res.extend_from_slice(&[0u8; 20]);
+        let flags = path.len() as u16 | extended.map(|_| FLAG_EXTENDED).unwrap_or(0);
+        res.extend_from_slice(&flags.to_be_bytes());
+        if let Some(extended) = extended {
+            res.extend_from_slice(&extended.to_be_bytes());
+        }
+        if let Some(strip) = strip {
+            res.push(strip);
+            res.extend_from_slice(path.as_bytes());
+            res.push(0);
+        } else {
+            res.extend_from_slice(path.as_bytes());
+            let padding = 8 - res.len() % 8;
+            res.extend(std::iter::repeat(0).take(padding));
+        }
+        res
+    }
+
+    fn index(version: u32, entries: Vec<Vec<u8>>) -> Vec<u8> {
+        let mut res = b"DIRC".to_vec();
+        res.extend_from_slice(&version.to_be_bytes());
+        res.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+        for e in entries {
+            res.extend(e);
+        }
+        res
+    }
+
+    #[test]
+    fn check_parse_head() {
+        assert_eq!(parse_head("ref: refs/heads/master\n"), Some("master".to_string()));
+        assert_eq!(parse_head("ref: refs/heads/feature/x\n"), Some("feature/x".to_string()));
+        assert_eq!(parse_head("0123456789abcdef\n"), Some("0123456".to_string()));
+        assert_eq!(parse_head(""), None);
+    }
+
+    #[test]
+    fn check_parse_index() {
+        let data = index(2, vec![entry("Cargo.toml", None, None), entry("src/main.rs", None, None)]);
+        let entries = parse_index(&data).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].path, "src/main.rs");
+        assert_eq!(entries[1].mtime, 17);
+        assert_eq!(entries[1].mtime_nanos, 42);
+        assert_eq!(entries[1].size, 1234);
+
+        let data = index(3, vec![
+            entry("a", Some(EXTENDED_FLAG_SKIP_WORKTREE), None),
+            entry("b", None, None)]);
+        let entries = parse_index(&data).unwrap();
+        assert!(entries[0].skip);
+        assert!(!entries[1].skip);
+        assert_eq!(entries[1].path, "b");
+
+        let data = index(4, vec![entry("src/a.rs", None, Some(0)), entry("b.rs", None, Some(4))]);
+        assert_eq!(parse_index(&data).unwrap()[1].path, "src/b.rs");
+
+        assert!(parse_index(b"NOPE").is_none());
+    }
+
+    #[test]
+    fn check_varint() {
+        let mut offset = 0;
+        assert_eq!(varint(&[0x05], &mut offset), Some(5));
+        let mut offset = 0;
+        assert_eq!(varint(&[0x80, 0x00], &mut offset), Some(128));
+        assert_eq!(offset, 2);
+    }
+
+    #[test]
+    fn check_state_cache() {
+        let dir = std::env::temp_dir().join(format!("crush_git_cache_{}", std::process::id()));
+        fs::create_dir_all(dir.join(".git")).unwrap();
+        fs::write(dir.join(".git/HEAD"), "ref: refs/heads/main\n").unwrap();
+        let cache = StateCache::default();
+        cache.start();
+        assert_eq!(cache.state(&dir).unwrap().branch, "main");
+        fs::write(dir.join(".git/HEAD"), "ref: refs/heads/other\n").unwrap();
+        assert_eq!(cache.state(&dir).unwrap().branch, "main");
+        cache.stop();
+        assert_eq!(cache.state(&dir).unwrap().branch, "other");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
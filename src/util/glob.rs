@@ -80,6 +80,15 @@ impl Glob {
     pub fn glob_files(&self, cwd: &Path, out: &mut Vec<PathBuf>) -> CrushResult<()> {
         glob_files(&self.pattern, cwd, out, &directory_lister())
     }
+
+    pub fn glob_files_with_lister(
+        &self,
+        cwd: &Path,
+        out: &mut Vec<PathBuf>,
+        lister: &impl DirectoryLister,
+    ) -> CrushResult<()> {
+        glob_files(&self.pattern, cwd, out, lister)
+    }
 }
 
 fn glob_files(pattern: &[Tile], cwd: &Path, out: &mut Vec<PathBuf>, lister: &impl DirectoryLister) -> CrushResult<()> {
@@ -0,0 +1,145 @@
+use crate::lang::errors::{argument_error_legacy, CrushResult};
+use std::fmt::{Display, Formatter};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+/** True if the address belongs to one of the address ranges reserved for private networks. */
+pub fn is_private(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => ip.is_private(),
+        // Unique local addresses, fc00::/7
+        IpAddr::V6(ip) => ip.segments()[0] & 0xfe00 == 0xfc00,
+    }
+}
+
+pub fn parse_ip(s: &str) -> CrushResult<IpAddr> {
+    match IpAddr::from_str(s.trim()) {
+        Ok(ip) => Ok(ip),
+        Err(_) => argument_error_legacy(format!("Invalid ip address {}", s)),
+    }
+}
+
+/**
+A network in CIDR notation, e.g. 10.0.0.0/8. The address is always the first address of the
+network, i.e. any host bits given when parsing are cleared.
+*/
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Cidr {
+    address: IpAddr,
+    prefix: u8,
+}
+
+fn max_prefix(address: &IpAddr) -> u8 {
+    match address {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+fn mask(address: &IpAddr, prefix: u8) -> IpAddr {
+    match address {
+        IpAddr::V4(a) => {
+            let mask = if prefix == 0 { 0 } else { u32::MAX << (32 - prefix) };
+            IpAddr::V4(Ipv4Addr::from(u32::from(*a) & mask))
+        }
+        IpAddr::V6(a) => {
+            let mask = if prefix == 0 { 0 } else { u128::MAX << (128 - prefix) };
+            IpAddr::V6(Ipv6Addr::from(u128::from(*a) & mask))
+        }
+    }
+}
+
+impl Cidr {
+    pub fn new(address: IpAddr, prefix: u8) -> CrushResult<Cidr> {
+        if prefix > max_prefix(&address) {
+            return argument_error_legacy(format!("Invalid prefix length {} for {}", prefix, address));
+        }
+        Ok(Cidr { address: mask(&address, prefix), prefix })
+    }
+
+    /**
+    Parse a network in CIDR notation. An address without a prefix length is a network containing
+    only that address.
+    */
+    pub fn parse(s: &str) -> CrushResult<Cidr> {
+        let s = s.trim();
+        match s.split_once('/') {
+            None => {
+                let address = parse_ip(s)?;
+                Cidr::new(address, max_prefix(&address))
+            }
+            Some((address, prefix)) => match prefix.parse::<u8>() {
+                Ok(prefix) => Cidr::new(parse_ip(address)?, prefix),
+                Err(_) => argument_error_legacy(format!("Invalid network {}", s)),
+            },
+        }
+    }
+
+    pub fn address(&self) -> IpAddr {
+        self.address
+    }
+
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+
+    /** The last address of this network. */
+    pub fn last(&self) -> IpAddr {
+        match self.address {
+            IpAddr::V4(a) => IpAddr::V4(Ipv4Addr::from(u32::from(a) | u32::MAX.checked_shr(self.prefix as u32).unwrap_or(0))),
+            IpAddr::V6(a) => IpAddr::V6(Ipv6Addr::from(u128::from(a) | u128::MAX.checked_shr(self.prefix as u32).unwrap_or(0))),
+        }
+    }
+
+    /** True if every address of this network belongs to a private network. */
+    pub fn is_private(&self) -> bool {
+        is_private(&self.address) && is_private(&self.last())
+    }
+
+    /** True if the address is part of this network. Addresses of the other IP version never are. */
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        ip.is_ipv4() == self.address.is_ipv4() && mask(ip, self.prefix) == self.address
+    }
+}
+
+impl Display for Cidr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_parse() {
+        assert_eq!(Cidr::parse("10.1.2.3/8").unwrap().to_string(), "10.0.0.0/8");
+        assert_eq!(Cidr::parse("192.168.1.1").unwrap().to_string(), "192.168.1.1/32");
+        assert_eq!(Cidr::parse("fe80::1/10").unwrap().to_string(), "fe80::/10");
+        assert_eq!(Cidr::parse("0.0.0.0/0").unwrap().to_string(), "0.0.0.0/0");
+        assert!(Cidr::parse("10.0.0.0/33").is_err());
+        assert!(Cidr::parse("10.0.0/8").is_err());
+        assert!(Cidr::parse("10.0.0.0/x").is_err());
+    }
+
+    #[test]
+    fn check_contains() {
+        let net = Cidr::parse("192.168.0.0/16").unwrap();
+        assert!(net.contains(&parse_ip("192.168.10.1").unwrap()));
+        assert!(!net.contains(&parse_ip("192.169.0.1").unwrap()));
+        assert!(!net.contains(&parse_ip("::1").unwrap()));
+        assert!(Cidr::parse("::/0").unwrap().contains(&parse_ip("2001:db8::1").unwrap()));
+    }
+
+    #[test]
+    fn check_is_private() {
+        assert!(is_private(&parse_ip("10.0.0.1").unwrap()));
+        assert!(is_private(&parse_ip("fd00::1").unwrap()));
+        assert!(!is_private(&parse_ip("8.8.8.8").unwrap()));
+        assert!(!is_private(&parse_ip("2001:db8::1").unwrap()));
+        assert!(Cidr::parse("10.1.0.0/16").unwrap().is_private());
+        assert!(!Cidr::parse("10.0.0.0/7").unwrap().is_private());
+        assert_eq!(Cidr::parse("10.0.0.0/8").unwrap().last().to_string(), "10.255.255.255");
+    }
+}
@@ -1,9 +1,11 @@
 pub mod directory_lister;
 pub mod escape;
 pub mod file;
+pub mod git;
 pub mod glob;
 pub mod hex;
 pub mod identity_arc;
+pub mod ip;
 pub mod regex;
 pub mod replace;
 pub mod time;
@@ -1,4 +1,10 @@
-use chrono::Duration;
+use crate::lang::errors::{argument_error_legacy, mandate, CrushResult};
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, Offset, TimeZone,
+    Timelike, Utc,
+};
+use chrono_tz::Tz;
+use std::convert::TryFrom;
 
 pub fn duration_format(d: &Duration) -> String {
     const MICROS_IN_SECOND: i128 = 1_000_000_000;
@@ -59,3 +65,457 @@ pub fn duration_format(d: &Duration) -> String {
     }
     res
 }
+
+/** The same point in time, with the UTC offset of the local time zone at that time. */
+pub fn local(time: DateTime<Local>) -> DateTime<FixedOffset> {
+    time.with_timezone(time.offset())
+}
+
+/** The current time, in the local time zone. */
+pub fn now() -> DateTime<FixedOffset> {
+    local(Local::now())
+}
+
+/**
+A time zone, used to decide which UTC offset a time is shown with. Named zones come from the
+IANA time zone database, so the offset depends on the time, e.g. because of daylight saving time.
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Zone {
+    Local,
+    Fixed(FixedOffset),
+    Named(Tz),
+}
+
+fn parse_offset(s: &str) -> Option<FixedOffset> {
+    let (sign, rest) = match s.chars().next()? {
+        '+' => (1, &s[1..]),
+        '-' => (-1, &s[1..]),
+        _ => return None,
+    };
+    let digits = rest.replace(':', "");
+    if digits.len() != 2 && digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let hours = digits[0..2].parse::<i32>().ok()?;
+    let minutes = if digits.len() == 4 { digits[2..4].parse::<i32>().ok()? } else { 0 };
+    if minutes >= 60 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+impl Zone {
+    /**
+    Parse a time zone. Accepts "local", "UTC", offsets like "+02:00" or "-0530", and IANA
+    time zone names like "Europe/Stockholm".
+    */
+    pub fn parse(s: &str) -> CrushResult<Zone> {
+        match s {
+            "local" => Ok(Zone::Local),
+            "UTC" | "utc" | "Z" => Ok(Zone::Fixed(Utc.fix())),
+            _ => match parse_offset(s) {
+                Some(offset) => Ok(Zone::Fixed(offset)),
+                None => match s.parse::<Tz>() {
+                    Ok(tz) => Ok(Zone::Named(tz)),
+                    Err(_) => argument_error_legacy(format!("Unknown time zone {}", s)),
+                },
+            },
+        }
+    }
+
+    /** The same point in time, shown with the UTC offset this zone has at that time. */
+    pub fn convert(&self, time: &DateTime<FixedOffset>) -> DateTime<FixedOffset> {
+        match self {
+            Zone::Local => local(time.with_timezone(&Local)),
+            Zone::Fixed(offset) => time.with_timezone(offset),
+            Zone::Named(tz) => {
+                let t = time.with_timezone(tz);
+                time.with_timezone(&t.offset().fix())
+            }
+        }
+    }
+
+    /**
+    Interpret a date and time without an offset as a time in this zone. If the time is ambiguous,
+    e.g. because clocks are turned back, the earliest match is used.
+    */
+    pub fn localize(&self, time: &NaiveDateTime) -> CrushResult<DateTime<FixedOffset>> {
+        let res = match self {
+            Zone::Local => Local.from_local_datetime(time).earliest().map(local),
+            Zone::Fixed(offset) => offset.from_local_datetime(time).earliest(),
+            Zone::Named(tz) => tz.from_local_datetime(time).earliest().map(|t| t.with_timezone(&t.offset().fix())),
+        };
+        match res {
+            Some(t) => Ok(t),
+            None => argument_error_legacy(format!("The time {} does not exist in this time zone", time)),
+        }
+    }
+}
+
+/** Parse a time in RFC 3339 format, e.g. 2020-06-01T12:00:00+02:00, keeping its offset. */
+pub fn parse_time(s: &str) -> CrushResult<DateTime<FixedOffset>> {
+    match DateTime::parse_from_rfc3339(s.trim()) {
+        Ok(t) => Ok(t),
+        Err(_) => argument_error_legacy(format!("Invalid time {}", s)),
+    }
+}
+
+/** Replace the date and time of day of a time, keeping its UTC offset. */
+fn with_local(time: &DateTime<FixedOffset>, local: NaiveDateTime) -> DateTime<FixedOffset> {
+    time.offset().from_local_datetime(&local).unwrap()
+}
+
+fn date(year: i32, month: u32, day: u32) -> CrushResult<NaiveDate> {
+    mandate(NaiveDate::from_ymd_opt(year, month, day), "Time out of range")
+}
+
+fn midnight(date: NaiveDate) -> NaiveDateTime {
+    date.and_hms_opt(0, 0, 0).unwrap()
+}
+
+fn days_in_month(year: i32, month: u32) -> CrushResult<u32> {
+    let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+    Ok(mandate(date(next_year, next_month, 1)?.pred_opt(), "Time out of range")?.day())
+}
+
+fn epoch() -> NaiveDateTime {
+    midnight(NaiveDate::from_ymd_opt(1970, 1, 1).unwrap())
+}
+
+/**
+Add a number of calendar months to a time. If the day of month doesn't exist in the resulting
+month, the last day of that month is used, e.g. January 31 plus one month is February 28 or 29.
+*/
+pub fn add_months(time: &DateTime<FixedOffset>, months: i64) -> CrushResult<DateTime<FixedOffset>> {
+    let local = time.naive_local();
+    let total = local.year() as i64 * 12 + local.month0() as i64 + months;
+    let year = match i32::try_from(total.div_euclid(12)) {
+        Ok(year) => year,
+        Err(_) => return argument_error_legacy("Time out of range"),
+    };
+    let month = total.rem_euclid(12) as u32 + 1;
+    let day = local.day().min(days_in_month(year, month)?);
+    Ok(with_local(time, date(year, month, day)?.and_time(local.time())))
+}
+
+/** A calendar unit that times can be truncated or rounded to. */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Unit {
+    Second,
+    Minute,
+    Hour,
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl Unit {
+    pub fn parse(s: &str) -> CrushResult<Unit> {
+        match s {
+            "second" => Ok(Unit::Second),
+            "minute" => Ok(Unit::Minute),
+            "hour" => Ok(Unit::Hour),
+            "day" => Ok(Unit::Day),
+            "week" => Ok(Unit::Week),
+            "month" => Ok(Unit::Month),
+            "year" => Ok(Unit::Year),
+            _ => argument_error_legacy(format!("Unknown time unit {}", s)),
+        }
+    }
+}
+
+/**
+The start of the unit the time is in, in the time's own UTC offset. Weeks start on Monday, as in
+ISO 8601.
+*/
+pub fn truncate(time: &DateTime<FixedOffset>, unit: Unit) -> DateTime<FixedOffset> {
+    let local = time.naive_local();
+    let day = local.date();
+    let res = match unit {
+        Unit::Second => local.with_nanosecond(0).unwrap(),
+        Unit::Minute => day.and_hms_opt(local.hour(), local.minute(), 0).unwrap(),
+        Unit::Hour => day.and_hms_opt(local.hour(), 0, 0).unwrap(),
+        Unit::Day => midnight(day),
+        Unit::Week => midnight(day - Duration::days(day.weekday().num_days_from_monday() as i64)),
+        Unit::Month => midnight(day.with_day(1).unwrap()),
+        Unit::Year => midnight(day.with_ordinal(1).unwrap()),
+    };
+    with_local(time, res)
+}
+
+/** The start of the unit closest to the time. Halfway points round up. */
+pub fn round(time: &DateTime<FixedOffset>, unit: Unit) -> CrushResult<DateTime<FixedOffset>> {
+    let start = truncate(time, unit);
+    let end = match unit {
+        Unit::Second => start + Duration::seconds(1),
+        Unit::Minute => start + Duration::minutes(1),
+        Unit::Hour => start + Duration::hours(1),
+        Unit::Day => start + Duration::days(1),
+        Unit::Week => start + Duration::weeks(1),
+        Unit::Month => add_months(&start, 1)?,
+        Unit::Year => add_months(&start, 12)?,
+    };
+    Ok(if *time - start < end - *time { start } else { end })
+}
+
+/**
+The start of the bucket of the specified size that the time falls into. Buckets are counted from
+midnight January 1 1970 in the time's own UTC offset, so e.g. buckets of one day start at midnight.
+*/
+pub fn bucket(time: &DateTime<FixedOffset>, size: &Duration) -> CrushResult<DateTime<FixedOffset>> {
+    let size = match size.num_nanoseconds() {
+        Some(n) if n > 0 => n as i128,
+        _ => return argument_error_legacy("Bucket size must be a positive duration"),
+    };
+    let since_epoch = time.naive_local() - epoch();
+    let nanos = match since_epoch.num_nanoseconds() {
+        Some(n) => n as i128,
+        None => return argument_error_legacy("Time out of range"),
+    };
+    let start = nanos - nanos.rem_euclid(size);
+    Ok(with_local(time, epoch() + Duration::nanoseconds(start as i64)))
+}
+
+const NANOS_IN_SECOND: i128 = 1_000_000_000;
+const NANOS_IN_MINUTE: i128 = NANOS_IN_SECOND * 60;
+const NANOS_IN_HOUR: i128 = NANOS_IN_MINUTE * 60;
+const NANOS_IN_DAY: i128 = NANOS_IN_HOUR * 24;
+
+fn unit_nanos(unit: &str) -> Option<i128> {
+    Some(match unit {
+        "ns" | "nanosecond" | "nanoseconds" => 1,
+        "us" | "µs" | "microsecond" | "microseconds" => 1_000,
+        "ms" | "millisecond" | "milliseconds" => 1_000_000,
+        "s" | "sec" | "secs" | "second" | "seconds" => NANOS_IN_SECOND,
+        "m" | "min" | "mins" | "minute" | "minutes" => NANOS_IN_MINUTE,
+        "h" | "hr" | "hrs" | "hour" | "hours" => NANOS_IN_HOUR,
+        "d" | "day" | "days" => NANOS_IN_DAY,
+        "w" | "week" | "weeks" => NANOS_IN_DAY * 7,
+        "y" | "yr" | "yrs" | "year" | "years" => NANOS_IN_DAY * 365,
+        _ => return None,
+    })
+}
+
+/** A decimal number like 1.25, multiplied by the number of nanoseconds in a unit. */
+fn scaled(number: &str, unit: i128) -> Option<i128> {
+    let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+    if whole.is_empty() && fraction.is_empty()
+        || !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit())
+        || fraction.len() > 9
+    {
+        return None;
+    }
+    let whole = if whole.is_empty() { 0 } else { whole.parse::<i128>().ok()? };
+    let fraction = if fraction.is_empty() {
+        0
+    } else {
+        fraction.parse::<i128>().ok()? * unit / 10i128.pow(fraction.len() as u32)
+    };
+    whole.checked_mul(unit)?.checked_add(fraction)
+}
+
+/** Durations like 1h30m, 2 days or 1.5 hours. */
+fn parse_human(s: &str) -> Option<i128> {
+    let mut total = 0i128;
+    let mut rest = s.trim();
+    if rest.is_empty() {
+        return None;
+    }
+    while !rest.is_empty() {
+        let number_len = rest.find(|c: char| !(c.is_ascii_digit() || c == '.'))?;
+        let (number, tail) = rest.split_at(number_len);
+        let tail = tail.trim_start();
+        let unit_len = tail.find(|c: char| !c.is_alphabetic()).unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+        total = total.checked_add(scaled(number, unit_nanos(unit)?)?)?;
+        rest = tail.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+    }
+    Some(total)
+}
+
+/**
+ISO 8601 durations like PT1H30M or P2DT12H. Years are 365 days and months are 30 days, since
+durations in crush have a fixed length.
+*/
+fn parse_iso(s: &str) -> Option<i128> {
+    let rest = s.strip_prefix('P')?;
+    let (date, time) = match rest.split_once('T') {
+        Some((date, time)) if !time.is_empty() => (date, Some(time)),
+        Some(_) => return None,
+        None => (rest, None),
+    };
+    if date.is_empty() && time.is_none() {
+        return None;
+    }
+    let mut total = 0i128;
+    for (part, units) in [
+        (date, &[('Y', NANOS_IN_DAY * 365), ('M', NANOS_IN_DAY * 30), ('W', NANOS_IN_DAY * 7), ('D', NANOS_IN_DAY)][..]),
+        (time.unwrap_or(""), &[('H', NANOS_IN_HOUR), ('M', NANOS_IN_MINUTE), ('S', NANOS_IN_SECOND)][..]),
+    ].iter() {
+        let mut rest = *part;
+        let mut next_unit = 0;
+        while !rest.is_empty() {
+            let idx = rest.find(|c: char| c.is_ascii_alphabetic())?;
+            let designator = rest[idx..].chars().next()?;
+            let pos = next_unit + units[next_unit..].iter().position(|(u, _)| *u == designator)?;
+            total = total.checked_add(scaled(&rest[..idx], units[pos].1)?)?;
+            next_unit = pos + 1;
+            rest = &rest[idx + 1..];
+        }
+    }
+    Some(total)
+}
+
+/** Durations in the default display format, like 1:30, 2:03:04 or 1d2:03:04. */
+fn parse_clock(s: &str) -> Option<i128> {
+    let (days, clock) = match s.split_once('d') {
+        Some((days, clock)) => (scaled(days, NANOS_IN_DAY)?, clock),
+        None => (0, s),
+    };
+    let parts = clock.split(':').collect::<Vec<_>>();
+    let (hours, minutes, seconds) = match parts.len() {
+        1 if days != 0 => return None,
+        2 => ("0", parts[0], parts[1]),
+        3 => (parts[0], parts[1], parts[2]),
+        _ => return None,
+    };
+    days.checked_add(scaled(hours, NANOS_IN_HOUR)?)?
+        .checked_add(scaled(minutes, NANOS_IN_MINUTE)?)?
+        .checked_add(scaled(seconds, NANOS_IN_SECOND)?)
+}
+
+/**
+Parse a duration. Accepts a number of seconds like 90 or 1.5, human readable durations like
+1h30m, 2 days or 1 hour, 30 minutes, ISO 8601 durations like PT1H30M and durations in the clock
+format used when showing durations, like 1:30:00. A leading minus sign makes the duration negative.
+*/
+pub fn parse_duration(s: &str) -> CrushResult<Duration> {
+    let trimmed = s.trim();
+    let (sign, unsigned) = match trimmed.strip_prefix('-') {
+        Some(rest) => (-1, rest.trim_start()),
+        None => (1, trimmed),
+    };
+    let nanos = scaled(unsigned, NANOS_IN_SECOND)
+        .or_else(|| parse_iso(unsigned))
+        .or_else(|| parse_clock(unsigned))
+        .or_else(|| parse_human(unsigned));
+    let nanos = match nanos {
+        Some(nanos) => sign * nanos,
+        None => return argument_error_legacy(format!("Invalid duration {}", s)),
+    };
+    // Durations must be able to report their length in milliseconds, or showing them panics.
+    let res = if (nanos / 1_000_000).abs() <= i64::MAX as i128 {
+        Duration::seconds((nanos / NANOS_IN_SECOND) as i64)
+            .checked_add(&Duration::nanoseconds((nanos % NANOS_IN_SECOND) as i64))
+    } else {
+        None
+    };
+    match res {
+        Some(d) => Ok(d),
+        None => argument_error_legacy(format!("Duration {} is too long", s)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(s: &str) -> DateTime<FixedOffset> {
+        parse_time(s).unwrap()
+    }
+
+    #[test]
+    fn check_zone_parse() {
+        assert_eq!(Zone::parse("UTC").unwrap(), Zone::Fixed(Utc.fix()));
+        assert_eq!(Zone::parse("+02:00").unwrap(), Zone::Fixed(FixedOffset::east_opt(7200).unwrap()));
+        assert_eq!(Zone::parse("-0530").unwrap(), Zone::Fixed(FixedOffset::west_opt(19800).unwrap()));
+        assert_eq!(Zone::parse("Europe/Stockholm").unwrap(), Zone::Named(Tz::Europe__Stockholm));
+        assert!(Zone::parse("Mars/Olympus").is_err());
+        assert!(Zone::parse("+2:00").is_err());
+    }
+
+    #[test]
+    fn check_convert() {
+        let t = time("2020-06-01T12:00:00Z");
+        let summer = Zone::parse("Europe/Stockholm").unwrap().convert(&t);
+        assert_eq!(summer.to_rfc3339(), "2020-06-01T14:00:00+02:00");
+        assert_eq!(summer, t);
+        let winter = Zone::parse("Europe/Stockholm").unwrap().convert(&time("2020-01-01T12:00:00Z"));
+        assert_eq!(winter.to_rfc3339(), "2020-01-01T13:00:00+01:00");
+        assert_eq!(Zone::parse("-05:00").unwrap().convert(&t).to_rfc3339(), "2020-06-01T07:00:00-05:00");
+    }
+
+    #[test]
+    fn check_add_months() {
+        let t = time("2020-01-31T10:00:00+01:00");
+        assert_eq!(add_months(&t, 1).unwrap().to_rfc3339(), "2020-02-29T10:00:00+01:00");
+        assert_eq!(add_months(&t, 13).unwrap().to_rfc3339(), "2021-02-28T10:00:00+01:00");
+        assert_eq!(add_months(&t, -2).unwrap().to_rfc3339(), "2019-11-30T10:00:00+01:00");
+        assert_eq!(add_months(&t, 12 * 4).unwrap().to_rfc3339(), "2024-01-31T10:00:00+01:00");
+    }
+
+    #[test]
+    fn check_truncate_and_round() {
+        let t = time("2020-06-03T14:35:10+02:00");
+        assert_eq!(truncate(&t, Unit::Hour).to_rfc3339(), "2020-06-03T14:00:00+02:00");
+        assert_eq!(truncate(&t, Unit::Day).to_rfc3339(), "2020-06-03T00:00:00+02:00");
+        assert_eq!(truncate(&t, Unit::Week).to_rfc3339(), "2020-06-01T00:00:00+02:00");
+        assert_eq!(truncate(&t, Unit::Month).to_rfc3339(), "2020-06-01T00:00:00+02:00");
+        assert_eq!(truncate(&t, Unit::Year).to_rfc3339(), "2020-01-01T00:00:00+02:00");
+        assert_eq!(round(&t, Unit::Hour).unwrap().to_rfc3339(), "2020-06-03T15:00:00+02:00");
+        assert_eq!(round(&t, Unit::Day).unwrap().to_rfc3339(), "2020-06-04T00:00:00+02:00");
+        assert_eq!(round(&t, Unit::Month).unwrap().to_rfc3339(), "2020-06-01T00:00:00+02:00");
+    }
+
+    #[test]
+    fn check_bucket() {
+        let t = time("2020-06-03T14:35:10+02:00");
+        assert_eq!(bucket(&t, &Duration::minutes(15)).unwrap().to_rfc3339(), "2020-06-03T14:30:00+02:00");
+        assert_eq!(bucket(&t, &Duration::days(1)).unwrap().to_rfc3339(), "2020-06-03T00:00:00+02:00");
+        let early = time("1960-01-01T00:00:01Z");
+        assert_eq!(bucket(&early, &Duration::hours(1)).unwrap().to_rfc3339(), "1960-01-01T00:00:00+00:00");
+        assert!(bucket(&t, &Duration::zero()).is_err());
+    }
+
+    #[test]
+    fn check_parse_duration() {
+        let ok = |s: &str| parse_duration(s).unwrap();
+        assert_eq!(ok("90"), Duration::seconds(90));
+        assert_eq!(ok("1.5"), Duration::milliseconds(1500));
+        assert_eq!(ok("1h30m"), Duration::minutes(90));
+        assert_eq!(ok("2 days"), Duration::days(2));
+        assert_eq!(ok("1 hour, 30 minutes"), Duration::minutes(90));
+        assert_eq!(ok("1.5h"), Duration::minutes(90));
+        assert_eq!(ok("250ms"), Duration::milliseconds(250));
+        assert_eq!(ok("-5s"), Duration::seconds(-5));
+        assert_eq!(ok("PT5M"), Duration::minutes(5));
+        assert_eq!(ok("P2DT12H"), Duration::hours(60));
+        assert_eq!(ok("PT0.5S"), Duration::milliseconds(500));
+        assert_eq!(ok("P1W"), Duration::weeks(1));
+        assert_eq!(ok("1:30"), Duration::seconds(90));
+        assert_eq!(ok("2:03:04"), Duration::seconds(2 * 3600 + 3 * 60 + 4));
+        assert_eq!(ok("1d2:03:04"), Duration::seconds(86400 + 2 * 3600 + 3 * 60 + 4));
+        let longest = ok("9223372036854775.807");
+        assert_eq!(longest.num_milliseconds(), i64::MAX);
+        assert_eq!(duration_format(&longest), "292471208y247d7:12:55");
+        assert_eq!(ok("-9223372036854775.807").num_milliseconds(), -i64::MAX);
+        assert!(parse_duration("9223372036854775.808").is_err());
+        assert!(parse_duration("9223372036854775.9").is_err());
+        assert!(parse_duration("-9223372036854775.9").is_err());
+        assert!(parse_duration("1000000000000000000 years").is_err());
+        for bad in &["", "h", "1 fortnight", "P", "PT", "P1H", "PT1M1H", "1:2:3:4", "1..5", "P1DT"] {
+            assert!(parse_duration(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn check_localize() {
+        let naive = NaiveDateTime::parse_from_str("2020-06-01 12:00", "%Y-%m-%d %H:%M").unwrap();
+        let zone = Zone::parse("America/New_York").unwrap();
+        assert_eq!(zone.localize(&naive).unwrap().to_rfc3339(), "2020-06-01T12:00:00-04:00");
+        let missing = NaiveDateTime::parse_from_str("2020-03-08 02:30", "%Y-%m-%d %H:%M").unwrap();
+        assert!(zone.localize(&missing).is_err());
+    }
+}
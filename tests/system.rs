@@ -16,7 +16,7 @@ fn run_all_tests() {
             .to_string();
         if name.ends_with(".crush") {
             let output = Command::new("./target/debug/crush")
-                .args(&[name.as_str()])
+                .args(&["--format", "pretty", name.as_str()])
                 .output()
                 .expect("failed to execute process");
             let output_name = name.clone() + ".output";